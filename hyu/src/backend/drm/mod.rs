@@ -1,7 +1,7 @@
 use crate::{
-	Config, Point, Result,
+	Config, Point, Result, backend,
 	drm::{self, HasProps as _, Object as _},
-	elp, gbm, state, wl,
+	elp, gbm, renderer, state, wl,
 };
 
 use color_eyre::eyre::OptionExt as _;
@@ -38,9 +38,6 @@ pub struct Screen {
 
 	state: ScreenState,
 
-	pub timer_tx: std::sync::Arc<nix::sys::timerfd::TimerFd>,
-	timer_rx: Option<elp::timer_fd::Source>,
-
 	last_refresh: Option<std::time::Duration>,
 }
 
@@ -225,8 +222,6 @@ impl Screen {
 			},
 		};
 
-		let (timer_tx, timer_rx) = elp::timer_fd::create()?;
-
 		Ok(Self {
 			connector,
 			mode,
@@ -235,8 +230,6 @@ impl Screen {
 			plane,
 			props,
 			state: ScreenState::Idle,
-			timer_tx,
-			timer_rx: Some(timer_rx),
 			buffers: asdf,
 			last_refresh: None,
 		})
@@ -380,171 +373,224 @@ pub fn initialize_state(config: &Config) -> Result<State> {
 	Ok(state)
 }
 
-pub fn attach(
-	event_loop: &mut elp::EventLoop<state::State>,
-	state: &mut state::State,
-) -> Result<()> {
-	event_loop.on(
-		elp::drm::create(state.hw.drm.device.get_fd()),
-		|msg, state, _| {
-			match msg {
-				elp::drm::Message::PageFlip {
-					tv_sec,
-					tv_usec,
-					sequence,
-					..
-				} => {
-					let ScreenState::WaitingForPageFlip { did_direct_scanout } =
-						std::mem::replace(&mut state.hw.drm.screen.state, ScreenState::Idle)
+impl State {
+	/// Renders the next frame, either by scanning out the only window's buffer directly or by
+	/// compositing with vulkan.
+	fn render(&mut self, compositor: &mut state::CompositorState) -> Result<()> {
+		let screen = &mut self.screen;
+
+		if let ScreenState::WaitingForPageFlip { .. } = &screen.state {
+			panic!();
+		}
+
+		let window = match &compositor.workspace().windows[..] {
+			[window] => Some(**window),
+			_ => None,
+		};
+
+		// a window destroyed earlier in this batch stays listed until the changes are applied, so
+		// anything that's gone already just means compositing this frame
+		if let Some((fd, toplevel)) = window
+			&& let Some(client) = compositor.clients.get_mut(&fd)
+			&& let Ok(xdg_toplevel) = client.get_object(toplevel)
+			&& let Ok(xdg_surface) = client.get_object(xdg_toplevel.surface)
+			&& let Ok(mut wl_surface) = client.get_object_mut(xdg_surface.surface)
+		{
+			let wl_surface = &mut *wl_surface;
+
+			if wl_surface.children.len() == 0 {
+				if let wl::SurfaceRenderTexture::AttachedDmabuf(attached_buffer) =
+					&wl_surface.render_texture
+					&& let Ok(mut wl_buffer) = client.get_object_mut(attached_buffer.wl_buffer_id)
+				{
+					let wl::BufferBackingStorage::Dmabuf(dmabuf_backing_storage) =
+						&mut wl_buffer.backing_storage
 					else {
 						panic!();
 					};
 
-					state.hw.drm.screen.buffers.swap(0, 1);
+					let mode_size = Point(screen.mode.hdisplay as _, screen.mode.vdisplay as _);
 
-					let refresh_time = std::time::Duration::from_micros(
-						tv_sec as u64 * 1_000_000 + tv_usec as u64,
-					);
+					if dmabuf_backing_storage.size == mode_size {
+						if dmabuf_backing_storage.gbm_buffer_object.is_none() {
+							dmabuf_backing_storage.gbm_buffer_object = Some(
+								self.gbm_device
+									.import_dmabuf(&dmabuf_backing_storage.attributes)
+									.ok_or_eyre("failed to import dmabuf as bo")?,
+							);
+						}
 
-					let one_display_refresh_cycle = std::time::Duration::from_micros(
-						1_000_000 / state.hw.drm.screen.mode.vrefresh as u64,
-					);
+						let Some(gbm_buffer_object) = &dmabuf_backing_storage.gbm_buffer_object
+						else {
+							panic!();
+						};
 
-					if let Some(last_refresh) = state.hw.drm.screen.last_refresh {
-						let time_since_last_refresh = refresh_time.saturating_sub(last_refresh);
+						screen.render(
+							&self.device,
+							&mut self.context,
+							false,
+							-1,
+							&gbm_buffer_object,
+						)?;
+
+						// releasing the old buffer looks it up again
+						drop(wl_buffer);
+
+						if let Some(currently_renderer_buffer) = std::mem::replace(
+							&mut wl_surface.currently_rendered_buffer,
+							Some(attached_buffer.clone()),
+						) {
+							currently_renderer_buffer.release(client)?;
+						}
 
-						let diff_from_expected_refresh_time =
-							time_since_last_refresh.saturating_sub(one_display_refresh_cycle);
+						screen.state = ScreenState::WaitingForPageFlip {
+							did_direct_scanout: true,
+						};
 
-						if diff_from_expected_refresh_time > std::time::Duration::from_micros(500) {
-							eprintln!("missed frame by {diff_from_expected_refresh_time:?}");
-						}
+						return Ok(());
 					}
+				}
+			}
+		}
 
-					state.hw.drm.screen.last_refresh = Some(refresh_time);
+		let (bo, image, _, framebuffer, command_buffer) = screen.buffers.first().unwrap();
 
-					let mut wp_presentation_flags = 0x1 | 0x2 | 0x4;
+		self.vulkan
+			.render(*image, *framebuffer, *command_buffer, |vulkan| {
+				compositor.render(vulkan)
+			})?;
 
-					if did_direct_scanout {
-						wp_presentation_flags |= 0x8;
-					}
+		screen.render(
+			&self.device,
+			&mut self.context,
+			false,
+			self.vulkan.semaphore_fd.unwrap(),
+			bo,
+		)?;
 
-					state.compositor.after_render(
-						refresh_time,
-						one_display_refresh_cycle,
-						sequence,
-						wp_presentation_flags,
-					)?;
-
-					let next_render = refresh_time + one_display_refresh_cycle
-						- std::time::Duration::from_micros(1_000);
-
-					state.hw.drm.screen.timer_tx.set(
-						nix::sys::timerfd::Expiration::OneShot(
-							nix::sys::time::TimeSpec::from_duration(next_render),
-						),
-						nix::sys::timerfd::TimerSetTimeFlags::TFD_TIMER_ABSTIME,
-					)?;
-				}
-			}
+		screen.state = ScreenState::WaitingForPageFlip {
+			did_direct_scanout: false,
+		};
 
-			Ok(())
-		},
-	)?;
+		Ok(())
+	}
+
+	fn page_flip(
+		&mut self,
+		compositor: &mut state::CompositorState,
+		event_loop: &mut elp::EventLoop<state::State>,
+		refresh_time: std::time::Duration,
+		sequence: u32,
+	) -> Result<()> {
+		let ScreenState::WaitingForPageFlip { did_direct_scanout } =
+			std::mem::replace(&mut self.screen.state, ScreenState::Idle)
+		else {
+			panic!();
+		};
+
+		self.screen.buffers.swap(0, 1);
 
-	event_loop.on(
-		std::mem::take(&mut state.hw.drm.screen.timer_rx).unwrap(),
-		|_, state, _| {
-			let screen = &mut state.hw.drm.screen;
+		let one_display_refresh_cycle =
+			std::time::Duration::from_micros(1_000_000 / self.screen.mode.vrefresh as u64);
 
-			if let ScreenState::WaitingForPageFlip { .. } = &screen.state {
-				panic!();
+		if let Some(last_refresh) = self.screen.last_refresh {
+			let time_since_last_refresh = refresh_time.saturating_sub(last_refresh);
+
+			let diff_from_expected_refresh_time =
+				time_since_last_refresh.saturating_sub(one_display_refresh_cycle);
+
+			if diff_from_expected_refresh_time > std::time::Duration::from_micros(500) {
+				eprintln!("missed frame by {diff_from_expected_refresh_time:?}");
 			}
+		}
 
-			if state.compositor.windows.len() == 1 {
-				let window = state.compositor.windows.first().unwrap();
-				let client = state.compositor.clients.get_mut(&window.0).unwrap();
-
-				let xdg_toplevel = client.get_object(window.1)?;
-				let xdg_surface = client.get_object(xdg_toplevel.surface)?;
-				let wl_surface = client.get_object_mut(xdg_surface.surface)?;
-
-				if wl_surface.children.len() == 0 {
-					if let wl::SurfaceRenderTexture::AttachedDmabuf(attached_buffer) =
-						&wl_surface.render_texture
-					{
-						let wl_buffer = client.get_object_mut(attached_buffer.wl_buffer_id)?;
-						let wl::BufferBackingStorage::Dmabuf(dmabuf_backing_storage) =
-							&mut wl_buffer.backing_storage
-						else {
-							panic!();
-						};
+		self.screen.last_refresh = Some(refresh_time);
 
-						if dmabuf_backing_storage.size == Point(2560, 1440) {
-							if dmabuf_backing_storage.gbm_buffer_object.is_none() {
-								dmabuf_backing_storage.gbm_buffer_object = Some(
-									state
-										.hw
-										.drm
-										.gbm_device
-										.import_dmabuf(&dmabuf_backing_storage.attributes)
-										.ok_or_eyre("failed to import dmabuf as bo")?,
-								);
-							}
-
-							let Some(gbm_buffer_object) = &dmabuf_backing_storage.gbm_buffer_object
-							else {
-								panic!();
-							};
-
-							screen.render(
-								&state.hw.drm.device,
-								&mut state.hw.drm.context,
-								false,
-								-1,
-								&gbm_buffer_object,
-							)?;
-
-							if let Some(currently_renderer_buffer) = std::mem::replace(
-								&mut wl_surface.currently_rendered_buffer,
-								Some(attached_buffer.clone()),
-							) {
-								currently_renderer_buffer.release(client)?;
-							}
-
-							screen.state = ScreenState::WaitingForPageFlip {
-								did_direct_scanout: true,
-							};
-
-							return Ok(());
-						}
-					}
+		let mut wp_presentation_flags = 0x1 | 0x2 | 0x4;
+
+		if did_direct_scanout {
+			wp_presentation_flags |= 0x8;
+		}
+
+		compositor.after_render(
+			refresh_time,
+			one_display_refresh_cycle,
+			sequence,
+			wp_presentation_flags,
+		)?;
+
+		let next_render =
+			refresh_time + one_display_refresh_cycle - std::time::Duration::from_micros(1_000);
+
+		event_loop.timer_after(
+			next_render.saturating_sub(backend::now()?),
+			|state, runtime| {
+				state
+					.hw
+					.handle(backend::Event::Render, &mut state.compositor, runtime)
+			},
+		)?;
+
+		Ok(())
+	}
+}
+
+impl backend::Backend for State {
+	fn outputs(&self) -> Vec<wl::OutputInfo> {
+		let mode = &self.screen.mode;
+
+		vec![wl::OutputInfo {
+			position: Point(0, 0),
+			size: Point(mode.hdisplay as _, mode.vdisplay as _),
+			refresh: mode.vrefresh * 1000,
+			physical_size: Point(600, 340),
+			make: String::from("AUS"),
+			model: String::from("ROG XG27AQM"),
+		}]
+	}
+
+	fn renderer(&mut self) -> &mut dyn renderer::Renderer {
+		&mut self.vulkan
+	}
+
+	fn attach(&mut self, event_loop: &mut elp::EventLoop<state::State>) -> Result<()> {
+		event_loop.on(
+			elp::drm::create(self.device.get_fd()),
+			|msg, state, runtime| match msg {
+				elp::drm::Message::PageFlip {
+					tv_sec,
+					tv_usec,
+					sequence,
+					..
+				} => {
+					let time = std::time::Duration::from_micros(
+						tv_sec as u64 * 1_000_000 + tv_usec as u64,
+					);
+
+					state.hw.handle(
+						backend::Event::FrameDone { time, sequence },
+						&mut state.compositor,
+						runtime,
+					)
 				}
-			}
+			},
+		)?;
+
+		backend::input::attach(event_loop, backend::input::initialize_state()?)
+	}
 
-			let (bo, image, _, framebuffer, command_buffer) = screen.buffers.first().unwrap();
-
-			state
-				.hw
-				.drm
-				.vulkan
-				.render(*image, *framebuffer, *command_buffer, |vulkan| {
-					state.compositor.render(vulkan)
-				})?;
-
-			screen.render(
-				&state.hw.drm.device,
-				&mut state.hw.drm.context,
-				false,
-				state.hw.drm.vulkan.semaphore_fd.unwrap(),
-				bo,
-			)?;
-
-			screen.state = ScreenState::WaitingForPageFlip {
-				did_direct_scanout: false,
-			};
-
-			Ok(())
-		},
-	)
+	fn handle(
+		&mut self,
+		event: backend::Event,
+		compositor: &mut state::CompositorState,
+		event_loop: &mut elp::EventLoop<state::State>,
+	) -> Result<()> {
+		match event {
+			backend::Event::Render => self.render(compositor),
+			backend::Event::FrameDone { time, sequence } => {
+				self.page_flip(compositor, event_loop, time, sequence)
+			}
+			backend::Event::Host(_) => Ok(()),
+		}
+	}
 }
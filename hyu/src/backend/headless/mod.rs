@@ -0,0 +1,70 @@
+use crate::{Config, Result, backend, elp, renderer, state, wl};
+
+pub struct State {
+	pub outputs: Vec<wl::OutputInfo>,
+	pub renderer: renderer::cpu::Renderer,
+	sequence: u32,
+}
+
+impl State {
+	fn refresh_cycle(&self) -> std::time::Duration {
+		// the first output drives frame callbacks for all of them
+		std::time::Duration::from_nanos(1_000_000_000_000 / self.outputs[0].refresh as u64)
+	}
+
+	/// There's nothing to wait for, so the frame is done as soon as it's rendered.
+	fn refresh(&mut self, compositor: &mut state::CompositorState) -> Result<()> {
+		let one_display_refresh_cycle = self.refresh_cycle();
+		let refresh_time = backend::now()?;
+
+		self.sequence = self.sequence.wrapping_add(1);
+
+		self.renderer.begin();
+		compositor.render(&mut self.renderer)?;
+
+		compositor.after_render(refresh_time, one_display_refresh_cycle, self.sequence, 0)
+	}
+}
+
+pub fn initialize_state(config: &Config) -> Result<State> {
+	let outputs = backend::virtual_outputs(config, "HEADLESS")?;
+
+	Ok(State {
+		renderer: backend::virtual_renderer(config, &outputs),
+		outputs,
+		sequence: 0,
+	})
+}
+
+impl backend::Backend for State {
+	fn outputs(&self) -> Vec<wl::OutputInfo> {
+		self.outputs.clone()
+	}
+
+	fn renderer(&mut self) -> &mut dyn renderer::Renderer {
+		&mut self.renderer
+	}
+
+	fn attach(&mut self, event_loop: &mut elp::EventLoop<state::State>) -> Result<()> {
+		// refreshes that were slept through are skipped, rather than caught up on
+		event_loop.timer_every(self.refresh_cycle(), |state, runtime| {
+			state
+				.hw
+				.handle(backend::Event::Render, &mut state.compositor, runtime)
+		})?;
+
+		Ok(())
+	}
+
+	fn handle(
+		&mut self,
+		event: backend::Event,
+		compositor: &mut state::CompositorState,
+		_event_loop: &mut elp::EventLoop<state::State>,
+	) -> Result<()> {
+		match event {
+			backend::Event::Render => self.refresh(compositor),
+			backend::Event::FrameDone { .. } | backend::Event::Host(_) => Ok(()),
+		}
+	}
+}
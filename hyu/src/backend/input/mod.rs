@@ -11,10 +11,7 @@ pub fn initialize_state() -> Result<State> {
 	Ok(State { x: 0.0, y: 0.0 })
 }
 
-pub fn attach(
-	event_loop: &mut elp::EventLoop<state::State>,
-	_state: &mut state::State,
-) -> Result<()> {
+pub fn attach(event_loop: &mut elp::EventLoop<state::State>, mut input: State) -> Result<()> {
 	let udev = udev::Instance::create().ok_or_eyre("failed to create udev instance")?;
 	let context = libinput::Context::create_from_udev(&udev)
 		.ok_or_eyre("failed to create libinput context")?;
@@ -22,7 +19,7 @@ pub fn attach(
 	let ret = context.assign();
 	assert!(ret != -1);
 
-	event_loop.on(elp::input::create(context), |msg, state, _| {
+	event_loop.on(elp::input::create(context), move |msg, state, _| {
 		let elp::input::Message::Event { event } = msg;
 
 		match event.get_type() {
@@ -40,24 +37,17 @@ pub fn attach(
 					panic!();
 				};
 
-				state.hw.input.x += pointer.get_dx();
-				state.hw.input.y += pointer.get_dy();
+				input.x += pointer.get_dx();
+				input.y += pointer.get_dy();
 
-				state.hw.input.x = state
-					.hw
-					.input
-					.x
-					.clamp(0.0, (state.compositor.width - 1) as f64);
+				let (_, size) = state.compositor.outputs[0].area;
 
-				state.hw.input.y = state
-					.hw
-					.input
-					.y
-					.clamp(0.0, (state.compositor.height - 1) as f64);
+				input.x = input.x.clamp(0.0, (size.0 - 1) as f64);
+				input.y = input.y.clamp(0.0, (size.1 - 1) as f64);
 
 				state
 					.compositor
-					.on_cursor_move((state.hw.input.x as _, state.hw.input.y as _))?;
+					.on_cursor_move((input.x as _, input.y as _))?;
 			}
 			402 => {
 				let Some(pointer) = event.get_pointer_event() else {
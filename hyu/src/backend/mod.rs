@@ -1,3 +1,92 @@
 pub mod drm;
+pub mod headless;
 pub mod input;
+pub mod nested;
 pub mod tty;
+
+use color_eyre::eyre::bail;
+
+use crate::{Config, Point, Result, elp, renderer, state, wl};
+
+/// What the event sources of a backend report back to it.
+pub enum Event {
+	/// Time to draw the next frame.
+	Render,
+	/// The last frame has been on screen since `time`, on `CLOCK_MONOTONIC`.
+	FrameDone {
+		time: std::time::Duration,
+		sequence: u32,
+	},
+	/// An event from the compositor hyu is running nested in.
+	Host(wlm::client::RawEvent),
+}
+
+pub trait Backend {
+	fn outputs(&self) -> Vec<wl::OutputInfo>;
+
+	fn renderer(&mut self) -> &mut dyn renderer::Renderer;
+
+	/// Registers the backend's event sources, which feed input into the compositor and pass
+	/// everything else on to `handle`.
+	fn attach(&mut self, event_loop: &mut elp::EventLoop<state::State>) -> Result<()>;
+
+	/// Renders with `CompositorState::render` and reports finished frames with
+	/// `CompositorState::after_render`, scheduling the next frame along the way.
+	fn handle(
+		&mut self,
+		event: Event,
+		compositor: &mut state::CompositorState,
+		event_loop: &mut elp::EventLoop<state::State>,
+	) -> Result<()>;
+}
+
+/// Lays out `config.virtual_outputs` left to right.
+pub fn virtual_outputs(config: &Config, model: &str) -> Result<Vec<wl::OutputInfo>> {
+	let mut x = 0;
+	let mut outputs = Vec::new();
+
+	for (index, output) in config.virtual_outputs.iter().enumerate() {
+		if output.width == 0 || output.height == 0 || output.refresh == 0 {
+			bail!("virtual output {index} has a zero size or refresh rate");
+		}
+
+		outputs.push(wl::OutputInfo {
+			position: Point(x, 0),
+			size: Point(output.width as _, output.height as _),
+			refresh: output.refresh,
+			physical_size: Point(0, 0),
+			make: String::from("hyu"),
+			model: format!("{model}-{}", index + 1),
+		});
+
+		x += output.width as i32;
+	}
+
+	if outputs.is_empty() {
+		bail!("at least one virtual output is needed");
+	}
+
+	Ok(outputs)
+}
+
+/// A CPU renderer with one framebuffer spanning every output.
+pub fn virtual_renderer(config: &Config, outputs: &[wl::OutputInfo]) -> renderer::cpu::Renderer {
+	let width = outputs
+		.iter()
+		.map(|x| x.position.0 + x.size.0)
+		.max()
+		.unwrap();
+	let height = outputs
+		.iter()
+		.map(|x| x.position.1 + x.size.1)
+		.max()
+		.unwrap();
+
+	// dmabufs can only be mapped, and only if there's a device to allocate them from
+	renderer::cpu::create(width as _, height as _, config.card.exists())
+}
+
+pub fn now() -> Result<std::time::Duration> {
+	let time = nix::time::clock_gettime(nix::time::ClockId::CLOCK_MONOTONIC)?;
+	Ok(time.into())
+}
@@ -0,0 +1,388 @@
+use std::os::fd::AsRawFd as _;
+
+use color_eyre::eyre::bail;
+use wlm::protocol::{
+	wl_buffer, wl_callback, wl_keyboard, wl_pointer, wl_seat, wl_shm, wl_shm_pool, wl_surface,
+	xdg_surface, xdg_toplevel,
+};
+
+use crate::{Config, Point, Result, backend, elp, renderer, state, wl};
+
+struct Buffer {
+	id: u32,
+	ptr: std::ptr::NonNull<std::ffi::c_void>,
+	size: usize,
+	busy: bool,
+}
+
+impl Buffer {
+	fn pixels_mut(&mut self) -> &mut [u32] {
+		unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr() as *mut u32, self.size / 4) }
+	}
+}
+
+impl Drop for Buffer {
+	fn drop(&mut self) {
+		unsafe {
+			nix::sys::mman::munmap(self.ptr, self.size).unwrap();
+		}
+	}
+}
+
+struct Window {
+	output: wl::OutputInfo,
+	toplevel: wlm::client::Toplevel,
+	buffers: [Buffer; 2],
+	configured: bool,
+}
+
+pub struct State {
+	client: std::rc::Rc<std::cell::RefCell<wlm::client::Client>>,
+	windows: Vec<Window>,
+	renderer: renderer::cpu::Renderer,
+	frame_callback: Option<u32>,
+	pointer: Option<u32>,
+	keyboard: Option<u32>,
+	pointer_window: Option<usize>,
+	sequence: u32,
+}
+
+fn create_buffer(client: &mut wlm::client::Client, size: Point) -> Result<Buffer> {
+	let Point(width, height) = size;
+
+	let stride = width * 4;
+	let length = (stride * height) as usize;
+
+	let fd = nix::sys::memfd::memfd_create(c"hyu-nested", nix::sys::memfd::MFdFlags::MFD_CLOEXEC)?;
+	nix::unistd::ftruncate(&fd, length as _)?;
+
+	let ptr = unsafe {
+		nix::sys::mman::mmap(
+			None,
+			std::num::NonZeroUsize::new(length).unwrap(),
+			nix::sys::mman::ProtFlags::PROT_READ | nix::sys::mman::ProtFlags::PROT_WRITE,
+			nix::sys::mman::MapFlags::MAP_SHARED,
+			&fd,
+			0,
+		)?
+	};
+
+	let shm = client.global(wl_shm::INTERFACE, 1)?;
+
+	let pool = client.new_id(wl_shm_pool::INTERFACE);
+	client.send(
+		shm,
+		&wl_shm::Request::CreatePool {
+			id: pool,
+			fd: fd.as_raw_fd(),
+			size: length as _,
+		},
+	)?;
+
+	let id = client.new_id(wl_buffer::INTERFACE);
+	client.send(
+		pool,
+		&wl_shm_pool::Request::CreateBuffer {
+			id,
+			offset: 0,
+			width,
+			height,
+			stride,
+			format: wl_shm::format::XRGB8888,
+		},
+	)?;
+
+	client.send(pool, &wl_shm_pool::Request::Destroy)?;
+
+	Ok(Buffer {
+		id,
+		ptr,
+		size: length,
+		busy: false,
+	})
+}
+
+pub fn initialize_state(config: &Config) -> Result<State> {
+	let mut client = wlm::client::Client::connect_to_env()?;
+	let outputs = backend::virtual_outputs(config, "NESTED")?;
+
+	let mut windows = Vec::new();
+
+	for output in &outputs {
+		let toplevel = client.create_toplevel()?;
+
+		client.send(
+			toplevel.toplevel,
+			&xdg_toplevel::Request::SetTitle {
+				title: &format!("hyu - {}", output.model),
+			},
+		)?;
+		client.send(
+			toplevel.toplevel,
+			&xdg_toplevel::Request::SetAppId { app_id: "hyu" },
+		)?;
+
+		// virtual outputs don't change size
+		client.send(
+			toplevel.toplevel,
+			&xdg_toplevel::Request::SetMinSize {
+				width: output.size.0,
+				height: output.size.1,
+			},
+		)?;
+		client.send(
+			toplevel.toplevel,
+			&xdg_toplevel::Request::SetMaxSize {
+				width: output.size.0,
+				height: output.size.1,
+			},
+		)?;
+
+		let buffers = [
+			create_buffer(&mut client, output.size)?,
+			create_buffer(&mut client, output.size)?,
+		];
+
+		windows.push(Window {
+			output: output.clone(),
+			toplevel,
+			buffers,
+			configured: false,
+		});
+	}
+
+	// pointer and keyboard get created once the seat announces its capabilities
+	client.global(wl_seat::INTERFACE, 5)?;
+
+	Ok(State {
+		client: std::rc::Rc::new(std::cell::RefCell::new(client)),
+		windows,
+		renderer: backend::virtual_renderer(config, &outputs),
+		frame_callback: None,
+		pointer: None,
+		keyboard: None,
+		pointer_window: None,
+		sequence: 0,
+	})
+}
+
+impl State {
+	fn refresh_cycle(&self) -> std::time::Duration {
+		std::time::Duration::from_nanos(1_000_000_000_000 / self.windows[0].output.refresh as u64)
+	}
+
+	/// Renders a frame and commits it to every window. The first window asks for a frame callback,
+	/// which drives the next one.
+	fn present(&mut self, compositor: &mut state::CompositorState) -> Result<()> {
+		self.renderer.begin();
+		compositor.render(&mut self.renderer)?;
+
+		let mut client = self.client.borrow_mut();
+
+		for (index, window) in self.windows.iter_mut().enumerate() {
+			let surface = window.toplevel.surface;
+
+			// if the host still holds both buffers, it's fine to skip this frame on this window
+			if let Some(buffer) = window.buffers.iter_mut().find(|x| !x.busy) {
+				let Point(x, y) = window.output.position;
+				let Point(width, height) = window.output.size;
+
+				let width = width as usize;
+				let pixels = buffer.pixels_mut();
+
+				for row in 0..height as usize {
+					let start = (y as usize + row) * self.renderer.width + x as usize;
+
+					pixels[row * width..][..width]
+						.copy_from_slice(&self.renderer.framebuffer[start..][..width]);
+				}
+
+				buffer.busy = true;
+
+				client.send(
+					surface,
+					&wl_surface::Request::Attach {
+						buffer: Some(buffer.id),
+						x: 0,
+						y: 0,
+					},
+				)?;
+				client.send(
+					surface,
+					&wl_surface::Request::Damage {
+						x: 0,
+						y: 0,
+						width: i32::MAX,
+						height: i32::MAX,
+					},
+				)?;
+			}
+
+			if index == 0 {
+				let callback = client.new_id(wl_callback::INTERFACE);
+				client.send(surface, &wl_surface::Request::Frame { callback })?;
+
+				self.frame_callback = Some(callback);
+			}
+
+			client.send(surface, &wl_surface::Request::Commit)?;
+		}
+
+		Ok(())
+	}
+
+	fn on_cursor_move(
+		&self,
+		compositor: &mut state::CompositorState,
+		surface_x: wlm::Fixed,
+		surface_y: wlm::Fixed,
+	) -> Result<()> {
+		let Some(window) = self.pointer_window.map(|x| &self.windows[x]) else {
+			return Ok(());
+		};
+
+		let Point(x, y) = window.output.position;
+
+		compositor.on_cursor_move((x + surface_x.to_f64() as i32, y + surface_y.to_f64() as i32))
+	}
+
+	fn handle_host(
+		&mut self,
+		event: wlm::client::RawEvent,
+		compositor: &mut state::CompositorState,
+	) -> Result<()> {
+		if event.is::<xdg_surface::event::Configure>() {
+			let serial = event.decode::<xdg_surface::event::Configure>()?.serial;
+
+			self.client.borrow_mut().send(
+				event.object_id,
+				&xdg_surface::Request::AckConfigure { serial },
+			)?;
+
+			if let Some(window) = self
+				.windows
+				.iter_mut()
+				.find(|x| x.toplevel.xdg_surface == event.object_id)
+			{
+				window.configured = true;
+			}
+
+			if self.frame_callback.is_none() && self.windows.iter().all(|x| x.configured) {
+				self.present(compositor)?;
+			}
+		} else if event.is::<xdg_toplevel::event::Close>() {
+			bail!("the host compositor closed the window");
+		} else if event.is::<wl_callback::event::Done>() {
+			if self.frame_callback != Some(event.object_id) {
+				return Ok(());
+			}
+
+			self.frame_callback = None;
+			self.sequence = self.sequence.wrapping_add(1);
+
+			compositor.after_render(backend::now()?, self.refresh_cycle(), self.sequence, 0)?;
+			self.present(compositor)?;
+		} else if event.is::<wl_buffer::event::Release>() {
+			for buffer in self.windows.iter_mut().flat_map(|x| &mut x.buffers) {
+				if buffer.id == event.object_id {
+					buffer.busy = false;
+				}
+			}
+		} else if event.is::<wl_seat::event::Capabilities>() {
+			let capabilities = event.decode::<wl_seat::event::Capabilities>()?.capabilities;
+
+			let mut client = self.client.borrow_mut();
+
+			if capabilities & wl_seat::capability::POINTER != 0 && self.pointer.is_none() {
+				let id = client.new_id(wl_pointer::INTERFACE);
+				client.send(event.object_id, &wl_seat::Request::GetPointer { id })?;
+
+				self.pointer = Some(id);
+			}
+
+			if capabilities & wl_seat::capability::KEYBOARD != 0 && self.keyboard.is_none() {
+				let id = client.new_id(wl_keyboard::INTERFACE);
+				client.send(event.object_id, &wl_seat::Request::GetKeyboard { id })?;
+
+				self.keyboard = Some(id);
+			}
+		} else if event.is::<wl_pointer::event::Enter>() {
+			let enter = event.decode::<wl_pointer::event::Enter>()?;
+
+			self.pointer_window = self
+				.windows
+				.iter()
+				.position(|x| x.toplevel.surface == enter.surface);
+
+			// hyu draws its own cursor
+			self.client.borrow_mut().send(
+				event.object_id,
+				&wl_pointer::Request::SetCursor {
+					serial: enter.serial,
+					surface: None,
+					hotspot_x: 0,
+					hotspot_y: 0,
+				},
+			)?;
+
+			self.on_cursor_move(compositor, enter.surface_x, enter.surface_y)?;
+		} else if event.is::<wl_pointer::event::Leave>() {
+			self.pointer_window = None;
+		} else if event.is::<wl_pointer::event::Motion>() {
+			let motion = event.decode::<wl_pointer::event::Motion>()?;
+			self.on_cursor_move(compositor, motion.surface_x, motion.surface_y)?;
+		} else if event.is::<wl_pointer::event::Button>() {
+			let button = event.decode::<wl_pointer::event::Button>()?;
+			compositor.on_mouse_button(button.button, button.state)?;
+		} else if event.is::<wl_pointer::event::Axis>() {
+			let axis = event.decode::<wl_pointer::event::Axis>()?;
+			let value = axis.value.to_f64();
+
+			// most compositors scroll 10 units per wheel step
+			compositor.on_mouse_scroll(value, (value / 10.0) as _, axis.axis)?;
+		} else if event.is::<wl_keyboard::event::Keymap>() {
+			// hyu compiles its own keymap
+			let keymap = event.decode::<wl_keyboard::event::Keymap>()?;
+			nix::unistd::close(keymap.fd)?;
+		} else if event.is::<wl_keyboard::event::Key>() {
+			let key = event.decode::<wl_keyboard::event::Key>()?;
+			compositor.on_keyboard_button(key.key, key.state)?;
+		}
+
+		Ok(())
+	}
+}
+
+impl backend::Backend for State {
+	fn outputs(&self) -> Vec<wl::OutputInfo> {
+		self.windows.iter().map(|x| x.output.clone()).collect()
+	}
+
+	fn renderer(&mut self) -> &mut dyn renderer::Renderer {
+		&mut self.renderer
+	}
+
+	fn attach(&mut self, event_loop: &mut elp::EventLoop<state::State>) -> Result<()> {
+		event_loop.on(
+			elp::wl_client::create(self.client.clone()),
+			|event, state, runtime| {
+				state
+					.hw
+					.handle(backend::Event::Host(event), &mut state.compositor, runtime)
+			},
+		)
+	}
+
+	fn handle(
+		&mut self,
+		event: backend::Event,
+		compositor: &mut state::CompositorState,
+		_event_loop: &mut elp::EventLoop<state::State>,
+	) -> Result<()> {
+		match event {
+			// frames are driven by the host's frame callbacks
+			backend::Event::Host(event) => self.handle_host(event, compositor),
+			backend::Event::Render | backend::Event::FrameDone { .. } => Ok(()),
+		}
+	}
+}
@@ -1,74 +1,222 @@
-use crate::{Point, Result, state, wl};
+use crate::{ClientLimits, Connection, Point, Result, state, wl};
+
+/// Ids from here on are allocated by the server.
+pub const SERVER_ID_START: u32 = 0xff000000;
+
+/// Client memory that hyu holds on to, counted against one of the `ClientLimits`. It's given back
+/// once this is dropped.
+pub struct Reservation {
+	usage: std::rc::Rc<std::cell::Cell<usize>>,
+	limit: usize,
+	size: usize,
+	what: &'static str,
+}
+
+impl Reservation {
+	pub fn resize(&mut self, size: usize) -> Result<()> {
+		let others = self.usage.get() - self.size;
+
+		if others.saturating_add(size) > self.limit {
+			Err(wl::ProtocolError::no_memory(format!(
+				"{} limit of {} bytes exceeded",
+				self.what, self.limit
+			)))?;
+		}
+
+		self.usage.set(others + size);
+		self.size = size;
+
+		Ok(())
+	}
+}
+
+impl Drop for Reservation {
+	fn drop(&mut self) {
+		self.usage.set(self.usage.get() - self.size);
+	}
+}
 
 pub struct Client {
 	pub fd: std::os::fd::RawFd,
+	pub conn: std::rc::Rc<Connection>,
+	limits: &'static ClientLimits,
+	shm_usage: std::rc::Rc<std::cell::Cell<usize>>,
+	dmabuf_usage: std::rc::Rc<std::cell::Cell<usize>>,
 	client_store: crate::Store<1>,
+	server_store: crate::Store<SERVER_ID_START>,
 	pub start_position: Point,
-	pub received_fds: std::collections::VecDeque<std::os::fd::RawFd>,
-	pub to_send_fds: Vec<std::os::fd::RawFd>,
 	pub changes: Vec<state::Change>,
+	/// Version of the object the current request was sent to. Objects created while handling it
+	/// get the same one, like in libwayland.
+	pub request_version: u32,
+	started: std::time::Instant,
+	serial: u32,
 }
 
-impl<'object> Client {
-	pub fn new(fd: std::os::fd::RawFd, start_position: Point) -> Self {
+impl Client {
+	pub fn new(
+		conn: std::rc::Rc<Connection>,
+		start_position: Point,
+		limits: &'static ClientLimits,
+	) -> Self {
 		Self {
-			fd,
+			fd: conn.as_raw_fd(),
+			conn,
+			limits,
+			shm_usage: Default::default(),
+			dmabuf_usage: Default::default(),
 			client_store: Default::default(),
+			server_store: Default::default(),
 			start_position,
-			received_fds: Default::default(),
-			to_send_fds: Default::default(),
 			changes: Vec::new(),
+			request_version: 1,
+			started: std::time::Instant::now(),
+			serial: 0,
 		}
 	}
 
-	pub fn ensure_objects_capacity(&mut self) {
-		self.client_store.ensure_objects_capacity();
+	pub fn credentials(&self) -> &crate::Credentials {
+		&self.conn.credentials
+	}
+
+	/// Checks the limits that aren't enforced where the resources get allocated.
+	pub fn check_limits(&self, pending_fds: usize) -> Result<()> {
+		let objects = self.client_store.object_count() + self.server_store.object_count();
+
+		if objects > self.limits.max_objects {
+			Err(wl::ProtocolError::no_memory(format!(
+				"limit of {} objects exceeded",
+				self.limits.max_objects
+			)))?;
+		}
+
+		if pending_fds > self.limits.max_pending_fds {
+			Err(wl::ProtocolError::no_memory(format!(
+				"limit of {} pending fds exceeded",
+				self.limits.max_pending_fds
+			)))?;
+		}
+
+		Ok(())
+	}
+
+	pub fn reserve_shm(&self, size: usize) -> Result<Reservation> {
+		Self::reserve(&self.shm_usage, self.limits.max_shm_bytes, size, "shm")
 	}
 
-	pub fn new_object<T: Into<wl::Resource>>(&mut self, id: wl::Id<T>, object: T) -> &'object mut T
-	where
-		Result<&'object mut T>: From<&'object mut wl::Resource>,
-	{
+	pub fn reserve_dmabuf(&self, size: usize) -> Result<Reservation> {
+		Self::reserve(
+			&self.dmabuf_usage,
+			self.limits.max_dmabuf_bytes,
+			size,
+			"dmabuf",
+		)
+	}
+
+	fn reserve(
+		usage: &std::rc::Rc<std::cell::Cell<usize>>,
+		limit: usize,
+		size: usize,
+		what: &'static str,
+	) -> Result<Reservation> {
+		let mut reservation = Reservation {
+			usage: usage.clone(),
+			limit,
+			size: 0,
+			what,
+		};
+
+		reservation.resize(size)?;
+		Ok(reservation)
+	}
+
+	/// Adds an object with an id the client picked.
+	pub fn new_object<T: wl::ResourceType>(
+		&mut self,
+		id: wl::Id<T>,
+		object: T,
+	) -> Result<crate::ObjectMut<T>> {
+		if *id >= SERVER_ID_START {
+			Err(wl::ProtocolError::invalid_new_id(*id))?;
+		}
+
+		self.conn.set_version(*id, self.request_version);
 		self.client_store.new_object(id, object)
 	}
 
-	pub unsafe fn remove_object<T>(&mut self, id: wl::Id<T>) -> Result<()> {
-		unsafe {
+	/// Adds an object that the server announces to the client, like `wl_data_offer`.
+	pub fn new_server_object<T: wl::ResourceType>(
+		&mut self,
+		object: impl FnOnce(wl::Id<T>) -> T,
+	) -> Result<crate::ObjectMut<T>> {
+		let id = wl::Id::new(self.server_store.allocate_id());
+
+		self.conn.set_version(*id, self.request_version);
+		self.server_store.new_object(id, object(id))
+	}
+
+	pub fn remove_object<T: wl::ResourceType>(&mut self, id: wl::Id<T>) -> Result<()> {
+		if *id >= SERVER_ID_START {
+			self.server_store.remove_object(id)?;
+			self.server_store.delete_id(*id);
+		} else {
 			self.client_store.remove_object(id)?;
 		}
 
-		const DISPLAY_ID: wl::Id<wl::Display> = wl::Id::new(1);
+		self.conn.set_version(*id, 1);
+
+		self.conn
+			.send_event(1, wlm::protocol::wl_display::event::DeleteId { id: *id })
+	}
 
-		let display = self.get_object(DISPLAY_ID)?;
-		display.delete_id(id)
+	/// Lets the server reuse the ids the client has been told are deleted, see
+	/// `Store::acknowledge_deleted_ids`.
+	pub fn acknowledge_deleted_ids(&mut self) {
+		self.server_store.acknowledge_deleted_ids();
 	}
 
-	pub fn get_object<T>(&self, id: wl::Id<T>) -> Result<&'object T>
-	where
-		Result<&'object T>: From<&'object wl::Resource>,
-	{
+	pub fn get_object<T: wl::ResourceType>(&self, id: wl::Id<T>) -> Result<crate::ObjectRef<T>> {
+		if *id >= SERVER_ID_START {
+			return self.server_store.get_object(id);
+		}
+
 		self.client_store.get_object(id)
 	}
 
-	pub fn get_object_mut<T>(&self, id: wl::Id<T>) -> Result<&'object mut T>
-	where
-		Result<&'object mut T>: From<&'object mut wl::Resource>,
-	{
+	pub fn get_object_mut<T: wl::ResourceType>(
+		&self,
+		id: wl::Id<T>,
+	) -> Result<crate::ObjectMut<T>> {
+		if *id >= SERVER_ID_START {
+			return self.server_store.get_object_mut(id);
+		}
+
 		self.client_store.get_object_mut(id)
 	}
 
-	pub fn get_resource(&self, id: u32) -> Option<&'object wl::Resource> {
+	pub fn get_resource(&self, id: u32) -> Option<wl::Resource> {
+		if id >= SERVER_ID_START {
+			return self.server_store.get_resource(id);
+		}
+
 		self.client_store.get_resource(id)
 	}
 
-	pub fn get_resource_mut(&self, id: u32) -> Option<&'object mut wl::Resource> {
-		self.client_store.get_resource_mut(id)
+	pub fn objects_mut<T: wl::ResourceType>(&self) -> Result<Vec<crate::ObjectMut<T>>> {
+		let mut ret = self.client_store.objects_mut()?;
+		ret.extend(self.server_store.objects_mut()?);
+
+		Ok(ret)
 	}
 
-	pub fn objects_mut<T>(&self) -> Vec<&'object mut T>
-	where
-		Result<&'object mut T>: From<&'object mut wl::Resource>,
-	{
-		self.client_store.objects_mut()
+	pub fn get_time(&self) -> std::time::Duration {
+		self.started.elapsed()
+	}
+
+	pub fn new_serial(&mut self) -> u32 {
+		let ret = self.serial;
+		self.serial += 1;
+
+		ret
 	}
 }
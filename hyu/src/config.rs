@@ -1,11 +1,140 @@
 use crate::Result;
 use color_eyre::eyre::ContextCompat;
 
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+	#[default]
+	Drm,
+	Headless,
+	Nested,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LayoutKind {
+	MasterStack,
+	Monocle,
+	Grid,
+	Columns,
+	Dwindle,
+	Spiral,
+}
+
+/// Picks the clients that get traced, written as `fd:12`, `pid:1234` or the executable's name in
+/// `HYU_TRACE_CLIENTS`.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceFilter {
+	Fd(std::os::fd::RawFd),
+	Pid(i32),
+	Name(String),
+}
+
+impl TraceFilter {
+	pub fn matches(&self, fd: std::os::fd::RawFd, credentials: &crate::Credentials) -> bool {
+		match self {
+			Self::Fd(x) => *x == fd,
+			Self::Pid(x) => *x == credentials.pid.as_raw(),
+			Self::Name(x) => credentials.name() == Some(x.as_str()),
+		}
+	}
+}
+
+impl std::str::FromStr for TraceFilter {
+	type Err = color_eyre::Report;
+
+	fn from_str(s: &str) -> Result<Self> {
+		Ok(if let Some(fd) = s.strip_prefix("fd:") {
+			Self::Fd(fd.parse()?)
+		} else if let Some(pid) = s.strip_prefix("pid:") {
+			Self::Pid(pid.parse()?)
+		} else {
+			Self::Name(s.to_owned())
+		})
+	}
+}
+
+/// RGBA, not premultiplied.
+pub type Color = [u8; 4];
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[serde(default)]
+pub struct BorderColors {
+	pub focused: Color,
+	pub unfocused: Color,
+	/// Windows that asked for attention.
+	pub urgent: Color,
+}
+
+impl Default for BorderColors {
+	fn default() -> Self {
+		Self {
+			focused: [0x88, 0xc0, 0xd0, 0xff],
+			unfocused: [0x4c, 0x56, 0x6a, 0xff],
+			urgent: [0xbf, 0x61, 0x6a, 0xff],
+		}
+	}
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+/// An output of the headless or nested backend.
+pub struct VirtualOutput {
+	pub width: u16,
+	pub height: u16,
+	/// Refresh rate in mHz.
+	pub refresh: u32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+#[serde(default)]
+/// Going over any of these is a protocol error for the client.
+pub struct ClientLimits {
+	pub max_objects: usize,
+	/// Fds that were received, but not consumed by a request yet.
+	pub max_pending_fds: usize,
+	pub max_shm_bytes: usize,
+	pub max_dmabuf_bytes: usize,
+}
+
+impl Default for ClientLimits {
+	fn default() -> Self {
+		Self {
+			max_objects: 0x10000,
+			max_pending_fds: 1024,
+			max_shm_bytes: 1024 * 1024 * 1024,
+			max_dmabuf_bytes: 1024 * 1024 * 1024,
+		}
+	}
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
 #[serde(default)]
 pub struct Config {
 	pub keymap: String,
 	pub card: std::path::PathBuf,
+	pub trace: bool,
+	/// Clients that get traced, every one if it's empty.
+	pub trace_clients: Vec<TraceFilter>,
+	pub backend: BackendKind,
+	/// Name of the socket to listen on, the first free `wayland-N` if unset.
+	pub socket: Option<String>,
+	pub virtual_outputs: Vec<VirtualOutput>,
+	/// How many bytes of events a client may leave unread before it gets disconnected.
+	pub client_buffer_limit: usize,
+	pub client_limits: ClientLimits,
+	/// Layouts that every workspace cycles through, starting with the first one. Only the first
+	/// output is tiled.
+	pub layouts: Vec<LayoutKind>,
+	/// Width of the master area in the master-stack layout, as a fraction of the output.
+	pub master_ratio: f32,
+	pub master_count: usize,
+	/// Space between tiled windows.
+	pub inner_gap: i32,
+	/// Space between tiled windows and the edges of the output.
+	pub outer_gap: i32,
+	pub border_width: i32,
+	pub border_colors: BorderColors,
 }
 
 impl Default for Config {
@@ -13,6 +142,31 @@ impl Default for Config {
 		Self {
 			keymap: String::from("us"),
 			card: std::path::PathBuf::from("/dev/dri/card0"),
+			trace: false,
+			trace_clients: Vec::new(),
+			backend: BackendKind::Drm,
+			socket: None,
+			virtual_outputs: vec![VirtualOutput {
+				width: 1920,
+				height: 1080,
+				refresh: 60_000,
+			}],
+			client_buffer_limit: 4 * 1024 * 1024,
+			client_limits: Default::default(),
+			layouts: vec![
+				LayoutKind::MasterStack,
+				LayoutKind::Monocle,
+				LayoutKind::Grid,
+				LayoutKind::Columns,
+				LayoutKind::Dwindle,
+				LayoutKind::Spiral,
+			],
+			master_ratio: 0.5,
+			master_count: 1,
+			inner_gap: 0,
+			outer_gap: 0,
+			border_width: 0,
+			border_colors: Default::default(),
 		}
 	}
 }
@@ -31,3 +185,41 @@ impl Config {
 		Ok(Box::leak(Box::new(config)))
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn trace_filter_from_str() {
+		assert_eq!("fd:12".parse::<TraceFilter>().unwrap(), TraceFilter::Fd(12));
+		assert_eq!(
+			"pid:1234".parse::<TraceFilter>().unwrap(),
+			TraceFilter::Pid(1234)
+		);
+		assert_eq!(
+			"foot".parse::<TraceFilter>().unwrap(),
+			TraceFilter::Name(String::from("foot"))
+		);
+
+		assert!("fd:foot".parse::<TraceFilter>().is_err());
+		assert!("pid:".parse::<TraceFilter>().is_err());
+	}
+
+	#[test]
+	fn trace_filter_matches() {
+		let credentials = crate::Credentials {
+			pid: nix::unistd::Pid::from_raw(1234),
+			uid: nix::unistd::Uid::from_raw(1000),
+			gid: nix::unistd::Gid::from_raw(1000),
+			executable: Some(std::path::PathBuf::from("/usr/bin/foot")),
+		};
+
+		assert!(TraceFilter::Fd(12).matches(12, &credentials));
+		assert!(!TraceFilter::Fd(13).matches(12, &credentials));
+		assert!(TraceFilter::Pid(1234).matches(12, &credentials));
+		assert!(!TraceFilter::Pid(1).matches(12, &credentials));
+		assert!(TraceFilter::Name(String::from("foot")).matches(12, &credentials));
+		assert!(!TraceFilter::Name(String::from("bin")).matches(12, &credentials));
+	}
+}
@@ -1,98 +1,312 @@
-use std::os::fd::AsRawFd as _;
+use std::os::fd::{AsFd as _, AsRawFd as _};
 
 use crate::Result;
 
+/// Who's on the other end of a connection, as reported by the kernel when it was accepted.
+pub struct Credentials {
+	pub pid: nix::unistd::Pid,
+	pub uid: nix::unistd::Uid,
+	pub gid: nix::unistd::Gid,
+	/// `None` if the process is already gone, or belongs to someone else.
+	pub executable: Option<std::path::PathBuf>,
+}
+
+impl Credentials {
+	pub fn from_stream(stream: &std::os::unix::net::UnixStream) -> Result<Self> {
+		let credentials =
+			nix::sys::socket::getsockopt(stream, nix::sys::socket::sockopt::PeerCredentials)?;
+
+		let pid = nix::unistd::Pid::from_raw(credentials.pid());
+
+		Ok(Self {
+			pid,
+			uid: nix::unistd::Uid::from_raw(credentials.uid()),
+			gid: nix::unistd::Gid::from_raw(credentials.gid()),
+			executable: std::fs::read_link(format!("/proc/{pid}/exe")).ok(),
+		})
+	}
+
+	/// File name of the executable, if it's known.
+	pub fn name(&self) -> Option<&str> {
+		self.executable.as_ref()?.file_name()?.to_str()
+	}
+}
+
+impl std::fmt::Display for Credentials {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}[{}]", self.name().unwrap_or("?"), self.pid)
+	}
+}
+
+pub enum Flush {
+	Done,
+	/// The socket is full, the caller should flush again once it's writable.
+	WouldBlock,
+	/// The socket is full, and someone is already waiting for it to be writable.
+	Waiting,
+}
+
+#[derive(Default)]
+struct Output {
+	bytes: Vec<u8>,
+	fds: std::collections::VecDeque<std::os::fd::OwnedFd>,
+	waiting_for_writable: bool,
+	closed: bool,
+}
+
+impl Output {
+	fn close(&mut self) {
+		self.closed = true;
+		self.bytes.clear();
+		self.fds.clear();
+	}
+}
+
 pub struct Connection {
 	stream: std::os::unix::net::UnixStream,
+	trace: bool,
+	pub credentials: Credentials,
+	output: std::cell::RefCell<Output>,
+	high_water_mark: usize,
+	/// Versions of the client's objects, the ones missing are version 1.
+	versions: std::cell::RefCell<std::collections::HashMap<u32, u32>>,
 }
 
 impl Connection {
-	pub fn new(stream: std::os::unix::net::UnixStream) -> Self {
-		Self { stream }
+	/// The client is traced if `trace` is set, and it's empty or any of the filters match.
+	pub fn new(
+		stream: std::os::unix::net::UnixStream,
+		trace: Option<&[crate::TraceFilter]>,
+		high_water_mark: usize,
+	) -> Result<Self> {
+		let credentials = Credentials::from_stream(&stream)?;
+		let fd = stream.as_raw_fd();
+
+		let trace = trace.is_some_and(|filters| {
+			filters.is_empty() || filters.iter().any(|x| x.matches(fd, &credentials))
+		});
+
+		Ok(Self {
+			credentials,
+			stream,
+			trace,
+			output: Default::default(),
+			high_water_mark,
+			versions: Default::default(),
+		})
+	}
+
+	pub fn version(&self, object_id: u32) -> u32 {
+		self.versions.borrow().get(&object_id).copied().unwrap_or(1)
+	}
+
+	pub fn set_version(&self, object_id: u32, version: u32) {
+		let mut versions = self.versions.borrow_mut();
+
+		if version > 1 {
+			versions.insert(object_id, version);
+		} else {
+			versions.remove(&object_id);
+		}
 	}
 
 	pub fn as_raw_fd(&self) -> std::os::fd::RawFd {
 		self.stream.as_raw_fd()
 	}
 
-	pub fn send_message<T: serde::Serialize>(&self, message: wlm::Message<T>) -> Result<()> {
-		let mut cmsg_buffer = [0u8; 0x20];
-		let mut cmsg = std::os::unix::net::SocketAncillary::new(&mut cmsg_buffer);
-
-		let ret = self
-			.stream
-			.send_vectored_with_ancillary(&[std::io::IoSlice::new(&message.to_vec()?)], &mut cmsg);
+	pub fn trace_request<'a, T: wlm::Request<'a>>(&self, object_id: u32, request: &T) {
+		self.trace(false, T::INTERFACE, object_id, request);
+	}
 
-		if ret.is_err() {
-			eprintln!("Client::send_message() failed!");
+	/// Logs a message in the same format as libwayland does with `WAYLAND_DEBUG`.
+	fn trace(
+		&self,
+		outgoing: bool,
+		interface: &str,
+		object_id: u32,
+		message: &dyn std::fmt::Display,
+	) {
+		if !self.trace {
+			return;
 		}
 
-		Ok(())
+		let time = std::time::SystemTime::now()
+			.duration_since(std::time::UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_micros() as u32;
+
+		eprintln!(
+			"[{:7}.{:03}] {{{} fd={}}} {}{interface}#{object_id}.{message}",
+			time / 1000,
+			time % 1000,
+			self.credentials,
+			self.as_raw_fd(),
+			if outgoing { " -> " } else { "" },
+		);
 	}
 
-	pub fn send_message_with_fd<T: serde::Serialize>(
-		&self,
-		message: wlm::Message<T>,
-		fd: std::os::fd::RawFd,
-	) -> Result<()> {
-		let mut cmsg_buffer = [0u8; 0x20];
-		let mut cmsg = std::os::unix::net::SocketAncillary::new(&mut cmsg_buffer);
+	/// Queues `event`, unless it's newer than the version of the object it's sent to.
+	pub fn send_event<T: wlm::Event>(&self, object_id: u32, event: T) -> Result<()> {
+		if T::SINCE > self.version(object_id) {
+			return Ok(());
+		}
 
-		cmsg.add_fds(&[fd]);
+		self.trace(true, T::INTERFACE, object_id, &event);
 
-		let ret = self
-			.stream
-			.send_vectored_with_ancillary(&[std::io::IoSlice::new(&message.to_vec()?)], &mut cmsg);
+		let fds = event.fds();
+
+		let message = wlm::Message {
+			object_id,
+			op: T::OP,
+			args: event,
+		};
+
+		let mut output = self.output.borrow_mut();
+
+		if output.closed {
+			return Ok(());
+		}
 
-		if ret.is_err() {
-			eprintln!("Client::send_message() failed!");
+		// the caller is free to close its fds before the queue gets flushed
+		for fd in fds {
+			let fd = unsafe { std::os::fd::BorrowedFd::borrow_raw(fd) };
+			output.fds.push_back(fd.try_clone_to_owned()?);
 		}
 
+		output.bytes.extend(message.to_vec()?);
+
 		Ok(())
 	}
 
-	pub fn read(&self, n: usize) -> Result<Option<(Vec<u8>, Vec<std::os::fd::RawFd>)>> {
-		let mut cmsg_buffer = [0u8; 0x40];
-		let mut cmsg = std::os::unix::net::SocketAncillary::new(&mut cmsg_buffer);
+	/// Writes as much of the queued output as the socket takes without blocking. If a client has
+	/// more than the high-water mark queued after that, it's disconnected.
+	pub fn flush(&self) -> Flush {
+		// libwayland won't take more than this per message
+		const MAX_FDS_OUT: usize = 28;
 
-		let mut bytes = vec![0u8; n];
+		let mut output = self.output.borrow_mut();
+		let output = &mut *output;
 
-		let len = self
-			.stream
-			.recv_vectored_with_ancillary(&mut [std::io::IoSliceMut::new(&mut bytes)], &mut cmsg);
+		while !output.bytes.is_empty() {
+			let mut cmsg_buffer = [0u8; 0x80];
+			let mut cmsg = std::os::unix::net::SocketAncillary::new(&mut cmsg_buffer);
 
-		let len = match len {
-			Ok(len) => len,
-			Err(x) => match x.kind() {
-				std::io::ErrorKind::ConnectionReset => {
-					return Ok(None);
+			let fds = output
+				.fds
+				.iter()
+				.take(MAX_FDS_OUT)
+				.map(|x| x.as_raw_fd())
+				.collect::<Vec<_>>();
+
+			assert!(cmsg.add_fds(&fds));
+
+			let ret = self
+				.stream
+				.send_vectored_with_ancillary(&[std::io::IoSlice::new(&output.bytes)], &mut cmsg);
+
+			match ret {
+				Ok(len) => {
+					output.bytes.drain(..len);
+					output.fds.drain(..fds.len());
 				}
-				_ => {
-					return Err(x)?;
+				Err(x) if x.kind() == std::io::ErrorKind::WouldBlock => break,
+				Err(x) => {
+					if !matches!(
+						x.kind(),
+						std::io::ErrorKind::BrokenPipe | std::io::ErrorKind::ConnectionReset
+					) {
+						eprintln!("client {}: failed to send events: {x}", self.credentials);
+					}
+
+					// reading will notice the hang up and remove the client
+					output.close();
+					let _ = self.stream.shutdown(std::net::Shutdown::Both);
+
+					return Flush::Done;
 				}
-			},
-		};
+			}
+		}
 
-		if len == 0 {
-			return Ok(None);
+		if output.bytes.is_empty() {
+			output.waiting_for_writable = false;
+			return Flush::Done;
 		}
 
-		if len != n {
-			color_eyre::eyre::bail!(
-				"socket did not have enough bytes to read, expected {n}, got {len}"
+		if output.bytes.len() > self.high_water_mark {
+			eprintln!(
+				"client {} has {} bytes of unread events, disconnecting",
+				self.credentials,
+				output.bytes.len()
 			);
+
+			output.close();
+			let _ = self.stream.shutdown(std::net::Shutdown::Both);
+
+			return Flush::Done;
 		}
 
-		let mut fds = Vec::new();
+		if std::mem::replace(&mut output.waiting_for_writable, true) {
+			return Flush::Waiting;
+		}
+
+		Flush::WouldBlock
+	}
+
+	/// Drops the queued output and hangs up, which also wakes up everything that's polling a
+	/// duplicate of the socket.
+	pub fn shutdown(&self) {
+		self.output.borrow_mut().close();
+		let _ = self.stream.shutdown(std::net::Shutdown::Both);
+	}
+
+	pub fn is_waiting_for_writable(&self) -> bool {
+		self.output.borrow().waiting_for_writable
+	}
+
+	/// Duplicates the socket, so it can be polled for writability separately from reading.
+	pub fn try_clone_fd(&self) -> Result<std::os::fd::OwnedFd> {
+		Ok(self.stream.as_fd().try_clone_to_owned()?)
+	}
+
+	/// Reads whatever is available into `buffer`, and appends the fds that came with it to `fds`.
+	/// Returns `None` once the client has hung up.
+	pub fn read(
+		&self,
+		buffer: &mut [u8],
+		fds: &mut std::collections::VecDeque<std::os::fd::RawFd>,
+	) -> Result<Option<usize>> {
+		// the kernel won't pass more than SCM_MAX_FD fds with a single message
+		const MAX_FDS_IN: usize = 253;
+
+		let mut cmsg_buffer = nix::cmsg_space!([std::os::fd::RawFd; MAX_FDS_IN]);
+		let mut cmsg = std::os::unix::net::SocketAncillary::new(&mut cmsg_buffer);
+
+		let len = self
+			.stream
+			.recv_vectored_with_ancillary(&mut [std::io::IoSliceMut::new(buffer)], &mut cmsg);
+
+		let len = match len {
+			Ok(0) => return Ok(None),
+			Ok(len) => len,
+			Err(x) => match x.kind() {
+				std::io::ErrorKind::WouldBlock => return Ok(Some(0)),
+				std::io::ErrorKind::ConnectionReset => return Ok(None),
+				_ => return Err(x)?,
+			},
+		};
 
 		for i in cmsg.messages() {
 			let std::os::unix::net::AncillaryData::ScmRights(scm_rights) = i.unwrap() else {
 				continue;
 			};
 
-			fds.extend(scm_rights.into_iter());
+			fds.extend(scm_rights);
+		}
+
+		if cmsg.truncated() {
+			color_eyre::eyre::bail!("client sent more fds than fit in the control buffer");
 		}
 
-		Ok(Some((bytes, fds)))
+		Ok(Some(len))
 	}
 }
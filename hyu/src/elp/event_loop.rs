@@ -1,5 +1,8 @@
 use crate::{Result, elp};
 
+/// How many ready fds are handled per wake up.
+const MAX_EVENTS: usize = 32;
+
 struct Caller<T: elp::Source, U, V: FnMut(T::Message<'_>, &mut U, &mut EventLoop<U>) -> T::Ret> {
 	producer: T,
 	callback: V,
@@ -7,32 +10,66 @@ struct Caller<T: elp::Source, U, V: FnMut(T::Message<'_>, &mut U, &mut EventLoop
 }
 
 trait CallerWrapper<T> {
-	fn call(&mut self, state: &mut T, rt: &mut EventLoop<T>) -> Result<std::ops::ControlFlow<()>>;
+	fn call(
+		&mut self,
+		state: &mut T,
+		rt: &mut EventLoop<T>,
+		hung_up: bool,
+	) -> Result<std::ops::ControlFlow<()>>;
 }
 
 impl<T: elp::Source, U, V: FnMut(T::Message<'_>, &mut U, &mut EventLoop<U>) -> T::Ret>
 	CallerWrapper<U> for Caller<T, U, V>
 {
-	fn call(&mut self, state: &mut U, rt: &mut EventLoop<U>) -> Result<std::ops::ControlFlow<()>> {
-		self.producer
-			.call(&mut |event| (self.callback)(event, state, rt))
+	fn call(
+		&mut self,
+		state: &mut U,
+		rt: &mut EventLoop<U>,
+		hung_up: bool,
+	) -> Result<std::ops::ControlFlow<()>> {
+		if hung_up {
+			self.producer
+				.hang_up(&mut |event| (self.callback)(event, state, rt))
+		} else {
+			self.producer
+				.call(&mut |event| (self.callback)(event, state, rt))
+		}
 	}
 }
 
+type IdleCallback<State> = Box<dyn FnMut(&mut State, &mut EventLoop<State>) -> Result<()>>;
+
 pub struct EventLoop<State> {
 	map: std::collections::HashMap<
 		std::os::fd::RawFd,
 		std::rc::Rc<std::cell::RefCell<dyn CallerWrapper<State>>>,
 	>,
+	idle: Vec<IdleCallback<State>>,
+	stopped: bool,
+	timers: elp::timer::Timers<State>,
 	epoll: nix::sys::epoll::Epoll,
 	_phantom: std::marker::PhantomData<State>,
 }
 
 impl<State: 'static> EventLoop<State> {
 	pub fn create() -> Result<Self> {
+		let timers = elp::timer::Timers::create()?;
+		let epoll = nix::sys::epoll::Epoll::new(nix::sys::epoll::EpollCreateFlags::empty())?;
+
+		epoll.add(
+			unsafe { std::os::fd::BorrowedFd::borrow_raw(timers.fd()) },
+			nix::sys::epoll::EpollEvent::new(
+				nix::sys::epoll::EpollFlags::EPOLLIN,
+				timers.fd() as _,
+			),
+		)?;
+
 		Ok(Self {
 			map: Default::default(),
-			epoll: nix::sys::epoll::Epoll::new(nix::sys::epoll::EpollCreateFlags::empty())?,
+			idle: Vec::new(),
+			stopped: false,
+			timers,
+			epoll,
 			_phantom: std::marker::PhantomData,
 		})
 	}
@@ -43,6 +80,109 @@ impl<State: 'static> EventLoop<State> {
 		callback: impl FnMut(T::Message<'_>, &mut State, &mut Self) -> T::Ret + 'static,
 	) -> Result<()> {
 		let fd = producer.fd();
+		let events = nix::sys::epoll::EpollEvent::new(producer.events(), fd as _);
+
+		self.epoll
+			.add(unsafe { std::os::fd::BorrowedFd::borrow_raw(fd) }, events)?;
+
+		self.insert(producer, callback);
+		Ok(())
+	}
+
+	/// Like `on`, but replaces the source that's already registered for the same fd, if any.
+	pub fn replace<T: elp::Source + 'static>(
+		&mut self,
+		producer: T,
+		callback: impl FnMut(T::Message<'_>, &mut State, &mut Self) -> T::Ret + 'static,
+	) -> Result<()> {
+		let fd = producer.fd();
+
+		if !self.map.contains_key(&fd) {
+			return self.on(producer, callback);
+		}
+
+		let mut events = nix::sys::epoll::EpollEvent::new(producer.events(), fd as _);
+
+		self.epoll.modify(
+			unsafe { std::os::fd::BorrowedFd::borrow_raw(fd) },
+			&mut events,
+		)?;
+
+		self.insert(producer, callback);
+		Ok(())
+	}
+
+	/// Removes the source registered for `fd`. Returns whether there was one.
+	pub fn remove(&mut self, fd: std::os::fd::RawFd) -> Result<bool> {
+		if self.map.remove(&fd).is_none() {
+			return Ok(false);
+		}
+
+		self.epoll
+			.delete(unsafe { std::os::fd::BorrowedFd::borrow_raw(fd) })?;
+
+		Ok(true)
+	}
+
+	/// Registers a callback that runs after every batch of events has been handled.
+	pub fn on_idle(&mut self, callback: impl FnMut(&mut State, &mut Self) -> Result<()> + 'static) {
+		self.idle.push(Box::new(callback));
+	}
+
+	/// Calls `callback` once, after `delay`.
+	pub fn timer_after(
+		&mut self,
+		delay: std::time::Duration,
+		callback: impl FnMut(&mut State, &mut Self) -> Result<()> + 'static,
+	) -> Result<elp::TimerId> {
+		self.timers.insert(
+			std::time::Instant::now() + delay,
+			None,
+			std::rc::Rc::new(std::cell::RefCell::new(callback)),
+		)
+	}
+
+	/// Calls `callback` every `interval`, starting one `interval` from now.
+	pub fn timer_every(
+		&mut self,
+		interval: std::time::Duration,
+		callback: impl FnMut(&mut State, &mut Self) -> Result<()> + 'static,
+	) -> Result<elp::TimerId> {
+		self.timers.insert(
+			std::time::Instant::now() + interval,
+			Some(interval),
+			std::rc::Rc::new(std::cell::RefCell::new(callback)),
+		)
+	}
+
+	/// Returns whether the timer was still pending. One-shot timers stop being pending right
+	/// before their callback is called.
+	pub fn cancel_timer(&mut self, id: elp::TimerId) -> Result<bool> {
+		self.timers.cancel(id)
+	}
+
+	/// Moves the next expiration of a pending timer to `delay` from now. Repeating timers keep their
+	/// interval from there on.
+	pub fn reschedule_timer(
+		&mut self,
+		id: elp::TimerId,
+		delay: std::time::Duration,
+	) -> Result<bool> {
+		self.timers
+			.reschedule(id, std::time::Instant::now() + delay)
+	}
+
+	/// Makes `run` return once the current batch of events has been handled.
+	pub fn stop(&mut self) {
+		self.stopped = true;
+	}
+
+	fn insert<T: elp::Source + 'static>(
+		&mut self,
+		producer: T,
+		callback: impl FnMut(T::Message<'_>, &mut State, &mut Self) -> T::Ret + 'static,
+	) {
+		let fd = producer.fd();
 
 		let a = Caller {
 			producer,
@@ -50,40 +190,71 @@ impl<State: 'static> EventLoop<State> {
 			_phantom: std::marker::PhantomData::<State>,
 		};
 
-		self.epoll.add(
-			unsafe { std::os::fd::BorrowedFd::borrow_raw(fd) },
-			nix::sys::epoll::EpollEvent::new(nix::sys::epoll::EpollFlags::EPOLLIN, fd as _),
-		)?;
-
 		self.map
 			.insert(fd, std::rc::Rc::new(std::cell::RefCell::new(a)));
-
-		Ok(())
 	}
 
 	pub fn run(&mut self, state: &mut State) -> Result<()> {
-		loop {
-			let mut events = [nix::sys::epoll::EpollEvent::empty()];
+		while !self.stopped {
+			let mut events = [nix::sys::epoll::EpollEvent::empty(); MAX_EVENTS];
 			let ret = self
 				.epoll
 				.wait(&mut events, nix::sys::epoll::EpollTimeout::NONE)?;
 
-			assert!(ret == 1);
-			let fd = events[0].data() as std::os::fd::RawFd;
+			for event in &events[..ret] {
+				let fd = event.data() as std::os::fd::RawFd;
+
+				if fd == self.timers.fd() {
+					let now = std::time::Instant::now();
 
-			let entry = std::rc::Rc::clone(self.map.get_mut(&fd).unwrap());
-			let mut entry = entry.borrow_mut();
+					// one at a time, so callbacks can cancel timers that are due as well
+					while let Some(callback) = self.timers.pop_expired(now) {
+						(callback.borrow_mut())(state, self)?;
+					}
 
-			let ret = entry.call(state, self)?;
+					self.timers.arm()?;
 
-			match ret {
-				std::ops::ControlFlow::Continue(_) => {}
-				std::ops::ControlFlow::Break(_) => {
-					self.map.remove(&fd).unwrap();
-					self.epoll
-						.delete(unsafe { std::os::fd::BorrowedFd::borrow_raw(fd) })?;
+					continue;
 				}
+
+				// an earlier callback in this batch might have removed it
+				let Some(entry) = self.map.get(&fd).cloned() else {
+					continue;
+				};
+
+				let flags = event.events();
+
+				// keep reading until the fd runs dry, the source notices the hang up itself then
+				let hung_up = flags.intersects(
+					nix::sys::epoll::EpollFlags::EPOLLHUP | nix::sys::epoll::EpollFlags::EPOLLERR,
+				) && !flags.intersects(
+					nix::sys::epoll::EpollFlags::EPOLLIN | nix::sys::epoll::EpollFlags::EPOLLOUT,
+				);
+
+				let ret = entry.borrow_mut().call(state, self, hung_up)?;
+
+				// unless the callback already removed or replaced itself
+				if ret.is_break()
+					&& self
+						.map
+						.get(&fd)
+						.is_some_and(|x| std::rc::Rc::ptr_eq(x, &entry))
+				{
+					self.remove(fd)?;
+				}
+			}
+
+			let mut idle = std::mem::take(&mut self.idle);
+
+			for callback in &mut idle {
+				callback(state, self)?;
 			}
+
+			// keep the ones registered while running
+			idle.append(&mut self.idle);
+			self.idle = idle;
 		}
+
+		Ok(())
 	}
 }
@@ -2,12 +2,16 @@ pub mod channel;
 pub mod drm;
 pub mod event_fd;
 pub mod input;
-pub mod timer_fd;
+pub mod signal;
 pub mod unix_listener;
 pub mod wl;
+pub mod wl_client;
+pub mod writable;
 
 mod event_loop;
 mod source;
+mod timer;
 
 pub use event_loop::*;
 pub use source::Source;
+pub use timer::TimerId;
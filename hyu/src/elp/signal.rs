@@ -0,0 +1,38 @@
+use std::os::fd::{AsFd as _, AsRawFd as _};
+
+use crate::{Result, elp};
+
+pub struct Source(nix::sys::signalfd::SignalFd);
+
+impl elp::Source for Source {
+	type Message<'a> = nix::sys::signal::Signal;
+	type Ret = Result<()>;
+
+	fn fd(&self) -> std::os::fd::RawFd {
+		self.0.as_fd().as_raw_fd()
+	}
+
+	fn call(
+		&mut self,
+		callback: &mut impl FnMut(Self::Message<'_>) -> Self::Ret,
+	) -> Result<std::ops::ControlFlow<()>> {
+		// several signals can be pending at once
+		while let Some(info) = self.0.read_signal()? {
+			callback(nix::sys::signal::Signal::try_from(info.ssi_signo as i32)?)?;
+		}
+
+		Ok(std::ops::ControlFlow::Continue(()))
+	}
+}
+
+/// Blocks `signals` for the calling thread and delivers them through the returned source instead.
+/// Has to be called before any threads are spawned, so they inherit the mask.
+pub fn create(signals: &[nix::sys::signal::Signal]) -> Result<Source> {
+	let mask = nix::sys::signal::SigSet::from_iter(signals.iter().copied());
+	mask.thread_block()?;
+
+	Ok(Source(nix::sys::signalfd::SignalFd::with_flags(
+		&mask,
+		nix::sys::signalfd::SfdFlags::SFD_NONBLOCK | nix::sys::signalfd::SfdFlags::SFD_CLOEXEC,
+	)?))
+}
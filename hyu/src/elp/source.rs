@@ -5,8 +5,22 @@ pub trait Source {
 	type Ret;
 
 	fn fd(&self) -> std::os::fd::RawFd;
+
+	fn events(&self) -> nix::sys::epoll::EpollFlags {
+		nix::sys::epoll::EpollFlags::EPOLLIN
+	}
+
 	fn call(
 		&mut self,
 		callback: &mut impl FnMut(Self::Message<'_>) -> Self::Ret,
 	) -> Result<std::ops::ControlFlow<()>>;
+
+	/// Called when the fd hung up or errored and there's nothing left to read from it. Removes the
+	/// source by default.
+	fn hang_up(
+		&mut self,
+		_callback: &mut impl FnMut(Self::Message<'_>) -> Self::Ret,
+	) -> Result<std::ops::ControlFlow<()>> {
+		Ok(std::ops::ControlFlow::Break(()))
+	}
 }
@@ -0,0 +1,159 @@
+use std::os::fd::{AsFd as _, AsRawFd as _};
+
+use crate::{Result, elp};
+
+pub(super) type Callback<State> = std::rc::Rc<
+	std::cell::RefCell<dyn FnMut(&mut State, &mut elp::EventLoop<State>) -> Result<()>>,
+>;
+
+/// Identifies a timer registered with `EventLoop::timer_after` or `EventLoop::timer_every`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimerId(u64);
+
+struct Timer<State> {
+	deadline: std::time::Instant,
+	interval: Option<std::time::Duration>,
+	callback: Callback<State>,
+}
+
+/// Every timer of an event loop, multiplexed onto a single timerfd.
+pub(super) struct Timers<State> {
+	fd: nix::sys::timerfd::TimerFd,
+	/// Cancelled or rescheduled timers leave their old entries here, they're skipped once they
+	/// come up.
+	queue: std::collections::BinaryHeap<std::cmp::Reverse<(std::time::Instant, u64)>>,
+	timers: std::collections::HashMap<u64, Timer<State>>,
+	next_id: u64,
+}
+
+impl<State> Timers<State> {
+	pub fn create() -> Result<Self> {
+		Ok(Self {
+			fd: nix::sys::timerfd::TimerFd::new(
+				nix::sys::timerfd::ClockId::CLOCK_MONOTONIC,
+				nix::sys::timerfd::TimerFlags::TFD_NONBLOCK
+					| nix::sys::timerfd::TimerFlags::TFD_CLOEXEC,
+			)?,
+			queue: Default::default(),
+			timers: Default::default(),
+			next_id: 0,
+		})
+	}
+
+	pub fn fd(&self) -> std::os::fd::RawFd {
+		self.fd.as_fd().as_raw_fd()
+	}
+
+	pub fn insert(
+		&mut self,
+		deadline: std::time::Instant,
+		interval: Option<std::time::Duration>,
+		callback: Callback<State>,
+	) -> Result<TimerId> {
+		let id = self.next_id;
+		self.next_id += 1;
+
+		self.timers.insert(
+			id,
+			Timer {
+				deadline,
+				interval,
+				callback,
+			},
+		);
+
+		self.queue.push(std::cmp::Reverse((deadline, id)));
+		self.arm()?;
+
+		Ok(TimerId(id))
+	}
+
+	pub fn cancel(&mut self, id: TimerId) -> Result<bool> {
+		if self.timers.remove(&id.0).is_none() {
+			return Ok(false);
+		}
+
+		self.arm()?;
+		Ok(true)
+	}
+
+	pub fn reschedule(&mut self, id: TimerId, deadline: std::time::Instant) -> Result<bool> {
+		let Some(timer) = self.timers.get_mut(&id.0) else {
+			return Ok(false);
+		};
+
+		timer.deadline = deadline;
+
+		self.queue.push(std::cmp::Reverse((deadline, id.0)));
+		self.arm()?;
+
+		Ok(true)
+	}
+
+	/// Takes the callback of the next timer that's due. One-shot timers are gone after this,
+	/// repeating ones are moved to their next deadline, skipping the ones that were missed.
+	pub fn pop_expired(&mut self, now: std::time::Instant) -> Option<Callback<State>> {
+		while let Some(&std::cmp::Reverse((deadline, id))) = self.queue.peek() {
+			if deadline > now {
+				break;
+			}
+
+			self.queue.pop();
+
+			let Some(timer) = self.timers.get_mut(&id) else {
+				continue;
+			};
+
+			if timer.deadline != deadline {
+				continue;
+			}
+
+			let callback = timer.callback.clone();
+
+			match timer.interval {
+				Some(interval) => {
+					while timer.deadline <= now {
+						timer.deadline += interval;
+					}
+
+					self.queue.push(std::cmp::Reverse((timer.deadline, id)));
+				}
+				None => {
+					self.timers.remove(&id);
+				}
+			}
+
+			return Some(callback);
+		}
+
+		None
+	}
+
+	/// Points the timerfd at the earliest deadline, or disarms it if there's none.
+	pub fn arm(&mut self) -> Result<()> {
+		while let Some(&std::cmp::Reverse((deadline, id))) = self.queue.peek() {
+			if self.timers.get(&id).is_some_and(|x| x.deadline == deadline) {
+				break;
+			}
+
+			self.queue.pop();
+		}
+
+		let Some(&std::cmp::Reverse((deadline, _))) = self.queue.peek() else {
+			self.fd.unset()?;
+			return Ok(());
+		};
+
+		// a zero expiration would disarm the timer instead
+		let delay = deadline
+			.saturating_duration_since(std::time::Instant::now())
+			.max(std::time::Duration::from_nanos(1));
+
+		self.fd.set(
+			nix::sys::timerfd::Expiration::OneShot(nix::sys::time::TimeSpec::from_duration(delay)),
+			nix::sys::timerfd::TimerSetTimeFlags::empty(),
+		)?;
+
+		Ok(())
+	}
+}
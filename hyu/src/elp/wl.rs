@@ -2,8 +2,19 @@ use std::rc::Rc;
 
 use crate::{Connection, Result, elp};
 
+const HEADER_SIZE: usize = 4 + 2 + 2;
+
+/// Big enough for the largest message the wire format can describe.
+const BUFFER_SIZE: usize = u16::MAX as usize + 1;
+
 pub struct Source {
 	connection: Rc<Connection>,
+	buffer: Box<[u8]>,
+	/// Bytes from `start` to `end` have been read but not dispatched yet.
+	start: usize,
+	end: usize,
+	/// Fds that came with the bytes, messages pop them as they're decoded.
+	fds: std::collections::VecDeque<std::os::fd::RawFd>,
 }
 
 pub enum Message<'a> {
@@ -11,14 +22,14 @@ pub enum Message<'a> {
 		object: u32,
 		op: u16,
 		params: &'a [u8],
-		fds: &'a [std::os::fd::RawFd],
+		fds: &'a mut std::collections::VecDeque<std::os::fd::RawFd>,
 	},
 	Closed,
 }
 
 impl elp::Source for Source {
 	type Message<'a> = Message<'a>;
-	type Ret = Result<()>;
+	type Ret = Result<std::ops::ControlFlow<()>>;
 
 	fn fd(&self) -> std::os::fd::RawFd {
 		self.connection.as_raw_fd()
@@ -28,47 +39,88 @@ impl elp::Source for Source {
 		&mut self,
 		callback: &mut impl FnMut(Self::Message<'_>) -> Self::Ret,
 	) -> Result<std::ops::ControlFlow<()>> {
-		const HEADER_SIZE: usize = 4 + 2 + 2;
-
-		let Some((header, mut fds)) = self.connection.read(HEADER_SIZE)? else {
-			callback(Message::Closed)?;
-			return Ok(std::ops::ControlFlow::Break(()));
+		// move the partial message at the end to the front, so there's room to read after it
+		self.buffer.copy_within(self.start..self.end, 0);
+		self.end -= self.start;
+		self.start = 0;
+
+		let len = match self
+			.connection
+			.read(&mut self.buffer[self.end..], &mut self.fds)
+		{
+			Ok(Some(len)) => len,
+			Ok(None) => return callback(Message::Closed),
+			Err(err) => {
+				eprintln!(
+					"failed to read from client {}: {err}",
+					self.connection.credentials
+				);
+				return callback(Message::Closed);
+			}
 		};
 
-		assert!(header.len() == HEADER_SIZE);
+		self.end += len;
 
-		let object = u32::from_ne_bytes(<[u8; 4]>::try_from(&header[0..4])?);
-		let op = u16::from_ne_bytes(<[u8; 2]>::try_from(&header[4..6])?);
-		let size = u16::from_ne_bytes(<[u8; 2]>::try_from(&header[6..8])?);
+		while self.end - self.start >= HEADER_SIZE {
+			let header = &self.buffer[self.start..][..HEADER_SIZE];
 
-		assert!(size >= HEADER_SIZE as u16);
-		let size = size - HEADER_SIZE as u16;
+			let object = u32::from_ne_bytes(<[u8; 4]>::try_from(&header[0..4])?);
+			let op = u16::from_ne_bytes(<[u8; 2]>::try_from(&header[4..6])?);
+			let size = u16::from_ne_bytes(<[u8; 2]>::try_from(&header[6..8])?) as usize;
 
-		let params = if size != 0 {
-			let Some((params, second_fds)) = self.connection.read(size as _)? else {
-				callback(Message::Closed)?;
-				return Ok(std::ops::ControlFlow::Break(()));
-			};
+			if size < HEADER_SIZE || !size.is_multiple_of(4) {
+				eprintln!(
+					"client {} sent a message with invalid size {size}",
+					self.connection.credentials
+				);
+				return callback(Message::Closed);
+			}
 
-			assert!(params.len() == size as usize);
-			fds.extend(second_fds);
+			if self.end - self.start < size {
+				break;
+			}
 
-			params
-		} else {
-			Vec::new()
-		};
+			let params = &self.buffer[self.start + HEADER_SIZE..self.start + size];
+			self.start += size;
 
-		callback(Message::Request {
-			object,
-			op,
-			params: &params,
-			fds: &fds,
-		})?;
+			let ret = callback(Message::Request {
+				object,
+				op,
+				params,
+				fds: &mut self.fds,
+			})?;
+
+			if ret.is_break() {
+				return Ok(ret);
+			}
+		}
 
 		Ok(std::ops::ControlFlow::Continue(()))
 	}
+
+	fn hang_up(
+		&mut self,
+		callback: &mut impl FnMut(Self::Message<'_>) -> Self::Ret,
+	) -> Result<std::ops::ControlFlow<()>> {
+		callback(Message::Closed)
+	}
+}
+
+impl Drop for Source {
+	fn drop(&mut self) {
+		// fds that no request consumed
+		for fd in self.fds.drain(..) {
+			let _ = nix::unistd::close(fd);
+		}
+	}
 }
 
 pub fn create(connection: Rc<Connection>) -> Source {
-	Source { connection }
+	Source {
+		connection,
+		buffer: vec![0; BUFFER_SIZE].into_boxed_slice(),
+		start: 0,
+		end: 0,
+		fds: Default::default(),
+	}
 }
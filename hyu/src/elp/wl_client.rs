@@ -0,0 +1,34 @@
+use std::os::fd::AsRawFd as _;
+
+use crate::{Result, elp};
+
+pub struct Source {
+	client: std::rc::Rc<std::cell::RefCell<wlm::client::Client>>,
+}
+
+impl elp::Source for Source {
+	type Message<'a> = wlm::client::RawEvent;
+	type Ret = Result<()>;
+
+	fn fd(&self) -> std::os::fd::RawFd {
+		self.client.borrow().as_raw_fd()
+	}
+
+	fn call(
+		&mut self,
+		callback: &mut impl FnMut(Self::Message<'_>) -> Self::Ret,
+	) -> Result<std::ops::ControlFlow<()>> {
+		self.client.borrow_mut().dispatch()?;
+
+		// the callback is free to borrow the client again
+		while let Some(event) = { self.client.borrow_mut().poll_event() } {
+			callback(event)?;
+		}
+
+		Ok(std::ops::ControlFlow::Continue(()))
+	}
+}
+
+pub fn create(client: std::rc::Rc<std::cell::RefCell<wlm::client::Client>>) -> Source {
+	Source { client }
+}
@@ -0,0 +1,30 @@
+use std::os::fd::AsRawFd as _;
+
+use crate::{Result, elp};
+
+/// Fires once the fd can be written to without blocking.
+pub struct Source(std::os::fd::OwnedFd);
+
+impl elp::Source for Source {
+	type Message<'a> = ();
+	type Ret = Result<std::ops::ControlFlow<()>>;
+
+	fn fd(&self) -> std::os::fd::RawFd {
+		self.0.as_raw_fd()
+	}
+
+	fn events(&self) -> nix::sys::epoll::EpollFlags {
+		nix::sys::epoll::EpollFlags::EPOLLOUT
+	}
+
+	fn call(
+		&mut self,
+		callback: &mut impl FnMut(Self::Message<'_>) -> Self::Ret,
+	) -> Result<std::ops::ControlFlow<()>> {
+		callback(())
+	}
+}
+
+pub fn create(fd: std::os::fd::OwnedFd) -> Source {
+	Source(fd)
+}
@@ -0,0 +1,61 @@
+use crate::{
+	Point,
+	layout::{self, Rect},
+};
+
+/// Side by side columns of the same width.
+pub struct Columns;
+
+impl layout::Layout for Columns {
+	fn name(&self) -> &'static str {
+		"columns"
+	}
+
+	fn arrange(&self, count: usize, (position, size): Rect) -> Vec<Rect> {
+		layout::split(position.0, size.0, count)
+			.take(count)
+			.map(|(x, width)| (Point(x, position.1), Point(width, size.1)))
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::{
+		Layout as _,
+		tests::{AREA, assert_tiles},
+	};
+
+	#[test]
+	fn empty() {
+		assert!(Columns.arrange(0, AREA).is_empty());
+	}
+
+	#[test]
+	fn single() {
+		assert_eq!(Columns.arrange(1, AREA), [AREA]);
+	}
+
+	#[test]
+	fn many() {
+		for count in 2..10 {
+			let rects = Columns.arrange(count, AREA);
+
+			assert_eq!(rects.len(), count);
+			assert_tiles(&rects, AREA);
+			assert!(rects.iter().all(|x| x.0.1 == AREA.0.1 && x.1.1 == AREA.1.1));
+		}
+	}
+
+	#[test]
+	fn rounding() {
+		let widths = Columns
+			.arrange(3, AREA)
+			.iter()
+			.map(|x| x.1.0)
+			.collect::<Vec<_>>();
+
+		assert_eq!(widths, [640, 640, 641]);
+	}
+}
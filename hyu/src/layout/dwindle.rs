@@ -0,0 +1,124 @@
+use crate::{
+	Point,
+	layout::{self, Rect},
+};
+
+/// Each window takes half of what the previous one left over, alternating between splitting
+/// the width and the height. As a spiral, the halves also rotate around the area.
+pub struct Dwindle {
+	spiral: bool,
+}
+
+impl Dwindle {
+	pub fn new(spiral: bool) -> Self {
+		Self { spiral }
+	}
+}
+
+impl layout::Layout for Dwindle {
+	fn name(&self) -> &'static str {
+		if self.spiral { "spiral" } else { "dwindle" }
+	}
+
+	fn arrange(&self, count: usize, (mut position, mut size): Rect) -> Vec<Rect> {
+		let mut ret = Vec::with_capacity(count);
+
+		for index in 0..count {
+			if index == count - 1 {
+				ret.push((position, size));
+				break;
+			}
+
+			let width = size.0 / 2;
+			let height = size.1 / 2;
+
+			match index % if self.spiral { 4 } else { 2 } {
+				// left
+				0 => {
+					ret.push((position, Point(width, size.1)));
+					position.0 += width;
+					size.0 -= width;
+				}
+				// top
+				1 => {
+					ret.push((position, Point(size.0, height)));
+					position.1 += height;
+					size.1 -= height;
+				}
+				// right
+				2 => {
+					ret.push((
+						Point(position.0 + size.0 - width, position.1),
+						Point(width, size.1),
+					));
+					size.0 -= width;
+				}
+				// bottom
+				_ => {
+					ret.push((
+						Point(position.0, position.1 + size.1 - height),
+						Point(size.0, height),
+					));
+					size.1 -= height;
+				}
+			}
+		}
+
+		ret
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::{
+		Layout as _,
+		tests::{AREA, assert_tiles},
+	};
+
+	#[test]
+	fn empty() {
+		assert!(Dwindle::new(false).arrange(0, AREA).is_empty());
+		assert!(Dwindle::new(true).arrange(0, AREA).is_empty());
+	}
+
+	#[test]
+	fn single() {
+		assert_eq!(Dwindle::new(false).arrange(1, AREA), [AREA]);
+		assert_eq!(Dwindle::new(true).arrange(1, AREA), [AREA]);
+	}
+
+	#[test]
+	fn many() {
+		for spiral in [false, true] {
+			for count in 2..12 {
+				let rects = Dwindle::new(spiral).arrange(count, AREA);
+
+				assert_eq!(rects.len(), count);
+				assert_tiles(&rects, AREA);
+			}
+		}
+	}
+
+	#[test]
+	fn odd_splits() {
+		// the window that's split off gets the smaller half
+		let rects = Dwindle::new(false).arrange(3, AREA);
+
+		assert_eq!(rects[0], (Point(10, 20), Point(960, 1081)));
+		assert_eq!(rects[1], (Point(970, 20), Point(961, 540)));
+		assert_eq!(rects[2], (Point(970, 560), Point(961, 541)));
+	}
+
+	#[test]
+	fn spiral() {
+		let rects = Dwindle::new(true).arrange(5, AREA);
+
+		// left, top, right, bottom, then whatever is left in the middle
+		assert_eq!(rects[0], (Point(10, 20), Point(960, 1081)));
+		assert_eq!(rects[1], (Point(970, 20), Point(961, 540)));
+		assert_eq!(rects[2], (Point(1451, 560), Point(480, 541)));
+		assert_eq!(rects[3], (Point(970, 831), Point(481, 270)));
+		assert_eq!(rects[4], (Point(970, 560), Point(481, 271)));
+	}
+}
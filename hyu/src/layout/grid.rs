@@ -0,0 +1,89 @@
+use crate::{
+	Point,
+	layout::{self, Rect},
+};
+
+/// Rows of the same height, the last one stretches its windows if it isn't full.
+pub struct Grid;
+
+impl layout::Layout for Grid {
+	fn name(&self) -> &'static str {
+		"grid"
+	}
+
+	fn arrange(&self, count: usize, (position, size): Rect) -> Vec<Rect> {
+		if count == 0 {
+			return Vec::new();
+		}
+
+		let columns = count.isqrt() + usize::from(count.isqrt().pow(2) != count);
+		let rows = count.div_ceil(columns);
+
+		let mut ret = Vec::with_capacity(count);
+
+		for (row, (y, height)) in layout::split(position.1, size.1, rows).enumerate() {
+			let in_row = columns.min(count - row * columns);
+
+			ret.extend(
+				layout::split(position.0, size.0, in_row)
+					.map(|(x, width)| (Point(x, y), Point(width, height))),
+			);
+		}
+
+		ret
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::{
+		Layout as _,
+		tests::{AREA, assert_tiles},
+	};
+
+	#[test]
+	fn empty() {
+		assert!(Grid.arrange(0, AREA).is_empty());
+	}
+
+	#[test]
+	fn single() {
+		assert_eq!(Grid.arrange(1, AREA), [AREA]);
+	}
+
+	#[test]
+	fn many() {
+		for count in 2..20 {
+			let rects = Grid.arrange(count, AREA);
+
+			assert_eq!(rects.len(), count);
+			assert_tiles(&rects, AREA);
+		}
+	}
+
+	#[test]
+	fn square() {
+		let rects = Grid.arrange(4, AREA);
+
+		assert_eq!(rects[0], (Point(10, 20), Point(960, 540)));
+		assert_eq!(rects[3], (Point(970, 560), Point(961, 541)));
+	}
+
+	#[test]
+	fn last_row_stretches() {
+		// two columns, the third window gets the whole bottom row
+		let rects = Grid.arrange(3, AREA);
+
+		assert_eq!(rects[0].1, Point(960, 540));
+		assert_eq!(rects[1].1, Point(961, 540));
+		assert_eq!(rects[2], (Point(10, 560), Point(1921, 541)));
+
+		// three columns, two rows with the last one having two windows
+		let rects = Grid.arrange(5, AREA);
+
+		assert!(rects[..3].iter().all(|x| x.0.1 == 20));
+		assert!(rects[3..].iter().all(|x| x.0.1 == 560));
+		assert_eq!(rects[3].1.0 + rects[4].1.0, 1921);
+	}
+}
@@ -0,0 +1,140 @@
+use crate::{
+	Point,
+	layout::{self, Rect},
+};
+
+/// Master windows stacked on the left, the rest stacked on the right.
+pub struct MasterStack {
+	/// Width of the master column, as a fraction of the area.
+	ratio: f32,
+	masters: usize,
+}
+
+impl MasterStack {
+	pub fn new(ratio: f32, masters: usize) -> Self {
+		Self {
+			ratio: ratio.clamp(0.1, 0.9),
+			masters,
+		}
+	}
+}
+
+impl layout::Layout for MasterStack {
+	fn name(&self) -> &'static str {
+		"master_stack"
+	}
+
+	fn arrange(&self, count: usize, (position, size): Rect) -> Vec<Rect> {
+		let masters = self.masters.min(count);
+		let stack = count - masters;
+
+		let master_width = match (masters, stack) {
+			(0, _) => 0,
+			(_, 0) => size.0,
+			_ => (size.0 as f32 * self.ratio) as i32,
+		};
+
+		let master_column = layout::split(position.1, size.1, masters)
+			.map(|(y, height)| (Point(position.0, y), Point(master_width, height)));
+
+		let stack_column = layout::split(position.1, size.1, stack).map(|(y, height)| {
+			(
+				Point(position.0 + master_width, y),
+				Point(size.0 - master_width, height),
+			)
+		});
+
+		master_column
+			.take(masters)
+			.chain(stack_column.take(stack))
+			.collect()
+	}
+
+	fn resize_master(&mut self, delta: f32) {
+		self.ratio = (self.ratio + delta).clamp(0.1, 0.9);
+	}
+
+	fn change_master_count(&mut self, delta: isize) {
+		self.masters = self.masters.saturating_add_signed(delta);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::{
+		Layout as _,
+		tests::{AREA, assert_tiles},
+	};
+
+	#[test]
+	fn empty() {
+		assert!(MasterStack::new(0.5, 1).arrange(0, AREA).is_empty());
+	}
+
+	#[test]
+	fn single() {
+		assert_eq!(MasterStack::new(0.5, 1).arrange(1, AREA), [AREA]);
+	}
+
+	#[test]
+	fn many() {
+		for masters in 0..4 {
+			for count in 2..10 {
+				let rects = MasterStack::new(0.6, masters).arrange(count, AREA);
+
+				assert_eq!(rects.len(), count);
+				assert_tiles(&rects, AREA);
+			}
+		}
+	}
+
+	#[test]
+	fn no_masters() {
+		let rects = MasterStack::new(0.5, 0).arrange(2, AREA);
+
+		assert_eq!(rects[0], (Point(10, 20), Point(1921, 540)));
+		assert_eq!(rects[1], (Point(10, 560), Point(1921, 541)));
+	}
+
+	#[test]
+	fn only_masters() {
+		let rects = MasterStack::new(0.5, 3).arrange(2, AREA);
+
+		assert!(rects.iter().all(|x| x.1.0 == 1921));
+	}
+
+	#[test]
+	fn rounding() {
+		let rects = MasterStack::new(0.5, 1).arrange(4, AREA);
+
+		// the stack gets whatever the master's width rounds away
+		assert_eq!(rects[0], (Point(10, 20), Point(960, 1081)));
+
+		let heights = rects[1..].iter().map(|x| x.1.1).collect::<Vec<_>>();
+		assert_eq!(heights, [360, 360, 361]);
+		assert!(rects[1..].iter().all(|x| x.1.0 == 961));
+	}
+
+	#[test]
+	fn ratio_is_clamped() {
+		let mut layout = MasterStack::new(2.0, 1);
+		assert_eq!(layout.arrange(2, AREA)[0].1.0, (1921.0 * 0.9f32) as i32);
+
+		layout.resize_master(-5.0);
+		assert_eq!(layout.arrange(2, AREA)[0].1.0, (1921.0 * 0.1f32) as i32);
+	}
+
+	#[test]
+	fn master_count() {
+		let mut layout = MasterStack::new(0.5, 1);
+
+		layout.change_master_count(-5);
+		assert_eq!(layout.arrange(2, AREA)[0].1.0, 1921);
+
+		layout.change_master_count(2);
+		let rects = layout.arrange(3, AREA);
+		assert_eq!(rects[0].1, Point(960, 540));
+		assert_eq!(rects[1].1, Point(960, 541));
+	}
+}
@@ -0,0 +1,180 @@
+mod columns;
+mod dwindle;
+mod grid;
+mod master_stack;
+mod monocle;
+
+pub use columns::*;
+pub use dwindle::*;
+pub use grid::*;
+pub use master_stack::*;
+pub use monocle::*;
+
+use crate::{Config, LayoutKind, Point};
+
+/// Position and size.
+pub type Rect = (Point, Point);
+
+/// Tiles windows inside an area. Doesn't know about clients or protocol objects, it only gets
+/// told how many windows there are.
+pub trait Layout {
+	fn name(&self) -> &'static str;
+
+	/// Returns a rectangle for each of the `count` windows, in the order of the window stack.
+	fn arrange(&self, count: usize, area: Rect) -> Vec<Rect>;
+
+	/// Grows the master area by `delta`, a fraction of the whole area.
+	fn resize_master(&mut self, delta: f32) {
+		let _ = delta;
+	}
+
+	fn change_master_count(&mut self, delta: isize) {
+		let _ = delta;
+	}
+}
+
+/// The layouts a workspace cycles through. Windows aren't assigned to outputs yet, so they're
+/// all tiled on the first one.
+pub struct Layouts {
+	layouts: Vec<Box<dyn Layout>>,
+	current: usize,
+}
+
+impl Layouts {
+	pub fn new(config: &Config) -> Self {
+		let mut layouts = config
+			.layouts
+			.iter()
+			.map(|kind| -> Box<dyn Layout> {
+				match kind {
+					LayoutKind::MasterStack => {
+						Box::new(MasterStack::new(config.master_ratio, config.master_count))
+					}
+					LayoutKind::Monocle => Box::new(Monocle),
+					LayoutKind::Grid => Box::new(Grid),
+					LayoutKind::Columns => Box::new(Columns),
+					LayoutKind::Dwindle => Box::new(Dwindle::new(false)),
+					LayoutKind::Spiral => Box::new(Dwindle::new(true)),
+				}
+			})
+			.collect::<Vec<_>>();
+
+		if layouts.is_empty() {
+			layouts.push(Box::new(MasterStack::new(
+				config.master_ratio,
+				config.master_count,
+			)));
+		}
+
+		Self {
+			layouts,
+			current: 0,
+		}
+	}
+
+	pub fn current(&self) -> &dyn Layout {
+		&*self.layouts[self.current]
+	}
+
+	pub fn current_mut(&mut self) -> &mut dyn Layout {
+		&mut *self.layouts[self.current]
+	}
+
+	pub fn cycle(&mut self) {
+		self.current = (self.current + 1) % self.layouts.len();
+	}
+}
+
+/// Shrinks `rect` by `amount` on every side, or grows it if `amount` is negative.
+pub fn inset((position, size): Rect, amount: i32) -> Rect {
+	(
+		position + Point(amount, amount),
+		Point((size.0 - amount * 2).max(1), (size.1 - amount * 2).max(1)),
+	)
+}
+
+/// Splits `length` into `count` parts that differ by at most one, returning their offsets from
+/// `start` and their lengths.
+fn split(start: i32, length: i32, count: usize) -> impl Iterator<Item = (i32, i32)> {
+	let count = count.max(1) as i64;
+
+	(0..count).map(move |i| {
+		let from = length as i64 * i / count;
+		let to = length as i64 * (i + 1) / count;
+
+		(start + from as i32, (to - from) as i32)
+	})
+}
+
+#[cfg(test)]
+pub mod tests {
+	use super::*;
+
+	/// An area with odd sizes and a non-zero origin, so rounding and offsets show up.
+	pub const AREA: Rect = (Point(10, 20), Point(1921, 1081));
+
+	/// Checks that `rects` cover `area` exactly, without overlapping each other.
+	pub fn assert_tiles(rects: &[Rect], (position, size): Rect) {
+		let mut covered = 0i64;
+
+		for (index, &(p, s)) in rects.iter().enumerate() {
+			assert!(s.0 > 0 && s.1 > 0, "{index}: empty {s:?}");
+			assert!(
+				p.0 >= position.0
+					&& p.1 >= position.1
+					&& p.0 + s.0 <= position.0 + size.0
+					&& p.1 + s.1 <= position.1 + size.1,
+				"{index}: {p:?} {s:?} is outside of the area"
+			);
+
+			for (other, &(q, t)) in rects.iter().enumerate().skip(index + 1) {
+				let overlaps =
+					p.0 < q.0 + t.0 && q.0 < p.0 + s.0 && p.1 < q.1 + t.1 && q.1 < p.1 + s.1;
+				assert!(!overlaps, "{index} and {other} overlap");
+			}
+
+			covered += s.0 as i64 * s.1 as i64;
+		}
+
+		assert_eq!(covered, size.0 as i64 * size.1 as i64);
+	}
+
+	#[test]
+	fn split_differs_by_at_most_one() {
+		let parts = split(5, 10, 3).collect::<Vec<_>>();
+		assert_eq!(parts, [(5, 3), (8, 3), (11, 4)]);
+
+		for count in 1..20 {
+			let parts = split(0, 1081, count).collect::<Vec<_>>();
+
+			assert_eq!(parts.len(), count);
+			assert_eq!(parts.iter().map(|x| x.1).sum::<i32>(), 1081);
+
+			let min = parts.iter().map(|x| x.1).min().unwrap();
+			let max = parts.iter().map(|x| x.1).max().unwrap();
+			assert!(max - min <= 1);
+
+			for pair in parts.windows(2) {
+				assert_eq!(pair[0].0 + pair[0].1, pair[1].0);
+			}
+		}
+	}
+
+	#[test]
+	fn split_zero_is_one_part() {
+		assert_eq!(split(0, 100, 0).collect::<Vec<_>>(), [(0, 100)]);
+	}
+
+	#[test]
+	fn inset_never_goes_empty() {
+		assert_eq!(
+			inset((Point(0, 0), Point(100, 50)), 10),
+			(Point(10, 10), Point(80, 30))
+		);
+		assert_eq!(
+			inset((Point(0, 0), Point(100, 50)), -5),
+			(Point(-5, -5), Point(110, 60))
+		);
+		assert_eq!(inset((Point(0, 0), Point(10, 10)), 8).1, Point(1, 1));
+	}
+}
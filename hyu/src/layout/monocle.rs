@@ -0,0 +1,27 @@
+use crate::layout::{self, Rect};
+
+/// Every window takes up the whole area, only the focused one is visible.
+pub struct Monocle;
+
+impl layout::Layout for Monocle {
+	fn name(&self) -> &'static str {
+		"monocle"
+	}
+
+	fn arrange(&self, count: usize, area: Rect) -> Vec<Rect> {
+		vec![area; count]
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::layout::{Layout as _, tests::AREA};
+
+	#[test]
+	fn every_window_is_fullscreen() {
+		for count in 0..5 {
+			assert_eq!(Monocle.arrange(count, AREA), vec![AREA; count]);
+		}
+	}
+}
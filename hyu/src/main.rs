@@ -1,7 +1,6 @@
 #![feature(unix_socket_ancillary_data)]
 #![feature(generic_arg_infer)]
 #![feature(seek_stream_len)]
-#![feature(sync_unsafe_cell)]
 
 pub mod backend;
 mod client;
@@ -10,9 +9,11 @@ pub mod connection;
 pub mod drm;
 pub mod elp;
 pub mod gbm;
+mod layout;
 pub mod libinput;
 mod point;
 pub mod renderer;
+mod socket;
 mod state;
 pub mod store;
 pub mod tty;
@@ -26,8 +27,6 @@ pub use connection::*;
 pub use point::*;
 pub use store::*;
 
-use wl::Object as _;
-
 use std::rc::Rc;
 
 pub type Result<T> = color_eyre::Result<T>;
@@ -40,88 +39,174 @@ impl<T: FnMut()> Drop for Defer<T> {
 	}
 }
 
+/// Keeps flushing `client` whenever its socket becomes writable, until its queue is drained.
+fn flush_when_writable(runtime: &mut elp::EventLoop<state::State>, client: &Client) -> Result<()> {
+	let fd = client.fd;
+	let conn = Rc::downgrade(&client.conn);
+
+	runtime.on(
+		elp::writable::create(client.conn.try_clone_fd()?),
+		move |_, state, _| {
+			// the fd might already belong to a different client
+			let Some(client) = state.compositor.clients.get(&fd).filter(|x| {
+				conn.upgrade()
+					.is_some_and(|conn| Rc::ptr_eq(&x.conn, &conn))
+			}) else {
+				return Ok(std::ops::ControlFlow::Break(()));
+			};
+
+			match client.conn.flush() {
+				connection::Flush::Done => Ok(std::ops::ControlFlow::Break(())),
+				_ => Ok(std::ops::ControlFlow::Continue(())),
+			}
+		},
+	)
+}
+
 fn main() -> Result<()> {
 	color_eyre::install()?;
 
+	let signals = elp::signal::create(&[
+		nix::sys::signal::SIGTERM,
+		nix::sys::signal::SIGINT,
+		nix::sys::signal::SIGHUP,
+		nix::sys::signal::SIGCHLD,
+	])?;
+
 	let config = Config::read_from_config_file()?;
-	let tty = tty::Device::open_current()?;
+	let trace_clients = match std::env::var("HYU_TRACE_CLIENTS") {
+		Ok(x) => x.split(',').map(str::parse).collect::<Result<Vec<_>>>()?,
+		Err(_) => config.trace_clients.clone(),
+	};
+	let trace = (config.trace
+		|| std::env::var("WAYLAND_DEBUG").is_ok_and(|x| x == "1" || x.contains("server")))
+	.then_some(trace_clients);
+	let backend = match std::env::var("HYU_BACKEND").as_deref() {
+		Ok("drm") => BackendKind::Drm,
+		Ok("headless") => BackendKind::Headless,
+		Ok("nested") => BackendKind::Nested,
+		Ok(x) => color_eyre::eyre::bail!("unknown backend '{x}'"),
+		Err(_) => config.backend,
+	};
+
+	let tty = match backend {
+		BackendKind::Drm => {
+			let tty = tty::Device::open_current()?;
+
+			let old_keyboard_mode = tty.get_keyboard_mode()?;
+			tty.set_mode(1)?;
+			tty.set_keyboard_mode(4)?;
 
-	let old_keyboard_mode = tty.get_keyboard_mode()?;
-	tty.set_mode(1)?;
-	tty.set_keyboard_mode(4)?;
+			Some((tty, old_keyboard_mode))
+		}
+		BackendKind::Headless | BackendKind::Nested => None,
+	};
 
 	let _restorer = Defer(|| {
-		let _ = tty.set_keyboard_mode(old_keyboard_mode);
-		let _ = tty.set_mode(0);
+		if let Some((tty, old_keyboard_mode)) = &tty {
+			let _ = tty.set_keyboard_mode(*old_keyboard_mode);
+			let _ = tty.set_mode(0);
+		}
 	});
 
-	let runtime_dir = std::env::var("XDG_RUNTIME_DIR")?;
-
-	let index = 1;
-	let path = std::path::PathBuf::from_iter([runtime_dir, format!("wayland-{index}")]);
+	let hw: Box<state::HwState> = match backend {
+		BackendKind::Drm => Box::new(backend::drm::initialize_state(&config)?),
+		BackendKind::Headless => Box::new(backend::headless::initialize_state(config)?),
+		BackendKind::Nested => Box::new(backend::nested::initialize_state(config)?),
+	};
 
-	if path.exists() {
-		std::fs::remove_file(&path)?;
-	}
+	let runtime_dir = std::path::PathBuf::from(std::env::var("XDG_RUNTIME_DIR")?);
+	let socket_name = std::env::var("HYU_SOCKET")
+		.ok()
+		.or_else(|| config.socket.clone());
 
-	let drm_state = backend::drm::initialize_state(&config)?;
+	let (socket, listener) = socket::bind(&runtime_dir, socket_name.as_deref())?;
+	listener.set_nonblocking(true)?;
 
-	let width = drm_state.screen.mode.hdisplay;
-	let height = drm_state.screen.mode.vdisplay;
+	eprintln!("listening on {}", socket.name);
 
 	let mut state = state::State {
-		hw: state::HwState {
-			drm: drm_state,
-			input: backend::input::initialize_state()?,
-		},
-		compositor: state::CompositorState::create(width, height, &config)?,
+		compositor: state::CompositorState::create(&hw.outputs(), &socket.name, &config)?,
+		hw,
 	};
 
-	let socket = std::os::unix::net::UnixListener::bind(&path)?;
-	socket.set_nonblocking(true)?;
+	let compositor = &mut state.compositor;
+
+	compositor.add_global(wl::ShmGlobal)?;
+	compositor.add_global(wl::CompositorGlobal)?;
+	compositor.add_global(wl::SubCompositorGlobal)?;
+	compositor.add_global(wl::DataDeviceManagerGlobal)?;
+	compositor.add_global(wl::SeatGlobal {
+		keymap: compositor.xkb_state.keymap_file,
+	})?;
+
+	for info in state.hw.outputs() {
+		compositor.add_global(wl::OutputGlobal { info })?;
+	}
+
+	compositor.add_global(wl::XdgWmBaseGlobal)?;
+
+	if state.hw.renderer().supports_dmabuf() {
+		compositor.add_global(wl::ZwpLinuxDmabufV1Global { config })?;
+	}
+
+	compositor.add_global(wl::WpPresentationGlobal)?;
+	compositor.add_global(wl::ZwlrLayerShellV1Global)?;
+	compositor.add_global(wl::ZxdgOutputManagerV1Global)?;
 
 	let mut event_loop = elp::EventLoop::create()?;
 
-	backend::drm::attach(&mut event_loop, &mut state)?;
-	backend::input::attach(&mut event_loop, &mut state)?;
+	state.hw.attach(&mut event_loop)?;
+
+	event_loop.on(signals, |signal, state, runtime| {
+		if signal == nix::sys::signal::SIGCHLD {
+			state.compositor.reap_children();
+			return Ok(());
+		}
+
+		eprintln!("received {signal}, shutting down");
+		runtime.stop();
+
+		Ok(())
+	})?;
+
+	event_loop.on_idle(|state, runtime| {
+		state.compositor.process_focus_changes()?;
+
+		// events queued while handling this batch, including the ones sent just above
+		for client in state.compositor.clients.values() {
+			if client.conn.is_waiting_for_writable() {
+				continue;
+			}
+
+			if let connection::Flush::WouldBlock = client.conn.flush() {
+				flush_when_writable(runtime, client)?;
+			}
+		}
+
+		Ok(())
+	});
 
 	event_loop.on(
-		elp::unix_listener::create(socket),
+		elp::unix_listener::create(listener),
 		move |(stream, _), state, runtime| {
 			stream.set_nonblocking(true)?;
-			let conn = Rc::new(Connection::new(stream));
+			let conn = Rc::new(Connection::new(
+				stream,
+				trace.as_deref(),
+				config.client_buffer_limit,
+			)?);
 
 			let fd = conn.as_raw_fd();
-			let mut client = Client::new(fd, Point(0, 0));
+			let mut client = Client::new(conn.clone(), Point(0, 0), &config.client_limits);
 
-			let mut display = wl::Display::new(wl::Id::new(1), conn.clone());
-
-			display.push_global(wl::Shm::new(wl::Id::null(), conn.clone()));
-			display.push_global(wl::Compositor::new(conn.clone()));
-			display.push_global(wl::SubCompositor::new(wl::Id::null(), conn.clone()));
-			display.push_global(wl::DataDeviceManager::new(conn.clone()));
-			display.push_global(wl::Seat::new(
-				wl::Id::null(),
-				conn.clone(),
-				state.compositor.xkb_state.keymap_file,
-			));
-			display.push_global(wl::Output::new(wl::Id::null(), conn.clone()));
-			display.push_global(wl::XdgWmBase::new(wl::Id::null(), conn.clone()));
-			display.push_global(wl::ZwpLinuxDmabufV1::new(
-				wl::Id::null(),
-				conn.clone(),
-				config,
-			)?);
-			display.push_global(wl::WpPresentation::new(wl::Id::null(), conn.clone()));
-			display.push_global(wl::ZwlrLayerShellV1::new(wl::Id::null(), conn.clone()));
-			display.push_global(wl::ZxdgOutputManagerV1::new(
-				wl::Id::null(),
+			let display = wl::Display::new(
+				wl::Id::new(1),
 				conn.clone(),
-				u32::MAX,
-			));
+				state.compositor.globals.clone(),
+			);
 
-			client.ensure_objects_capacity();
-			client.new_object(wl::Id::new(1), display);
+			client.new_object(wl::Id::new(1), display)?;
 
 			state.compositor.clients.insert(fd, client);
 
@@ -134,23 +219,63 @@ fn main() -> Result<()> {
 						params,
 						fds,
 					} => {
-						let client = state.compositor.clients.get_mut(&fd).unwrap();
-						client.received_fds.extend(fds);
-
-						client.ensure_objects_capacity();
+						// disconnected while handling something else
+						let Some(client) = state.compositor.clients.get_mut(&fd) else {
+							return Ok(std::ops::ControlFlow::Break(()));
+						};
 
-						let Some(object) = client.get_resource_mut(object) else {
-							color_eyre::eyre::bail!("unknown object '{object}'");
+						let ret = match client.get_resource(object) {
+							Some(resource) => resource.handle(
+								client,
+								&mut *state.hw,
+								&conn,
+								object,
+								op,
+								params,
+								fds,
+							),
+							None => Err(wl::ProtocolError::invalid_object(object).into()),
 						};
 
-						object.handle(client, &mut state.hw, op, params)?;
+						let ret = ret.and_then(|()| client.check_limits(fds.len()));
 
 						state
 							.compositor
 							.changes
 							.extend(std::mem::take(&mut client.changes));
 
-						state.compositor.process_focus_changes()
+						let Err(err) = ret else {
+							return Ok(std::ops::ControlFlow::Continue(()));
+						};
+
+						let error = wl::ProtocolError::from_report(err, object);
+						eprintln!("client {}: {error}", conn.credentials);
+
+						let ret =
+							client
+								.get_object(wl::Id::<wl::Display>::new(1))
+								.and_then(|display| {
+									display.error(
+										error.object_id.unwrap_or(object),
+										error.code,
+										&error.message,
+									)
+								});
+
+						// it's removed either way
+						if let Err(err) = ret {
+							eprintln!("client {}: failed to post error: {err}", conn.credentials);
+						}
+
+						// the client is going away, so this is the last chance to get the error out
+						let _ = conn.flush();
+
+						state
+							.compositor
+							.changes
+							.push(state::Change::RemoveClient(fd));
+
+						Ok(std::ops::ControlFlow::Break(()))
 					}
 					elp::wl::Message::Closed => {
 						state
@@ -158,7 +283,7 @@ fn main() -> Result<()> {
 							.changes
 							.push(state::Change::RemoveClient(fd));
 
-						state.compositor.process_focus_changes()
+						Ok(std::ops::ControlFlow::Break(()))
 					}
 				},
 			)
@@ -168,7 +293,7 @@ fn main() -> Result<()> {
 	event_loop.run(&mut state)?;
 
 	drop(event_loop);
-	std::fs::remove_file(path)?;
+	drop(socket);
 
 	Ok(())
 }
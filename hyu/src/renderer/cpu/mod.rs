@@ -0,0 +1,429 @@
+use std::os::fd::RawFd;
+
+use color_eyre::eyre::bail;
+
+use crate::{Color, Point, Result, renderer, wl};
+
+const CLEAR_COLOR: u32 = 0xff333333;
+const CURSOR_COLOR: u32 = 0xffccccff;
+
+// ARGB8888 and XRGB8888 are the only wl_shm formats that aren't their drm fourcc
+const FORMAT_ARGB8888: u32 = 0x34325241;
+const FORMAT_XRGB8888: u32 = 0x34325258;
+const FORMAT_ABGR8888: u32 = 0x34324241;
+const FORMAT_XBGR8888: u32 = 0x34324258;
+
+const DMA_BUF_SYNC_READ: u64 = 1 << 0;
+const DMA_BUF_SYNC_START: u64 = 0;
+const DMA_BUF_SYNC_END: u64 = 1 << 2;
+
+#[repr(C)]
+struct DmaBufSync {
+	flags: u64,
+}
+
+/// Pixels are stored as premultiplied ARGB8888.
+#[derive(Clone)]
+pub struct Texture {
+	pub pixels: Vec<u32>,
+	pub opaque: bool,
+}
+
+pub struct Renderer {
+	pub width: usize,
+	pub height: usize,
+	pub framebuffer: Vec<u32>,
+	supports_dmabuf: bool,
+}
+
+pub fn create(width: usize, height: usize, supports_dmabuf: bool) -> Renderer {
+	Renderer {
+		width,
+		height,
+		framebuffer: vec![CLEAR_COLOR; width * height],
+		supports_dmabuf,
+	}
+}
+
+fn fourcc(format: u32) -> u32 {
+	match format {
+		0 => FORMAT_ARGB8888,
+		1 => FORMAT_XRGB8888,
+		x => x,
+	}
+}
+
+fn is_opaque(format: u32) -> bool {
+	matches!(fourcc(format), FORMAT_XRGB8888 | FORMAT_XBGR8888)
+}
+
+fn is_supported(format: u32) -> bool {
+	matches!(
+		fourcc(format),
+		FORMAT_ARGB8888 | FORMAT_XRGB8888 | FORMAT_ABGR8888 | FORMAT_XBGR8888
+	)
+}
+
+fn read_pixel(format: u32, bytes: &[u8], offset: usize) -> u32 {
+	let pixel = u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+
+	let pixel = match fourcc(format) {
+		FORMAT_ABGR8888 | FORMAT_XBGR8888 => {
+			(pixel & 0xff00ff00) | ((pixel & 0xff) << 16) | ((pixel >> 16) & 0xff)
+		}
+		_ => pixel,
+	};
+
+	if is_opaque(format) {
+		pixel | 0xff000000
+	} else {
+		pixel
+	}
+}
+
+/// Porter-Duff "over" for premultiplied pixels.
+fn blend(src: u32, dst: u32) -> u32 {
+	let alpha = src >> 24;
+
+	if alpha == 0xff {
+		return src;
+	}
+
+	let inverse = 0xff - alpha;
+
+	[0, 8, 16, 24].into_iter().fold(0, |acc, shift| {
+		let src = (src >> shift) & 0xff;
+		let dst = (dst >> shift) & 0xff;
+
+		acc | ((src + (dst * inverse + 0x7f) / 0xff).min(0xff) << shift)
+	})
+}
+
+fn dma_buf_sync(fd: RawFd, flags: u64) -> Result<()> {
+	nix::ioctl_write_ptr!(func, b'b', 0, DmaBufSync);
+
+	unsafe {
+		func(fd, &DmaBufSync { flags })?;
+	}
+
+	Ok(())
+}
+
+impl Renderer {
+	pub fn begin(&mut self) {
+		self.framebuffer.fill(CLEAR_COLOR);
+	}
+
+	/// Draws a `size` rectangle at `position`, clipped to the framebuffer. `fetch` is called with
+	/// coordinates relative to the rectangle.
+	fn draw(
+		&mut self,
+		position: Point,
+		size: Point,
+		opaque: bool,
+		fetch: impl Fn(usize, usize) -> u32,
+	) {
+		let left = position.0.max(0);
+		let top = position.1.max(0);
+		let right = (position.0 + size.0).min(self.width as i32);
+		let bottom = (position.1 + size.1).min(self.height as i32);
+
+		if left >= right || top >= bottom {
+			return;
+		}
+
+		for y in top..bottom {
+			let row = &mut self.framebuffer[y as usize * self.width..][..self.width];
+
+			for x in left..right {
+				let src = fetch((x - position.0) as usize, (y - position.1) as usize);
+				let dst = &mut row[x as usize];
+
+				*dst = if opaque { src } else { blend(src, *dst) };
+			}
+		}
+	}
+}
+
+impl renderer::Renderer for Renderer {
+	fn supports_dmabuf(&self) -> bool {
+		self.supports_dmabuf
+	}
+
+	fn upload_shm(
+		&mut self,
+		storage: &wl::ShmBackingStorage,
+		texture: &mut Option<(Point, renderer::Texture)>,
+	) -> Result<()> {
+		if !is_supported(storage.format) {
+			bail!("unsupported shm format {:#x}", storage.format);
+		}
+
+		let map = unsafe { (*storage.map.as_mut_ptr()).as_slice() };
+
+		// the pool checks this too, but a bad buffer must not be able to take the renderer down
+		let bounds = || {
+			let width = usize::try_from(storage.size.0).ok()?;
+			let height = usize::try_from(storage.size.1).ok()?;
+			let offset = usize::try_from(storage.offset).ok()?;
+			let stride = usize::try_from(storage.stride).ok()?;
+
+			let end = stride.checked_mul(height)?.checked_add(offset)?;
+
+			(end <= map.len() && stride >= width.checked_mul(4)?)
+				.then_some((width, height, offset, stride))
+		};
+
+		let Some((width, height, offset, stride)) = bounds() else {
+			bail!("shm buffer is out of bounds of its pool");
+		};
+
+		let mut pixels = match texture.take() {
+			Some((_, renderer::Texture::Cpu(texture))) => texture.pixels,
+			Some(_) => unreachable!(),
+			None => Vec::new(),
+		};
+
+		pixels.clear();
+		pixels.reserve(width * height);
+
+		for y in 0..height {
+			let row = offset + y * stride;
+
+			for x in 0..width {
+				pixels.push(read_pixel(storage.format, map, row + x * 4));
+			}
+		}
+
+		*texture = Some((
+			storage.size,
+			renderer::Texture::Cpu(Texture {
+				pixels,
+				opaque: is_opaque(storage.format),
+			}),
+		));
+
+		Ok(())
+	}
+
+	fn import_dmabuf(&mut self, storage: &mut wl::DmabufBackingStorage) -> Result<()> {
+		let attributes = &storage.attributes;
+
+		if attributes.modifier != 0 || attributes.planes.len() != 1 {
+			bail!("only single plane linear dmabufs can be mapped");
+		}
+
+		if !is_supported(attributes.format) {
+			bail!("unsupported dmabuf format {:#x}", attributes.format);
+		}
+
+		// every row is read whole, the bounds of the dmabuf itself are checked by the caller
+		let stride = attributes.planes[0].stride as u64;
+
+		if stride < attributes.width as u64 * 4 {
+			bail!(
+				"dmabuf stride {stride} is too small for width {}",
+				attributes.width
+			);
+		}
+
+		Ok(())
+	}
+
+	fn destroy_texture(&mut self, _texture: renderer::Texture) {}
+
+	fn draw_texture(
+		&mut self,
+		position: Point,
+		size: Point,
+		texture: &renderer::Texture,
+	) -> Result<()> {
+		let renderer::Texture::Cpu(texture) = texture else {
+			unreachable!();
+		};
+
+		let width = size.0 as usize;
+		self.draw(position, size, texture.opaque, |x, y| {
+			texture.pixels[y * width + x]
+		});
+
+		Ok(())
+	}
+
+	fn draw_dmabuf(&mut self, position: Point, storage: &wl::DmabufBackingStorage) -> Result<()> {
+		let attributes = &storage.attributes;
+		let plane = &attributes.planes[0];
+
+		let offset = plane.offset as usize;
+		let stride = plane.stride as usize;
+
+		let Some(size) = stride
+			.checked_mul(attributes.height as usize)
+			.and_then(|x| x.checked_add(offset))
+			.and_then(std::num::NonZeroUsize::new)
+		else {
+			bail!("dmabuf has an invalid size");
+		};
+
+		let ptr = unsafe {
+			nix::sys::mman::mmap(
+				None,
+				size,
+				nix::sys::mman::ProtFlags::PROT_READ,
+				nix::sys::mman::MapFlags::MAP_SHARED,
+				std::os::fd::BorrowedFd::borrow_raw(plane.fd),
+				0,
+			)?
+		};
+
+		// not every exporter implements the sync ioctl
+		let _ = dma_buf_sync(plane.fd, DMA_BUF_SYNC_START | DMA_BUF_SYNC_READ);
+
+		let size = size.get();
+		let bytes = unsafe { std::slice::from_raw_parts(ptr.as_ptr() as *const u8, size) };
+		let format = attributes.format;
+
+		self.draw(position, storage.size, is_opaque(format), |x, y| {
+			read_pixel(format, bytes, offset + y * stride + x * 4)
+		});
+
+		let _ = dma_buf_sync(plane.fd, DMA_BUF_SYNC_END | DMA_BUF_SYNC_READ);
+
+		unsafe {
+			nix::sys::mman::munmap(ptr, size)?;
+		}
+
+		Ok(())
+	}
+
+	fn draw_cursor(&mut self, position: Point) -> Result<()> {
+		self.draw(position, Point(2, 2), true, |_, _| CURSOR_COLOR);
+		Ok(())
+	}
+
+	fn draw_rect(&mut self, position: Point, size: Point, color: Color) -> Result<()> {
+		let [r, g, b, a] = color.map(u32::from);
+		let premultiply = |x: u32| x * a / 0xff;
+
+		let pixel = (a << 24) | (premultiply(r) << 16) | (premultiply(g) << 8) | premultiply(b);
+
+		self.draw(position, size, a == 0xff, |_, _| pixel);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use renderer::Renderer as _;
+
+	const RED: Color = [0xff, 0, 0, 0xff];
+
+	fn pixel(renderer: &Renderer, x: usize, y: usize) -> u32 {
+		renderer.framebuffer[y * renderer.width + x]
+	}
+
+	/// Positions of the pixels that aren't the clear color, row by row.
+	fn drawn(renderer: &Renderer) -> Vec<(usize, usize)> {
+		(0..renderer.height)
+			.flat_map(|y| (0..renderer.width).map(move |x| (x, y)))
+			.filter(|&(x, y)| pixel(renderer, x, y) != CLEAR_COLOR)
+			.collect()
+	}
+
+	#[test]
+	fn blend_is_premultiplied_over() {
+		assert_eq!(blend(0xff112233, 0xff445566), 0xff112233);
+		assert_eq!(blend(0x00000000, 0xff445566), 0xff445566);
+
+		// half transparent red over opaque blue
+		assert_eq!(blend(0x80400000, 0xff0000ff), 0xff40007f);
+
+		// over a transparent destination the source stays as it is
+		assert_eq!(blend(0x80402010, 0x00000000), 0x80402010);
+
+		// the channels can't overflow into each other
+		assert_eq!(blend(0x80ffffff, 0xffffffff), 0xffffffff);
+	}
+
+	#[test]
+	fn draw_clips_negative_positions() {
+		let mut renderer = create(4, 3, false);
+		renderer.draw_rect(Point(-2, -1), Point(4, 3), RED).unwrap();
+
+		assert_eq!(drawn(&renderer), [(0, 0), (1, 0), (0, 1), (1, 1)]);
+	}
+
+	#[test]
+	fn draw_clips_oversized_rects() {
+		let mut renderer = create(4, 3, false);
+		renderer
+			.draw_rect(Point(2, 1), Point(100, 100), RED)
+			.unwrap();
+
+		assert_eq!(drawn(&renderer), [(2, 1), (3, 1), (2, 2), (3, 2)]);
+
+		let mut renderer = create(4, 3, false);
+		renderer
+			.draw_rect(Point(-10, -10), Point(100, 100), RED)
+			.unwrap();
+
+		assert_eq!(drawn(&renderer).len(), 4 * 3);
+	}
+
+	#[test]
+	fn draw_skips_rects_outside_of_the_framebuffer() {
+		let mut renderer = create(4, 3, false);
+
+		renderer.draw_rect(Point(4, 0), Point(2, 2), RED).unwrap();
+		renderer.draw_rect(Point(0, -2), Point(2, 2), RED).unwrap();
+		renderer.draw_rect(Point(1, 1), Point(-1, 1), RED).unwrap();
+
+		assert!(drawn(&renderer).is_empty());
+	}
+
+	#[test]
+	fn draw_fetches_relative_to_the_rect() {
+		let mut renderer = create(4, 3, false);
+		renderer.draw(Point(-1, -2), Point(3, 3), true, |x, y| (y * 16 + x) as u32);
+
+		assert_eq!(pixel(&renderer, 0, 0), 0x21);
+		assert_eq!(pixel(&renderer, 1, 0), 0x22);
+		assert_eq!(pixel(&renderer, 2, 0), CLEAR_COLOR);
+		assert_eq!(pixel(&renderer, 0, 1), CLEAR_COLOR);
+	}
+
+	#[test]
+	fn xrgb_is_opaque() {
+		let bytes = 0x00112233u32.to_le_bytes();
+
+		// 1 is the wl_shm value, the rest are drm fourccs
+		assert_eq!(read_pixel(1, &bytes, 0), 0xff112233);
+		assert_eq!(read_pixel(FORMAT_XRGB8888, &bytes, 0), 0xff112233);
+		assert_eq!(read_pixel(0, &bytes, 0), 0x00112233);
+
+		assert!(is_opaque(1));
+		assert!(is_opaque(FORMAT_XBGR8888));
+		assert!(!is_opaque(0));
+		assert!(!is_opaque(FORMAT_ABGR8888));
+	}
+
+	#[test]
+	fn abgr_is_swizzled() {
+		// red 0x11, green 0x22, blue 0x33, alpha 0x44
+		let bytes = [0x11, 0x22, 0x33, 0x44];
+
+		assert_eq!(read_pixel(FORMAT_ABGR8888, &bytes, 0), 0x44112233);
+		assert_eq!(read_pixel(FORMAT_XBGR8888, &bytes, 0), 0xff112233);
+
+		// in ARGB the bytes are blue, green, red, alpha
+		assert_eq!(read_pixel(0, &bytes, 0), 0x44332211);
+	}
+
+	#[test]
+	fn read_pixel_uses_the_offset() {
+		let bytes = [0, 0, 0, 0, 0x11, 0x22, 0x33, 0x44];
+
+		assert_eq!(read_pixel(0, &bytes, 4), 0x44332211);
+	}
+}
@@ -1 +1,58 @@
+pub mod cpu;
 pub mod vulkan;
+
+use crate::{Color, Point, Result, wl};
+
+pub enum Texture {
+	Vulkan(vulkan::Texture),
+	Cpu(cpu::Texture),
+}
+
+pub trait Renderer {
+	/// Whether clients get offered `zwp_linux_dmabuf_v1`.
+	fn supports_dmabuf(&self) -> bool;
+
+	/// Copies the contents of `storage` into `texture`, creating the texture if it's `None`.
+	fn upload_shm(
+		&mut self,
+		storage: &wl::ShmBackingStorage,
+		texture: &mut Option<(Point, Texture)>,
+	) -> Result<()>;
+
+	fn import_dmabuf(&mut self, storage: &mut wl::DmabufBackingStorage) -> Result<()>;
+
+	fn destroy_texture(&mut self, texture: Texture);
+
+	fn draw_texture(&mut self, position: Point, size: Point, texture: &Texture) -> Result<()>;
+
+	fn draw_dmabuf(&mut self, position: Point, storage: &wl::DmabufBackingStorage) -> Result<()>;
+
+	fn draw_cursor(&mut self, position: Point) -> Result<()>;
+
+	fn draw_rect(&mut self, position: Point, size: Point, color: Color) -> Result<()>;
+
+	/// Draws a frame of `width` around the outside of `position` and `size`.
+	fn draw_border(
+		&mut self,
+		position: Point,
+		size: Point,
+		width: i32,
+		color: Color,
+	) -> Result<()> {
+		let outer = position - Point(width, width);
+		let outer_width = size.0 + width * 2;
+
+		self.draw_rect(outer, Point(outer_width, width), color)?;
+		self.draw_rect(
+			Point(outer.0, position.1 + size.1),
+			Point(outer_width, width),
+			color,
+		)?;
+		self.draw_rect(Point(outer.0, position.1), Point(width, size.1), color)?;
+		self.draw_rect(
+			Point(position.0 + size.0, position.1),
+			Point(width, size.1),
+			color,
+		)
+	}
+}
@@ -1,6 +1,6 @@
-use color_eyre::eyre::OptionExt as _;
+use color_eyre::eyre::{OptionExt as _, bail};
 
-use crate::{Point, Result, gbm};
+use crate::{Color, Point, Result, gbm, renderer, wl};
 
 #[repr(C)]
 #[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable, Debug)]
@@ -75,6 +75,8 @@ pub struct Renderer {
 	pub textures_to_delete: Vec<Texture>,
 
 	pub cursor_texture: Texture,
+	/// 1x1 textures that solid rectangles are drawn with.
+	pub color_textures: std::collections::HashMap<Color, Texture>,
 	pub semaphore_fd: Option<std::os::fd::RawFd>,
 	pub fence: Option<ash::vk::Fence>,
 }
@@ -456,6 +458,7 @@ pub fn create(card: impl AsRef<std::path::Path>) -> Result<Renderer> {
 			buffer_size: 0,
 			buffer_ptr: std::ptr::null_mut(),
 		},
+		color_textures: Default::default(),
 		semaphore_fd: None,
 		fence: None,
 	})
@@ -827,20 +830,10 @@ impl Renderer {
 			}
 		}
 
-		for texture in &self.textures_to_delete {
-			unsafe {
-				self.device.free_memory(texture.buffer_device_memory, None);
-				self.device.destroy_buffer(texture.buffer, None);
-
-				self.device.destroy_image_view(texture.image_view, None);
-
-				self.device.free_memory(texture.image_device_memory, None);
-				self.device.destroy_image(texture.image, None);
-			}
+		for texture in std::mem::take(&mut self.textures_to_delete) {
+			self.free_texture(&texture);
 		}
 
-		self.textures_to_delete.clear();
-
 		let ret;
 
 		let command_buffers = [{
@@ -1366,4 +1359,149 @@ impl Renderer {
 		self.textures.clear();
 		Ok(())
 	}
+
+	/// The texture must not be in use by the GPU anymore.
+	fn free_texture(&self, texture: &Texture) {
+		unsafe {
+			self.device.free_memory(texture.buffer_device_memory, None);
+			self.device.destroy_buffer(texture.buffer, None);
+
+			self.device.destroy_image_view(texture.image_view, None);
+
+			self.device.free_memory(texture.image_device_memory, None);
+			self.device.destroy_image(texture.image, None);
+		}
+	}
+}
+
+impl Drop for Renderer {
+	fn drop(&mut self) {
+		// the last frame might still be reading from them
+		let _ = unsafe { self.device.device_wait_idle() };
+
+		for texture in std::mem::take(&mut self.textures_to_delete) {
+			self.free_texture(&texture);
+		}
+
+		for (_, texture) in std::mem::take(&mut self.color_textures) {
+			self.free_texture(&texture);
+		}
+	}
+}
+
+impl renderer::Renderer for Renderer {
+	fn supports_dmabuf(&self) -> bool {
+		true
+	}
+
+	fn upload_shm(
+		&mut self,
+		storage: &wl::ShmBackingStorage,
+		texture: &mut Option<(Point, renderer::Texture)>,
+	) -> Result<()> {
+		let mut output = match texture.take() {
+			Some((size, renderer::Texture::Vulkan(texture))) => Some((size, texture)),
+			Some(_) => unreachable!(),
+			None => None,
+		};
+
+		storage.copy_into_texture(self, &mut output)?;
+
+		*texture = output.map(|(size, texture)| (size, renderer::Texture::Vulkan(texture)));
+		Ok(())
+	}
+
+	fn import_dmabuf(&mut self, storage: &mut wl::DmabufBackingStorage) -> Result<()> {
+		let attributes = &storage.attributes;
+
+		let format = match attributes.format {
+			0x34325241 | 0x34325258 => ash::vk::Format::B8G8R8A8_UNORM,
+			0x34324241 => ash::vk::Format::R8G8B8A8_UNORM,
+			x => bail!("unsupported dmabuf format {x:#x}"),
+		};
+
+		(storage.image, storage.image_view) = self.create_image_from_dmabuf(
+			attributes.width,
+			attributes.height,
+			format,
+			attributes.modifier,
+			&attributes.planes,
+		)?;
+
+		Ok(())
+	}
+
+	fn destroy_texture(&mut self, texture: renderer::Texture) {
+		let renderer::Texture::Vulkan(texture) = texture else {
+			unreachable!();
+		};
+
+		self.textures_to_delete.push(texture);
+	}
+
+	fn draw_texture(
+		&mut self,
+		position: Point,
+		size: Point,
+		texture: &renderer::Texture,
+	) -> Result<()> {
+		let renderer::Texture::Vulkan(texture) = texture else {
+			unreachable!();
+		};
+
+		self.record_quad(position, size, texture)
+	}
+
+	fn draw_dmabuf(&mut self, position: Point, storage: &wl::DmabufBackingStorage) -> Result<()> {
+		let texture = Texture {
+			image: storage.image,
+			image_view: storage.image_view,
+			image_device_memory: ash::vk::DeviceMemory::null(),
+			image_layout: ash::vk::ImageLayout::GENERAL,
+			buffer: ash::vk::Buffer::null(),
+			buffer_device_memory: ash::vk::DeviceMemory::null(),
+			buffer_size: 0,
+			buffer_ptr: std::ptr::null_mut(),
+		};
+
+		self.record_quad(position, storage.size, &texture)
+	}
+
+	fn draw_cursor(&mut self, position: Point) -> Result<()> {
+		let cursor_texture = self.cursor_texture.clone();
+		self.record_quad(position, Point(2, 2), &cursor_texture)
+	}
+
+	fn draw_rect(&mut self, position: Point, size: Point, color: Color) -> Result<()> {
+		if let Some(texture) = self.color_textures.get(&color) {
+			let texture = texture.clone();
+			return self.record_quad(position, size, &texture);
+		}
+
+		let (image, image_device_memory, image_view) =
+			Self::create_image(&self.device, &self.instance, self.physical_device, 1, 1)?;
+
+		let [r, g, b, a] = color.map(|x| x as f32 / 255.0);
+		Self::clear_image(
+			&self.device,
+			self.queue,
+			self.command_pool,
+			image,
+			(r, g, b, a),
+		)?;
+
+		let texture = Texture {
+			image,
+			image_device_memory,
+			image_view,
+			image_layout: ash::vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+			buffer: ash::vk::Buffer::null(),
+			buffer_device_memory: ash::vk::DeviceMemory::null(),
+			buffer_size: 0,
+			buffer_ptr: std::ptr::null_mut(),
+		};
+
+		self.color_textures.insert(color, texture.clone());
+		self.record_quad(position, size, &texture)
+	}
 }
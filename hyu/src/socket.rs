@@ -0,0 +1,85 @@
+use std::os::unix::fs::OpenOptionsExt as _;
+
+use color_eyre::eyre::bail;
+
+use crate::Result;
+
+/// libwayland gives up after the same amount of tries.
+const MAX_DISPLAYS: u32 = 32;
+
+/// A claimed Wayland socket. The socket and its lock file are removed when this is dropped.
+pub struct Socket {
+	pub name: String,
+	path: std::path::PathBuf,
+	lock_path: std::path::PathBuf,
+	_lock: nix::fcntl::Flock<std::fs::File>,
+}
+
+impl Drop for Socket {
+	fn drop(&mut self) {
+		let _ = std::fs::remove_file(&self.path);
+		let _ = std::fs::remove_file(&self.lock_path);
+	}
+}
+
+/// Binds `name` in `runtime_dir`, or the first free `wayland-N` if there's no name given.
+pub fn bind(
+	runtime_dir: &std::path::Path,
+	name: Option<&str>,
+) -> Result<(Socket, std::os::unix::net::UnixListener)> {
+	if let Some(name) = name {
+		let Some(ret) = try_bind(runtime_dir, name)? else {
+			bail!("socket '{name}' is already in use");
+		};
+
+		return Ok(ret);
+	}
+
+	for index in 0..MAX_DISPLAYS {
+		if let Some(ret) = try_bind(runtime_dir, &format!("wayland-{index}"))? {
+			return Ok(ret);
+		}
+	}
+
+	bail!("no free socket in {}", runtime_dir.display());
+}
+
+/// Returns `None` if another compositor holds the lock of `name`.
+fn try_bind(
+	runtime_dir: &std::path::Path,
+	name: &str,
+) -> Result<Option<(Socket, std::os::unix::net::UnixListener)>> {
+	let path = runtime_dir.join(name);
+	let lock_path = runtime_dir.join(format!("{name}.lock"));
+
+	let file = std::fs::OpenOptions::new()
+		.read(true)
+		.write(true)
+		.create(true)
+		.truncate(false)
+		.mode(0o660)
+		.open(&lock_path)?;
+
+	let lock = match nix::fcntl::Flock::lock(file, nix::fcntl::FlockArg::LockExclusiveNonblock) {
+		Ok(lock) => lock,
+		Err((_, nix::errno::Errno::EWOULDBLOCK)) => return Ok(None),
+		Err((_, err)) => return Err(err)?,
+	};
+
+	// whoever created it is gone, otherwise they would still hold the lock
+	if path.exists() {
+		std::fs::remove_file(&path)?;
+	}
+
+	let listener = std::os::unix::net::UnixListener::bind(&path)?;
+
+	Ok(Some((
+		Socket {
+			name: name.to_owned(),
+			path,
+			lock_path,
+			_lock: lock,
+		},
+		listener,
+	)))
+}
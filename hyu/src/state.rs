@@ -4,9 +4,10 @@ use std::{
 };
 
 use color_eyre::eyre::OptionExt as _;
+use wlm::protocol::xdg_toplevel;
 
 use crate::{
-	Client, Config, Point, Result,
+	Client, Config, Point, Result, layout,
 	renderer::{self},
 	wl, xkb,
 };
@@ -19,8 +20,64 @@ pub enum Change {
 	Pick(std::os::fd::RawFd, wl::Id<wl::XdgToplevel>),
 	MoveDown(std::os::fd::RawFd, wl::Id<wl::XdgToplevel>),
 	MoveUp(std::os::fd::RawFd, wl::Id<wl::XdgToplevel>),
+	/// Something about the layout changed, the windows have to be arranged again.
+	Relayout,
+	SwitchWorkspace(usize),
+	MoveToWorkspace(std::os::fd::RawFd, wl::Id<wl::XdgToplevel>, usize),
+	/// The serial has to be the one of the button press that's still being held.
+	StartGrab(std::os::fd::RawFd, wl::Id<wl::XdgToplevel>, u32, GrabKind),
 }
 
+#[derive(Clone, Copy)]
+pub enum GrabKind {
+	Move,
+	/// Holds the `resize_edge` that's being dragged.
+	Resize(u32),
+}
+
+/// A window that's being moved or resized with the pointer, until the button is released.
+pub struct Grab {
+	pub fd: std::os::fd::RawFd,
+	pub toplevel: wl::Id<wl::XdgToplevel>,
+	pub kind: GrabKind,
+	/// Where the pointer and the window were when the grab started.
+	pub pointer_start: Point,
+	pub window_start: layout::Rect,
+	last_configure: std::time::Instant,
+}
+
+impl Grab {
+	/// Moves the dragged edges by how far the pointer has moved, the opposite ones stay put.
+	fn resize(&self, edges: u32, pointer: Point) -> layout::Rect {
+		let delta = pointer - self.pointer_start;
+		let (start, size) = self.window_start;
+		let end = start + size;
+
+		let (mut start, mut end) = (start, end);
+
+		if edges & xdg_toplevel::resize_edge::LEFT != 0 {
+			start.0 = (start.0 + delta.0).min(end.0 - 1);
+		}
+
+		if edges & xdg_toplevel::resize_edge::RIGHT != 0 {
+			end.0 = (end.0 + delta.0).max(start.0 + 1);
+		}
+
+		if edges & xdg_toplevel::resize_edge::TOP != 0 {
+			start.1 = (start.1 + delta.1).min(end.1 - 1);
+		}
+
+		if edges & xdg_toplevel::resize_edge::BOTTOM != 0 {
+			end.1 = (end.1 + delta.1).max(start.1 + 1);
+		}
+
+		(start, end - start)
+	}
+}
+
+/// Clients get at most one configure per this long while being resized.
+const RESIZE_CONFIGURE_INTERVAL: std::time::Duration = std::time::Duration::from_millis(16);
+
 #[derive(Clone, Copy)]
 pub struct PointerOver {
 	pub fd: std::os::fd::RawFd,
@@ -36,31 +93,83 @@ pub struct XkbState {
 	pub keymap_file: (std::os::fd::RawFd, u64),
 }
 
-pub struct HwState {
-	pub drm: crate::backend::drm::State,
-	pub input: crate::backend::input::State,
+pub struct Output {
+	/// Position and size in compositor coordinates.
+	pub area: layout::Rect,
+}
+
+/// Windows on hidden workspaces aren't rendered, don't get frame callbacks and can't be under
+/// the pointer.
+pub struct Workspace {
+	pub windows: Vec<std::rc::Rc<(std::os::fd::RawFd, wl::Id<wl::XdgToplevel>)>>,
+	/// Remembered while the workspace is hidden, so it can get focus back.
+	pub focused_window: Option<std::rc::Weak<(std::os::fd::RawFd, wl::Id<wl::XdgToplevel>)>>,
+	pub layouts: layout::Layouts,
+}
+
+impl Workspace {
+	pub fn new(config: &Config) -> Self {
+		Self {
+			windows: Vec::new(),
+			focused_window: None,
+			layouts: layout::Layouts::new(config),
+		}
+	}
+
+	pub fn get_focused_window(&self) -> Option<(std::os::fd::RawFd, wl::Id<wl::XdgToplevel>)> {
+		self.focused_window
+			.as_ref()
+			.and_then(|x| x.upgrade())
+			.map(|x| *x)
+	}
+
+	fn position(&self, window: (std::os::fd::RawFd, wl::Id<wl::XdgToplevel>)) -> Option<usize> {
+		self.windows.iter().position(|x| **x == window)
+	}
+
+	/// Focuses the first window if the focused one went away.
+	fn fix_focus(&mut self) {
+		if self.get_focused_window().is_none() {
+			self.focused_window = self.windows.first().map(std::rc::Rc::downgrade);
+		}
+	}
 }
 
+const WORKSPACE_COUNT: usize = 9;
+
+pub type HwState = dyn crate::backend::Backend;
+
 pub struct State {
-	pub hw: HwState,
+	pub hw: Box<HwState>,
 	pub compositor: CompositorState,
 }
 
 pub struct CompositorState {
 	pub clients: std::collections::HashMap<std::os::fd::RawFd, Client>,
-	pub windows: Vec<std::rc::Rc<(std::os::fd::RawFd, wl::Id<wl::XdgToplevel>)>>,
-	pub focused_window: Option<std::rc::Weak<(std::os::fd::RawFd, wl::Id<wl::XdgToplevel>)>>,
+	pub workspaces: Vec<Workspace>,
+	pub current_workspace: usize,
 	pub changes: Vec<Change>,
 	pub pointer_over: Option<PointerOver>,
+	/// Client and serial of the button press that's being held.
+	pub button_serial: Option<(std::os::fd::RawFd, u32)>,
+	pub grab: Option<Grab>,
 	pub pointer_position: Point,
 	pub xkb_state: XkbState,
-	pub width: u16,
-	pub height: u16,
+	pub outputs: Vec<Output>,
 	pub config: &'static Config,
+	/// Exported as `WAYLAND_DISPLAY` to spawned programs.
+	pub socket_name: String,
+	/// Programs spawned by keybinds, until they exit.
+	pub children: Vec<std::process::Child>,
+	pub globals: std::rc::Rc<std::cell::RefCell<wl::Globals>>,
 }
 
 impl CompositorState {
-	pub fn create(width: u16, height: u16, config: &'static Config) -> Result<Self> {
+	pub fn create(
+		outputs: &[wl::OutputInfo],
+		socket_name: &str,
+		config: &'static Config,
+	) -> Result<Self> {
 		let xkb_context = xkb::Context::create().ok_or_eyre("failed to create xkb context")?;
 
 		let xkb_keymap = xkb::Keymap::create(&xkb_context, &config.keymap)
@@ -79,10 +188,14 @@ impl CompositorState {
 
 		Ok(Self {
 			clients: Default::default(),
-			windows: Default::default(),
-			focused_window: Default::default(),
+			workspaces: (0..WORKSPACE_COUNT)
+				.map(|_| Workspace::new(config))
+				.collect(),
+			current_workspace: 0,
 			changes: Default::default(),
 			pointer_over: Default::default(),
+			button_serial: None,
+			grab: None,
 			pointer_position: Default::default(),
 			xkb_state: XkbState {
 				context: xkb_context,
@@ -90,40 +203,138 @@ impl CompositorState {
 				state: xkb_state,
 				keymap_file: (fd, size),
 			},
-			width,
-			height,
+			outputs: outputs
+				.iter()
+				.map(|x| Output {
+					area: (x.position, x.size),
+				})
+				.collect(),
 			config,
+			socket_name: socket_name.to_owned(),
+			children: Vec::new(),
+			globals: Default::default(),
 		})
 	}
 
+	/// Adds a global and announces it to every registry that's already bound.
+	pub fn add_global(&mut self, global: impl wl::Global + 'static) -> Result<u32> {
+		let name = self.globals.borrow_mut().insert(global);
+
+		let globals = self.globals.borrow();
+		let global = globals.get(name).unwrap();
+
+		for client in self.clients.values() {
+			for registry in client.objects_mut::<wl::Registry>()? {
+				registry.global(name, global.get_name(), global.get_version())?;
+			}
+		}
+
+		Ok(name)
+	}
+
+	/// Announces that a global is gone. Clients that bind it before they've seen that still get
+	/// an object, see `wl::Globals`.
+	pub fn remove_global(&mut self, name: u32) -> Result<()> {
+		if self.globals.borrow().get(name).is_none() {
+			color_eyre::eyre::bail!("global {name} does not exist");
+		}
+
+		let mut told = Vec::new();
+
+		for (&fd, client) in &self.clients {
+			let registries = client.objects_mut::<wl::Registry>()?;
+
+			for registry in &registries {
+				registry.global_remove(name)?;
+			}
+
+			if !registries.is_empty() {
+				told.push(fd);
+			}
+		}
+
+		self.globals.borrow_mut().remove(name, told);
+
+		Ok(())
+	}
+
+	pub fn reap_children(&mut self) {
+		self.children.retain_mut(|child| match child.try_wait() {
+			Ok(Some(status)) => {
+				eprintln!("child {} exited with {status}", child.id());
+				false
+			}
+			Ok(None) => true,
+			Err(err) => {
+				eprintln!("failed to wait for child {}: {err}", child.id());
+				false
+			}
+		});
+	}
+
 	pub fn process_focus_changes(&mut self) -> Result<()> {
+		// clients that fail while the changes are applied get disconnected, which is another change
+		while !self.changes.is_empty() {
+			self.apply_changes()?;
+		}
+
+		Ok(())
+	}
+
+	/// Runs `f` with the client, if it's still connected. If `f` fails the client is disconnected,
+	/// so one misbehaving client can't take the compositor down with it.
+	fn with_client(&mut self, fd: std::os::fd::RawFd, f: impl FnOnce(&mut Client) -> Result<()>) {
+		let Some(client) = self.clients.get_mut(&fd) else {
+			return;
+		};
+
+		if let Err(err) = f(client) {
+			eprintln!("client {}: {err}", client.conn.credentials);
+			self.changes.push(Change::RemoveClient(fd));
+		}
+	}
+
+	fn apply_changes(&mut self) -> Result<()> {
 		let old = self.get_focused_window();
 
 		let mut should_leave_from_old = false;
 
 		let changes = std::mem::take(&mut self.changes);
-		let should_recompute_size_and_pos = !changes.is_empty();
 
 		for (i, change) in changes.into_iter().enumerate() {
 			let x = match change {
 				Change::Push(fd, id) => {
-					let rc = std::rc::Rc::new((fd, id));
+					// it might have been destroyed again, or its client removed, in the same batch
+					let Some(mut toplevel) = self
+						.clients
+						.get(&fd)
+						.and_then(|x| x.get_object_mut(id).ok())
+					else {
+						continue;
+					};
 
-					self.windows.insert(0, rc.clone());
-					self.focused_window = Some(std::rc::Rc::downgrade(&rc));
+					toplevel.add_state(xdg_toplevel::state::MAXIMIZED);
 
-					let client = self.clients.get_mut(&fd).unwrap();
+					let rc = std::rc::Rc::new((fd, id));
+					let workspace = &mut self.workspaces[self.current_workspace];
 
-					let toplevel = client.get_object_mut(id)?;
-					toplevel.add_state(1);
+					workspace.windows.insert(0, rc.clone());
+					workspace.focused_window = Some(std::rc::Rc::downgrade(&rc));
 
 					true
 				}
 				Change::RemoveToplevel(fd, id) => {
-					self.windows.retain(|x| **x != (fd, id));
+					for workspace in &mut self.workspaces {
+						workspace.windows.retain(|x| **x != (fd, id));
+						workspace.fix_focus();
+					}
 
-					if self.get_focused_window().is_none() {
-						self.focused_window = self.windows.first().map(std::rc::Rc::downgrade);
+					if self
+						.grab
+						.as_ref()
+						.is_some_and(|x| x.fd == fd && x.toplevel == id)
+					{
+						self.grab = None;
 					}
 
 					if let Some(value) = &self.pointer_over {
@@ -144,11 +355,24 @@ impl CompositorState {
 					false
 				}
 				Change::RemoveClient(fd) => {
-					self.windows.retain(|x| x.0 != fd);
-					self.clients.remove(&fd);
+					for workspace in &mut self.workspaces {
+						workspace.windows.retain(|x| x.0 != fd);
+						workspace.fix_focus();
+					}
+
+					// the source waiting for it to be writable holds a duplicate of the socket
+					if let Some(client) = self.clients.remove(&fd) {
+						client.conn.shutdown();
+					}
+
+					self.globals.borrow_mut().remove_client(fd);
 
-					if self.get_focused_window().is_none() {
-						self.focused_window = self.windows.first().map(std::rc::Rc::downgrade);
+					if self.grab.as_ref().is_some_and(|x| x.fd == fd) {
+						self.grab = None;
+					}
+
+					if self.button_serial.is_some_and(|x| x.0 == fd) {
+						self.button_serial = None;
 					}
 
 					if let Some(value) = &self.pointer_over {
@@ -160,41 +384,35 @@ impl CompositorState {
 					false
 				}
 				Change::Pick(fd, toplevel) => {
-					self.focused_window = self
-						.windows
-						.iter()
-						.find(|x| ***x == (fd, toplevel))
-						.map(std::rc::Rc::downgrade);
+					let workspace = &mut self.workspaces[self.current_workspace];
+
+					// removed in the same batch
+					let Some(window) = workspace.windows.iter().find(|x| ***x == (fd, toplevel))
+					else {
+						continue;
+					};
 
-					assert!(self.focused_window.is_some());
+					workspace.focused_window = Some(std::rc::Rc::downgrade(window));
 					true
 				}
 				Change::MoveDown(fd, xdg_toplevel) => {
-					let Some(index) = self
-						.windows
-						.iter()
-						.enumerate()
-						.find(|(_, x)| ***x == (fd, xdg_toplevel))
-						.map(|(x, _)| x)
-					else {
+					let workspace = &mut self.workspaces[self.current_workspace];
+
+					let Some(index) = workspace.position((fd, xdg_toplevel)) else {
 						continue;
 					};
 
-					if index >= (self.windows.len() - 1) {
+					if index >= (workspace.windows.len() - 1) {
 						continue;
 					};
 
-					self.windows.swap(index, index + 1);
+					workspace.windows.swap(index, index + 1);
 					false
 				}
 				Change::MoveUp(fd, xdg_toplevel) => {
-					let Some(index) = self
-						.windows
-						.iter()
-						.enumerate()
-						.find(|(_, x)| ***x == (fd, xdg_toplevel))
-						.map(|(x, _)| x)
-					else {
+					let workspace = &mut self.workspaces[self.current_workspace];
+
+					let Some(index) = workspace.position((fd, xdg_toplevel)) else {
 						continue;
 					};
 
@@ -202,7 +420,83 @@ impl CompositorState {
 						continue;
 					};
 
-					self.windows.swap(index, index - 1);
+					workspace.windows.swap(index, index - 1);
+					false
+				}
+				Change::Relayout => false,
+				Change::SwitchWorkspace(index) => {
+					if index == self.current_workspace {
+						continue;
+					}
+
+					self.current_workspace = index;
+					true
+				}
+				Change::MoveToWorkspace(fd, xdg_toplevel, index) => {
+					if index == self.current_workspace {
+						continue;
+					}
+
+					let workspace = &mut self.workspaces[self.current_workspace];
+
+					let Some(position) = workspace.position((fd, xdg_toplevel)) else {
+						continue;
+					};
+
+					let rc = workspace.windows.remove(position);
+					workspace.fix_focus();
+
+					let target = &mut self.workspaces[index];
+
+					target.windows.insert(0, rc.clone());
+					target.focused_window = Some(std::rc::Rc::downgrade(&rc));
+
+					true
+				}
+				Change::StartGrab(fd, xdg_toplevel, serial, kind) => {
+					if self.grab.is_some() || self.button_serial != Some((fd, serial)) {
+						continue;
+					}
+
+					let workspace = &mut self.workspaces[self.current_workspace];
+
+					let Some(index) = workspace.position((fd, xdg_toplevel)) else {
+						continue;
+					};
+
+					let Some(mut toplevel) = self
+						.clients
+						.get(&fd)
+						.and_then(|x| x.get_object_mut(xdg_toplevel).ok())
+					else {
+						continue;
+					};
+
+					// raised above the other floating windows
+					let rc = workspace.windows.remove(index);
+					workspace.windows.insert(0, rc);
+
+					toplevel.set_floating(true);
+
+					if let GrabKind::Resize(_) = kind {
+						toplevel.add_state(xdg_toplevel::state::RESIZING);
+					}
+
+					self.grab = Some(Grab {
+						fd,
+						toplevel: xdg_toplevel,
+						kind,
+						pointer_start: self.pointer_position,
+						window_start: (toplevel.position, toplevel.size.unwrap_or_default()),
+						last_configure: std::time::Instant::now(),
+					});
+
+					drop(toplevel);
+
+					self.with_client(fd, |client| {
+						client.get_object(xdg_toplevel)?.configure(client)
+					});
+
 					false
 				}
 			};
@@ -214,91 +508,89 @@ impl CompositorState {
 
 		let current = self.get_focused_window();
 
-		if !should_recompute_size_and_pos {
-			return Ok(());
-		}
+		// windows aren't assigned to outputs yet, they're all tiled on the first one
+		let output = &self.outputs[0];
+		let workspace = &self.workspaces[self.current_workspace];
 
-		const GAP: i32 = 0;
+		// half of the inner gap goes on each side of a window, so the outer gap makes up for it
+		let half_gap = self.config.inner_gap / 2;
+		let area = layout::inset(output.area, self.config.outer_gap - half_gap);
 
-		let width = self.width as i32;
-		let height = self.height as i32;
+		let mut tiled = Vec::new();
 
-		let get_pos_and_size = |index: u32, amount: u32| -> (Point, Point) {
-			match amount {
-				0 => {
-					unreachable!();
-				}
-				1 => (
-					Point(0 + GAP, 0 + GAP),
-					Point(width - GAP * 2, height - GAP * 2),
-				),
-				2.. => match index {
-					0 => (
-						Point(0 + GAP, 0 + GAP),
-						Point(width / 2 - GAP * 2, height - GAP * 2),
-					),
-					1.. => {
-						let frac = ((1. / (amount - 1) as f32) * height as f32) as i32;
-						(
-							Point(width / 2 + GAP, frac * (index as i32 - 1) + GAP),
-							Point(width / 2 - GAP * 2, frac - GAP * 2),
-						)
-					}
-				},
+		for window in workspace.windows.iter().map(|x| **x) {
+			if !self.is_floating(window) {
+				tiled.push(window);
 			}
-		};
+		}
 
-		let mut leave = None;
-		let mut enter = None;
+		let rects = workspace.layouts.current().arrange(tiled.len(), area);
 
-		for (index, (fd, xdg_toplevel)) in self.windows.iter().map(|x| **x).enumerate() {
-			let client = self.clients.get_mut(&fd).unwrap();
-			let xdg_toplevel = client.get_object_mut(xdg_toplevel)?;
+		// the old window might have been moved to a hidden workspace, it still has to leave. there
+		// might not be a new one either, when the workspace that's shown now is empty
+		let leave = old.filter(|&old| {
+			should_leave_from_old
+				&& Some(old) != current
+				&& self.workspaces.iter().any(|x| x.position(old).is_some())
+		});
 
-			let (pos, size) = get_pos_and_size(index as _, self.windows.len() as _);
+		// the client only gets what's inside of the gap and the border
+		let inset = half_gap + self.config.border_width;
 
-			xdg_toplevel.position = pos;
-			xdg_toplevel.size = Some(size);
+		for ((fd, xdg_toplevel), rect) in tiled.into_iter().zip(rects) {
+			// these get configured below, together with their new state
+			let configure =
+				leave != Some((fd, xdg_toplevel)) && current != Some((fd, xdg_toplevel));
 
-			let xdg_surface = client.get_object(xdg_toplevel.surface)?;
-			let surface = client.get_object(xdg_surface.surface)?;
+			self.with_client(fd, |client| {
+				let mut xdg_toplevel = client.get_object_mut(xdg_toplevel)?;
 
-			if should_leave_from_old && old == Some((fd, xdg_toplevel.object_id)) {
-				leave = Some((fd, xdg_toplevel.object_id, surface.object_id));
-				continue;
-			} else if current == Some((fd, xdg_toplevel.object_id)) {
-				enter = Some((fd, xdg_toplevel.object_id, surface.object_id));
-				continue;
-			}
+				let (pos, size) = layout::inset(rect, inset);
 
-			xdg_toplevel.configure(client)?;
+				xdg_toplevel.position = pos;
+				xdg_toplevel.size = Some(size);
+
+				if configure {
+					xdg_toplevel.configure(client)?;
+				}
+
+				Ok(())
+			});
 		}
 
-		if let Some((fd, xdg_toplevel, surface)) = leave {
-			let client = self.clients.get_mut(&fd).unwrap();
-			let xdg_toplevel = client.get_object_mut(xdg_toplevel)?;
+		if let Some((fd, xdg_toplevel)) = leave {
+			self.with_client(fd, |client| {
+				let mut xdg_toplevel = client.get_object_mut(xdg_toplevel)?;
 
-			for keyboard in client.objects_mut::<wl::Keyboard>() {
-				keyboard.leave(client, surface)?;
-			}
+				// the xdg_surface can't stay borrowed, configuring goes through it
+				let surface = client.get_object(xdg_toplevel.surface)?.surface;
 
-			xdg_toplevel.remove_state(4);
-			xdg_toplevel.configure(client)?;
-		}
+				for mut keyboard in client.objects_mut::<wl::Keyboard>()? {
+					keyboard.leave(client, surface)?;
+				}
 
-		if let Some((fd, xdg_toplevel, surface)) = enter {
-			let client = self.clients.get_mut(&fd).unwrap();
-			let xdg_toplevel = client.get_object_mut(xdg_toplevel)?;
+				xdg_toplevel.remove_state(xdg_toplevel::state::ACTIVATED);
+				xdg_toplevel.configure(client)
+			});
+		}
 
+		if let Some((fd, xdg_toplevel)) = current {
 			let depressed = self.xkb_state.state.serialize_mods(1);
 
-			for keyboard in client.objects_mut::<wl::Keyboard>() {
-				keyboard.enter(client, surface)?;
-				keyboard.modifiers(client, depressed)?;
-			}
+			self.with_client(fd, |client| {
+				let mut xdg_toplevel = client.get_object_mut(xdg_toplevel)?;
+
+				let surface = client.get_object(xdg_toplevel.surface)?.surface;
 
-			xdg_toplevel.add_state(4);
-			xdg_toplevel.configure(client)?;
+				for mut keyboard in client.objects_mut::<wl::Keyboard>()? {
+					keyboard.enter(client, surface)?;
+					keyboard.modifiers(client, depressed)?;
+				}
+
+				xdg_toplevel.add_state(xdg_toplevel::state::ACTIVATED);
+				xdg_toplevel.urgent = false;
+				xdg_toplevel.configure(client)
+			});
 		}
 
 		self.calculate_pointer_focus()
@@ -308,115 +600,122 @@ impl CompositorState {
 		let old = self.pointer_over;
 		let mut new = None;
 
-		'outer: for (fd, xdg_toplevel) in self.windows.iter().map(|x| **x) {
-			let client = self.clients.get_mut(&fd).unwrap();
-
-			fn is_cursor_over_surface(
-				client: &mut Client,
-				cursor_position: Point,
-				surface_position: Point,
-				surface: &wl::Surface,
-			) -> Result<bool> {
-				if let Some(input_region) = &surface.current.input_region {
-					for area in &input_region.areas {
-						let position = surface_position + area.0;
-
-						if cursor_position.is_inside((position, area.1)) {
-							return Ok(true);
-						}
+		fn is_cursor_over_surface(
+			client: &mut Client,
+			cursor_position: Point,
+			surface_position: Point,
+			surface: &wl::Surface,
+		) -> Result<bool> {
+			if let Some(input_region) = &surface.current.input_region {
+				for area in &input_region.areas {
+					let position = surface_position + area.0;
+
+					if cursor_position.is_inside((position, area.1)) {
+						return Ok(true);
 					}
+				}
 
-					Ok(false)
-				} else {
-					match &surface.render_texture {
-						wl::SurfaceRenderTexture::None => Ok(false),
-						&wl::SurfaceRenderTexture::UnattachedShmCopy((size, _)) => {
-							Ok(cursor_position.is_inside((surface_position, size)))
-						}
-						wl::SurfaceRenderTexture::AttachedDmabuf(attached_buffer) => {
-							let wl_buffer = client.get_object(attached_buffer.wl_buffer_id)?;
-							let wl::BufferBackingStorage::Dmabuf(dmabuf_backing_storage) =
-								&wl_buffer.backing_storage
-							else {
-								panic!();
-							};
+				Ok(false)
+			} else {
+				match &surface.render_texture {
+					wl::SurfaceRenderTexture::None => Ok(false),
+					&wl::SurfaceRenderTexture::UnattachedShmCopy((size, _)) => {
+						Ok(cursor_position.is_inside((surface_position, size)))
+					}
+					wl::SurfaceRenderTexture::AttachedDmabuf(attached_buffer) => {
+						let wl_buffer = client.get_object(attached_buffer.wl_buffer_id)?;
+						let wl::BufferBackingStorage::Dmabuf(dmabuf_backing_storage) =
+							&wl_buffer.backing_storage
+						else {
+							panic!();
+						};
 
-							Ok(cursor_position
-								.is_inside((surface_position, dmabuf_backing_storage.size)))
-						}
+						Ok(cursor_position
+							.is_inside((surface_position, dmabuf_backing_storage.size)))
 					}
 				}
 			}
+		}
 
-			fn recurse(
-				pointer_over: &mut Option<PointerOver>,
-				client: &mut Client,
-				toplevel: &wl::XdgToplevel,
-				surface: &wl::Surface,
-				cursor_position: Point,
-				surface_position: Point,
-			) -> Result<()> {
-				if is_cursor_over_surface(client, cursor_position, surface_position, surface)? {
-					*pointer_over = Some(PointerOver {
-						fd: client.fd,
-						toplevel: toplevel.object_id,
-						surface: surface.object_id,
-						position: cursor_position - surface_position,
-					});
-				}
+		fn recurse(
+			pointer_over: &mut Option<PointerOver>,
+			client: &mut Client,
+			toplevel: &wl::XdgToplevel,
+			surface: &wl::Surface,
+			cursor_position: Point,
+			surface_position: Point,
+		) -> Result<()> {
+			if is_cursor_over_surface(client, cursor_position, surface_position, surface)? {
+				*pointer_over = Some(PointerOver {
+					fd: client.fd,
+					toplevel: toplevel.object_id,
+					surface: surface.object_id,
+					position: cursor_position - surface_position,
+				});
+			}
 
-				for child in &surface.children {
-					let sub_surface = client.get_object(*child).unwrap();
-					let surface = client.get_object(sub_surface.surface)?;
+			for child in &surface.children {
+				let sub_surface = client.get_object(*child).unwrap();
+				let surface = client.get_object(sub_surface.surface)?;
 
-					recurse(
-						pointer_over,
-						client,
-						toplevel,
-						surface,
-						cursor_position,
-						surface_position + sub_surface.position,
-					)?;
-				}
-
-				Ok(())
+				recurse(
+					pointer_over,
+					client,
+					toplevel,
+					&surface,
+					cursor_position,
+					surface_position + sub_surface.position,
+				)?;
 			}
 
-			let toplevel = client.get_object(xdg_toplevel)?;
-			let xdg_surface = client.get_object(toplevel.surface)?;
-			let surface = client.get_object(xdg_surface.surface)?;
+			Ok(())
+		}
 
-			let position = toplevel.position - xdg_surface.position;
+		let pointer_position = self.pointer_position;
 
-			for &popup in &xdg_surface.popups {
-				let popup = client.get_object(popup)?;
-				let xdg_surface = client.get_object(popup.xdg_surface)?;
+		// top to bottom
+		for (fd, xdg_toplevel) in self.stacking_order().into_iter().rev() {
+			self.with_client(fd, |client| {
+				// destroyed, the change that removes it just hasn't been processed yet
+				let Ok(toplevel) = client.get_object(xdg_toplevel) else {
+					return Ok(());
+				};
+
+				let xdg_surface = client.get_object(toplevel.surface)?;
 				let surface = client.get_object(xdg_surface.surface)?;
 
-				let position = (position - xdg_surface.position) + popup.position;
+				let position = toplevel.position - xdg_surface.position;
+
+				for &popup in &xdg_surface.popups {
+					let popup = client.get_object(popup)?;
+					let xdg_surface = client.get_object(popup.xdg_surface)?;
+					let surface = client.get_object(xdg_surface.surface)?;
+
+					let position = (position - xdg_surface.position) + popup.position;
+
+					recurse(
+						&mut new,
+						client,
+						&toplevel,
+						&surface,
+						pointer_position,
+						position,
+					)?;
+
+					if new.is_some() {
+						return Ok(());
+					}
+				}
 
 				recurse(
 					&mut new,
 					client,
-					toplevel,
-					surface,
-					self.pointer_position,
+					&toplevel,
+					&surface,
+					pointer_position,
 					position,
-				)?;
-
-				if new.is_some() {
-					break 'outer;
-				}
-			}
-
-			recurse(
-				&mut new,
-				client,
-				toplevel,
-				surface,
-				self.pointer_position,
-				position,
-			)?;
+				)
+			});
 
 			if new.is_some() {
 				break;
@@ -425,19 +724,21 @@ impl CompositorState {
 
 		if old.map(|x| (x.fd, x.surface)) != new.map(|x| (x.fd, x.surface)) {
 			if let Some(PointerOver { fd, surface, .. }) = old {
-				let client = self.clients.get_mut(&fd).unwrap();
-				let mut pointers = client.objects_mut::<wl::Pointer>();
+				self.with_client(fd, |client| {
+					let mut pointers = client.objects_mut::<wl::Pointer>()?;
 
-				let display = client.get_object_mut(wl::Id::<wl::Display>::new(1))?;
-				let serial = display.new_serial();
+					let serial = client.new_serial();
 
-				for pointer in &mut pointers {
-					pointer.leave(serial, surface)?;
-				}
+					for pointer in &mut pointers {
+						pointer.leave(serial, surface)?;
+					}
 
-				for pointer in &mut pointers {
-					pointer.frame()?;
-				}
+					for pointer in &mut pointers {
+						pointer.frame()?;
+					}
+
+					Ok(())
+				});
 			}
 
 			if let Some(PointerOver {
@@ -447,33 +748,38 @@ impl CompositorState {
 				..
 			}) = new
 			{
-				let client = self.clients.get_mut(&fd).unwrap();
-				let mut pointers = client.objects_mut::<wl::Pointer>();
+				self.with_client(fd, |client| {
+					let mut pointers = client.objects_mut::<wl::Pointer>()?;
 
-				let display = client.get_object_mut(wl::Id::<wl::Display>::new(1))?;
-				let serial = display.new_serial();
+					let serial = client.new_serial();
+
+					for pointer in &mut pointers {
+						pointer.enter(serial, surface, position)?;
+					}
+
+					for pointer in &mut pointers {
+						pointer.frame()?;
+					}
+
+					Ok(())
+				});
+			}
+		} else if let Some(PointerOver { fd, position, .. }) = new
+			&& old.map(|x| x.position) != Some(position)
+		{
+			self.with_client(fd, |client| {
+				let mut pointers = client.objects_mut::<wl::Pointer>()?;
 
 				for pointer in &mut pointers {
-					pointer.enter(serial, surface, position)?;
+					pointer.motion(client, position)?;
 				}
 
 				for pointer in &mut pointers {
 					pointer.frame()?;
 				}
-			}
-		} else if old.map(|x| x.position) != new.map(|x| x.position) {
-			let PointerOver { fd, position, .. } = new.unwrap();
 
-			let client = self.clients.get_mut(&fd).unwrap();
-			let mut pointers = client.objects_mut::<wl::Pointer>();
-
-			for pointer in &mut pointers {
-				pointer.motion(client, position)?;
-			}
-
-			for pointer in &mut pointers {
-				pointer.frame()?;
-			}
+				Ok(())
+			});
 		}
 
 		self.pointer_over = new;
@@ -484,29 +790,95 @@ impl CompositorState {
 		let cursor_position = Point(cursor_position.0, cursor_position.1);
 
 		self.pointer_position = cursor_position;
+
+		if self.grab.is_some() {
+			return self.update_grab();
+		}
+
+		self.calculate_pointer_focus()
+	}
+
+	fn update_grab(&mut self) -> Result<()> {
+		let Some(mut grab) = self.grab.take() else {
+			return Ok(());
+		};
+
+		let pointer_position = self.pointer_position;
+
+		self.with_client(grab.fd, |client| {
+			let mut toplevel = client.get_object_mut(grab.toplevel)?;
+
+			match grab.kind {
+				GrabKind::Move => {
+					toplevel.position =
+						grab.window_start.0 + (pointer_position - grab.pointer_start);
+				}
+				GrabKind::Resize(edges) => {
+					let (position, size) = grab.resize(edges, pointer_position);
+
+					toplevel.position = position;
+					toplevel.size = Some(size);
+
+					if grab.last_configure.elapsed() >= RESIZE_CONFIGURE_INTERVAL {
+						grab.last_configure = std::time::Instant::now();
+						toplevel.configure(client)?;
+					}
+				}
+			}
+
+			Ok(())
+		});
+
+		// removing the client or the window ends the grab
+		self.grab = Some(grab);
+		Ok(())
+	}
+
+	fn end_grab(&mut self) -> Result<()> {
+		let Some(grab) = self.grab.take() else {
+			return Ok(());
+		};
+
+		if let GrabKind::Resize(_) = grab.kind {
+			self.with_client(grab.fd, |client| {
+				let mut toplevel = client.get_object_mut(grab.toplevel)?;
+
+				// the last size might have been throttled
+				toplevel.remove_state(xdg_toplevel::state::RESIZING);
+				toplevel.configure(client)
+			});
+		}
+
 		self.calculate_pointer_focus()
 	}
 
 	pub fn on_mouse_button(&mut self, button: u32, input_state: u32) -> Result<()> {
+		// the client doesn't see the buttons of its own grab
+		if self.grab.is_some() {
+			if input_state == 0 {
+				self.button_serial = None;
+				self.end_grab()?;
+			}
+
+			return Ok(());
+		}
+
 		if let Some(PointerOver { fd, toplevel, .. }) = self.pointer_over {
 			let client = self.clients.get_mut(&fd).unwrap();
 
-			let display = client.get_object_mut(wl::Id::<wl::Display>::new(1))?;
-			let serial = display.new_serial();
+			let serial = client.new_serial();
 
-			for pointer in client.objects_mut::<wl::Pointer>() {
+			for mut pointer in client.objects_mut::<wl::Pointer>()? {
 				pointer.button(client, serial, button, input_state)?;
 			}
 
-			for pointer in client.objects_mut::<wl::Pointer>() {
+			self.button_serial = (input_state == 1).then_some((fd, serial));
+
+			for mut pointer in client.objects_mut::<wl::Pointer>()? {
 				pointer.frame()?;
 			}
 
-			let Some(focused_window) = self.get_focused_window() else {
-				panic!();
-			};
-
-			if focused_window != (fd, toplevel) {
+			if self.get_focused_window() != Some((fd, toplevel)) {
 				self.changes.push(Change::Pick(fd, toplevel));
 			}
 
@@ -520,13 +892,13 @@ impl CompositorState {
 		if let Some(PointerOver { fd, .. }) = self.pointer_over {
 			let client = self.clients.get_mut(&fd).unwrap();
 
-			for pointer in client.objects_mut::<wl::Pointer>() {
+			for mut pointer in client.objects_mut::<wl::Pointer>()? {
 				pointer.axis_source(0)?;
 				pointer.axis_discrete(axis, discrete)?;
 				pointer.axis(client, axis, value)?;
 			}
 
-			for pointer in client.objects_mut::<wl::Pointer>() {
+			for mut pointer in client.objects_mut::<wl::Pointer>()? {
 				pointer.frame()?;
 			}
 		}
@@ -545,28 +917,94 @@ impl CompositorState {
 			}
 
 			if code == 20 && input_state == 1 {
-				std::process::Command::new("foot")
-					.env("WAYLAND_DISPLAY", "wayland-1")
+				let child = std::process::Command::new("foot")
+					.env("WAYLAND_DISPLAY", &self.socket_name)
 					.stdout(std::process::Stdio::null())
 					.stderr(std::process::Stdio::null())
-					.spawn()
-					.unwrap();
+					.spawn()?;
+
+				self.children.push(child);
+
+				return Ok(());
+			}
+
+			if code == 33 && input_state == 1 {
+				if let Some((fd, xdg_toplevel)) = self.get_focused_window() {
+					self.with_client(fd, |client| {
+						// destroyed, the change that removes it just hasn't been processed yet
+						let Ok(mut xdg_toplevel) = client.get_object_mut(xdg_toplevel) else {
+							return Ok(());
+						};
+
+						let floating = xdg_toplevel.floating;
+						xdg_toplevel.set_floating(!floating);
+
+						Ok(())
+					});
 
+					self.changes.push(Change::Relayout);
+				}
+
+				self.process_focus_changes()?;
 				return Ok(());
 			}
 
 			if code == 46 && input_state == 1 {
 				if let Some((fd, xdg_toplevel)) = self.get_focused_window() {
-					let client = self.clients.get_mut(&fd).unwrap();
-					let xdg_toplevel = client.get_object(xdg_toplevel)?;
+					self.with_client(fd, |client| {
+						// destroyed, the change that removes it just hasn't been processed yet
+						let Ok(xdg_toplevel) = client.get_object(xdg_toplevel) else {
+							return Ok(());
+						};
 
-					xdg_toplevel.close()?;
+						xdg_toplevel.close()
+					});
 				}
 
 				return Ok(());
 			}
 
+			// space, h, l, i, d
+			if matches!(code, 57 | 35 | 38 | 23 | 32) && input_state == 1 {
+				let layouts = &mut self.workspaces[self.current_workspace].layouts;
+
+				match code {
+					57 => {
+						layouts.cycle();
+						eprintln!("layout: {}", layouts.current().name());
+					}
+					35 => layouts.current_mut().resize_master(-0.05),
+					38 => layouts.current_mut().resize_master(0.05),
+					23 => layouts.current_mut().change_master_count(1),
+					_ => layouts.current_mut().change_master_count(-1),
+				}
+
+				self.changes.push(Change::Relayout);
+				self.process_focus_changes()?;
+
+				return Ok(());
+			}
+
+			// 1 to 9
+			let workspace = (2..2 + WORKSPACE_COUNT as u32)
+				.contains(&code)
+				.then(|| (code - 2) as usize);
+
 			if (depressed & 1) != 0 {
+				if let Some(workspace) = workspace
+					&& input_state == 1
+				{
+					let Some((fd, xdg_toplevel)) = self.get_focused_window() else {
+						return Ok(());
+					};
+
+					self.changes
+						.push(Change::MoveToWorkspace(fd, xdg_toplevel, workspace));
+					self.process_focus_changes()?;
+
+					return Ok(());
+				}
+
 				if code == 36 && input_state == 1 {
 					let Some((fd, xdg_toplevel)) = self.get_focused_window() else {
 						return Ok(());
@@ -590,26 +1028,31 @@ impl CompositorState {
 				}
 			}
 
+			if let Some(workspace) = workspace
+				&& input_state == 1
+			{
+				self.changes.push(Change::SwitchWorkspace(workspace));
+				self.process_focus_changes()?;
+
+				return Ok(());
+			}
+
 			if code == 36 && input_state == 1 {
 				let Some((fd, xdg_toplevel)) = self.get_focused_window() else {
 					return Ok(());
 				};
 
-				let Some(index) = self
-					.windows
-					.iter()
-					.enumerate()
-					.find(|(_, x)| ***x == (fd, xdg_toplevel))
-					.map(|(x, _)| x)
-				else {
+				let workspace = self.workspace();
+
+				let Some(index) = workspace.position((fd, xdg_toplevel)) else {
 					return Ok(());
 				};
 
-				if index >= (self.windows.len() - 1) {
+				if index >= (workspace.windows.len() - 1) {
 					return Ok(());
 				};
 
-				let Some((fd, xdg_toplevel)) = self.windows.get(index + 1).map(|x| **x) else {
+				let Some((fd, xdg_toplevel)) = workspace.windows.get(index + 1).map(|x| **x) else {
 					panic!();
 				};
 
@@ -624,13 +1067,9 @@ impl CompositorState {
 					return Ok(());
 				};
 
-				let Some(index) = self
-					.windows
-					.iter()
-					.enumerate()
-					.find(|(_, x)| ***x == (fd, xdg_toplevel))
-					.map(|(x, _)| x)
-				else {
+				let workspace = self.workspace();
+
+				let Some(index) = workspace.position((fd, xdg_toplevel)) else {
 					return Ok(());
 				};
 
@@ -638,7 +1077,7 @@ impl CompositorState {
 					return Ok(());
 				};
 
-				let Some((fd, xdg_toplevel)) = self.windows.get(index - 1).map(|x| **x) else {
+				let Some((fd, xdg_toplevel)) = workspace.windows.get(index - 1).map(|x| **x) else {
 					panic!();
 				};
 
@@ -654,7 +1093,7 @@ impl CompositorState {
 		if let Some((fd, _)) = self.get_focused_window() {
 			let client = self.clients.get_mut(&fd).unwrap();
 
-			for keyboard in client.objects_mut::<wl::Keyboard>() {
+			for mut keyboard in client.objects_mut::<wl::Keyboard>()? {
 				keyboard.key(client, code, input_state)?;
 				keyboard.modifiers(client, depressed)?;
 			}
@@ -663,104 +1102,153 @@ impl CompositorState {
 		Ok(())
 	}
 
+	/// The workspace that's being shown.
+	pub fn workspace(&self) -> &Workspace {
+		&self.workspaces[self.current_workspace]
+	}
+
 	pub fn get_focused_window(&self) -> Option<(std::os::fd::RawFd, wl::Id<wl::XdgToplevel>)> {
-		self.focused_window
-			.as_ref()
-			.and_then(|x| x.upgrade())
-			.map(|x| *x)
+		self.workspace().get_focused_window()
 	}
 
-	pub fn render(&mut self, vk: &mut renderer::vulkan::Renderer) -> Result<()> {
-		for (fd, xdg_toplevel) in self.windows.iter().map(|x| **x) {
-			let client = self.clients.get_mut(&fd).unwrap();
+	fn is_floating(
+		&self,
+		(fd, xdg_toplevel): (std::os::fd::RawFd, wl::Id<wl::XdgToplevel>),
+	) -> bool {
+		self.clients
+			.get(&fd)
+			.and_then(|x| x.get_object(xdg_toplevel).ok())
+			.is_some_and(|x| x.floating)
+	}
 
-			let mut draw = |client: &mut Client,
-			                toplevel_position: Point,
-			                xdg_surface: &wl::XdgSurface,
-			                surface: &mut wl::Surface|
-			 -> Result<()> {
-				for (position, surface_id) in surface.get_front_buffers(client)? {
-					let surface = client.get_object_mut(surface_id)?;
+	/// Windows on the current workspace from the bottom up. The focused tiled window is above the
+	/// other tiled ones, and floating ones are above those, the most recently raised at the top.
+	fn stacking_order(&self) -> Vec<(std::os::fd::RawFd, wl::Id<wl::XdgToplevel>)> {
+		let focused_window = self.get_focused_window();
 
-					match &surface.render_texture {
-						wl::SurfaceRenderTexture::None => {
-							panic!();
-						}
-						wl::SurfaceRenderTexture::UnattachedShmCopy((size, texture)) => {
-							vk.record_quad(
-								toplevel_position - xdg_surface.position + position,
-								*size,
-								texture,
-							)?;
-						}
-						wl::SurfaceRenderTexture::AttachedDmabuf(attached_buffer) => {
-							let wl_buffer = client.get_object(attached_buffer.wl_buffer_id)?;
+		let mut tiled = Vec::new();
+		let mut floating = Vec::new();
+
+		for window in self.workspace().windows.iter().map(|x| **x) {
+			if self.is_floating(window) {
+				floating.push(window);
+			} else {
+				tiled.push(window);
+			}
+		}
+
+		// tiled windows only overlap in monocle, where the focused one has to be on top
+		if let Some(position) = tiled.iter().position(|x| Some(*x) == focused_window) {
+			let window = tiled.remove(position);
+			tiled.push(window);
+		}
+
+		tiled.extend(floating.into_iter().rev());
+		tiled
+	}
+
+	pub fn render(&mut self, renderer: &mut dyn renderer::Renderer) -> Result<()> {
+		let focused_window = self.get_focused_window();
+		let config = self.config;
+
+		for (fd, xdg_toplevel) in self.stacking_order() {
+			self.with_client(fd, |client| {
+				// destroyed, the change that removes it just hasn't been processed yet
+				let Ok(toplevel) = client.get_object(xdg_toplevel) else {
+					return Ok(());
+				};
+
+				if config.border_width > 0 {
+					let colors = &config.border_colors;
+
+					let color = if toplevel.urgent {
+						colors.urgent
+					} else if focused_window == Some((fd, xdg_toplevel)) {
+						colors.focused
+					} else {
+						colors.unfocused
+					};
 
-							let wl::BufferBackingStorage::Dmabuf(dmabuf_baking_storage) =
-								&wl_buffer.backing_storage
-							else {
+					renderer.draw_border(
+						toplevel.position,
+						toplevel.size.unwrap_or_default(),
+						config.border_width,
+						color,
+					)?;
+				}
+
+				let mut draw = |client: &mut Client,
+				                toplevel_position: Point,
+				                xdg_surface: &wl::XdgSurface|
+				 -> Result<()> {
+					let front_buffers = client
+						.get_object(xdg_surface.surface)?
+						.get_front_buffers(client)?;
+
+					for (position, surface_id) in front_buffers {
+						let mut surface = client.get_object_mut(surface_id)?;
+						let surface = &mut *surface;
+
+						match &surface.render_texture {
+							wl::SurfaceRenderTexture::None => {
 								panic!();
-							};
-
-							let size = dmabuf_baking_storage.size;
-							let texture = renderer::vulkan::Texture {
-								image: dmabuf_baking_storage.image,
-								image_view: dmabuf_baking_storage.image_view,
-								image_device_memory: ash::vk::DeviceMemory::null(),
-								image_layout: ash::vk::ImageLayout::GENERAL,
-								buffer: ash::vk::Buffer::null(),
-								buffer_device_memory: ash::vk::DeviceMemory::null(),
-								buffer_size: 0,
-								buffer_ptr: std::ptr::null_mut(),
-							};
-
-							vk.record_quad(
-								toplevel_position - xdg_surface.position + position,
-								size,
-								&texture,
-							)?;
-
-							eprintln!(
-								"surface({})::render_buffer({})",
-								*surface.object_id, *attached_buffer.wl_buffer_id
-							);
-
-							if let Some(currently_renderer_buffer) = std::mem::replace(
-								&mut surface.currently_rendered_buffer,
-								Some(attached_buffer.clone()),
-							) {
-								currently_renderer_buffer.release(client)?;
+							}
+							wl::SurfaceRenderTexture::UnattachedShmCopy((size, texture)) => {
+								renderer.draw_texture(
+									toplevel_position - xdg_surface.position + position,
+									*size,
+									texture,
+								)?;
+							}
+							wl::SurfaceRenderTexture::AttachedDmabuf(attached_buffer) => {
+								let wl_buffer = client.get_object(attached_buffer.wl_buffer_id)?;
+
+								let wl::BufferBackingStorage::Dmabuf(dmabuf_baking_storage) =
+									&wl_buffer.backing_storage
+								else {
+									panic!();
+								};
+
+								renderer.draw_dmabuf(
+									toplevel_position - xdg_surface.position + position,
+									dmabuf_baking_storage,
+								)?;
+
+								if let Some(currently_renderer_buffer) = std::mem::replace(
+									&mut surface.currently_rendered_buffer,
+									Some(attached_buffer.clone()),
+								) {
+									currently_renderer_buffer.release(client)?;
+								}
 							}
 						}
 					}
-				}
 
-				Ok(())
-			};
+					Ok(())
+				};
 
-			let toplevel = client.get_object(xdg_toplevel)?;
+				let xdg_surface = client.get_object(toplevel.surface)?;
 
-			let xdg_surface = client.get_object(toplevel.surface)?;
-			let surface = client.get_object_mut(xdg_surface.surface)?;
+				draw(client, toplevel.position, &xdg_surface)?;
 
-			draw(client, toplevel.position, xdg_surface, surface)?;
+				for &popup in &xdg_surface.popups {
+					let popup = client.get_object(popup)?;
 
-			for &popup in &xdg_surface.popups {
-				let popup = client.get_object(popup)?;
+					let xdg_surface = client.get_object(popup.xdg_surface)?;
 
-				let xdg_surface = client.get_object(popup.xdg_surface)?;
-				let surface = client.get_object_mut(xdg_surface.surface)?;
+					let position = toplevel.position + popup.position;
 
-				let position = toplevel.position + popup.position;
+					draw(client, position, &xdg_surface)?;
+				}
 
-				draw(client, position, xdg_surface, surface)?;
-			}
+				Ok(())
+			});
 		}
 
 		let should_hide_cursor = if let Some(a) = &self.pointer_over {
 			let client = self.clients.get(&a.fd).unwrap();
 			client
-				.objects_mut::<wl::Pointer>()
+				.objects_mut::<wl::Pointer>()?
 				.iter()
 				.fold(false, |acc, x| acc | x.should_hide_cursor)
 		} else {
@@ -768,9 +1256,7 @@ impl CompositorState {
 		};
 
 		if !should_hide_cursor {
-			let cursor_pos = self.pointer_position;
-			let cursor_texture = vk.cursor_texture.clone();
-			vk.record_quad(cursor_pos, Point(2, 2), &cursor_texture)?;
+			renderer.draw_cursor(self.pointer_position)?;
 		}
 
 		Ok(())
@@ -783,48 +1269,246 @@ impl CompositorState {
 		sequence: u32,
 		flags: u32,
 	) -> Result<()> {
-		for (fd, xdg_toplevel) in self.windows.iter().map(|x| **x) {
-			let client = self.clients.get_mut(&fd).unwrap();
-			let display = client.get_object(wl::Id::<wl::Display>::new(1))?;
-
-			let frame = |client: &mut Client, surface: &mut wl::Surface| -> Result<()> {
-				if let Some(currently_rendered_buffer) =
-					std::mem::take(&mut surface.currently_rendered_buffer)
-				{
-					if let Some(old_displayed_buffer) = std::mem::replace(
-						&mut surface.currently_displaying_buffer,
-						Some(currently_rendered_buffer),
-					) {
-						old_displayed_buffer.release(client)?;
+		// hidden workspaces don't get frame callbacks, so their clients stop drawing
+		let windows = self
+			.workspace()
+			.windows
+			.iter()
+			.map(|x| **x)
+			.collect::<Vec<_>>();
+
+		for (fd, xdg_toplevel) in windows {
+			self.with_client(fd, |client| {
+				let frame = |client: &mut Client, surface: &mut wl::Surface| -> Result<()> {
+					if let Some(currently_rendered_buffer) =
+						std::mem::take(&mut surface.currently_rendered_buffer)
+					{
+						if let Some(old_displayed_buffer) = std::mem::replace(
+							&mut surface.currently_displaying_buffer,
+							Some(currently_rendered_buffer),
+						) {
+							old_displayed_buffer.release(client)?;
+						}
 					}
-				}
 
-				surface.frame(display.get_time().as_millis() as u32, client)?;
-				surface.presentation_feedback(
-					duration,
-					till_next_refresh,
-					sequence as _,
-					flags,
-					client,
-				)
-			};
+					surface.frame(client.get_time().as_millis() as u32, client)?;
+					surface.presentation_feedback(
+						duration,
+						till_next_refresh,
+						sequence as _,
+						flags,
+						client,
+					)
+				};
+
+				let Ok(toplevel) = client.get_object(xdg_toplevel) else {
+					return Ok(());
+				};
 
-			let toplevel = client.get_object(xdg_toplevel)?;
-			let xdg_surface = client.get_object(toplevel.surface)?;
-			let surface = client.get_object_mut(xdg_surface.surface)?;
+				let xdg_surface = client.get_object(toplevel.surface)?;
+				let mut surface = client.get_object_mut(xdg_surface.surface)?;
 
-			frame(client, surface)?;
+				frame(client, &mut surface)?;
 
-			for &popup in &xdg_surface.popups {
-				let popup = client.get_object(popup)?;
+				for &popup in &xdg_surface.popups {
+					let popup = client.get_object(popup)?;
 
-				let xdg_surface = client.get_object(popup.xdg_surface)?;
-				let surface = client.get_object_mut(xdg_surface.surface)?;
+					let xdg_surface = client.get_object(popup.xdg_surface)?;
+					let mut surface = client.get_object_mut(xdg_surface.surface)?;
 
-				frame(client, surface)?;
-			}
+					frame(client, &mut surface)?;
+				}
+
+				Ok(())
+			});
 		}
 
 		Ok(())
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn compositor() -> CompositorState {
+		let config: &'static Config = Box::leak(Box::default());
+
+		let output = wl::OutputInfo {
+			position: Point(0, 0),
+			size: Point(1920, 1080),
+			refresh: 60_000,
+			physical_size: Point(0, 0),
+			make: String::new(),
+			model: String::new(),
+		};
+
+		CompositorState::create(&[output], "wayland-test", config).unwrap()
+	}
+
+	/// Connects a client with one toplevel. The other end of its socket is returned, the events
+	/// sent to the client end up there.
+	fn add_toplevel(
+		state: &mut CompositorState,
+	) -> (
+		std::os::unix::net::UnixStream,
+		(std::os::fd::RawFd, wl::Id<wl::XdgToplevel>),
+	) {
+		let (stream, peer) = std::os::unix::net::UnixStream::pair().unwrap();
+		let conn = std::rc::Rc::new(crate::Connection::new(stream, None, usize::MAX).unwrap());
+		let fd = conn.as_raw_fd();
+
+		let mut client = Client::new(conn.clone(), Point(0, 0), &state.config.client_limits);
+
+		let surface = wl::Id::new(1);
+		let xdg_surface = wl::Id::new(2);
+		let toplevel = wl::Id::new(3);
+
+		client
+			.new_object(surface, wl::Surface::new(surface, conn.clone()))
+			.unwrap();
+		client
+			.new_object(
+				xdg_surface,
+				wl::XdgSurface::new(xdg_surface, conn.clone(), surface),
+			)
+			.unwrap();
+
+		let object =
+			wl::XdgToplevel::new(&mut client, toplevel, conn, xdg_surface, Point(0, 0), fd);
+		client.new_object(toplevel, object).unwrap();
+
+		state.changes.append(&mut client.changes);
+		state.clients.insert(fd, client);
+		state.process_focus_changes().unwrap();
+
+		(peer, (fd, toplevel))
+	}
+
+	fn is_activated(
+		state: &CompositorState,
+		(fd, toplevel): (std::os::fd::RawFd, wl::Id<wl::XdgToplevel>),
+	) -> bool {
+		state.clients[&fd]
+			.get_object(toplevel)
+			.unwrap()
+			.states
+			.contains(&xdg_toplevel::state::ACTIVATED)
+	}
+
+	#[test]
+	fn switching_to_an_empty_workspace_deactivates_the_window() {
+		let mut state = compositor();
+		let (_peer, window) = add_toplevel(&mut state);
+
+		assert!(state.get_focused_window() == Some(window));
+		assert!(is_activated(&state, window));
+
+		state.changes.push(Change::SwitchWorkspace(1));
+		state.process_focus_changes().unwrap();
+
+		assert!(state.get_focused_window().is_none());
+		assert!(!is_activated(&state, window));
+
+		state.changes.push(Change::SwitchWorkspace(0));
+		state.process_focus_changes().unwrap();
+
+		assert!(is_activated(&state, window));
+	}
+
+	#[test]
+	fn late_bind_of_a_removed_global() {
+		use wlm::protocol::{wl_display, wl_output, wl_registry};
+
+		let mut state = compositor();
+		let mut hw = crate::backend::headless::initialize_state(state.config).unwrap();
+
+		let (stream, _peer) = std::os::unix::net::UnixStream::pair().unwrap();
+		let conn = std::rc::Rc::new(crate::Connection::new(stream, None, usize::MAX).unwrap());
+		let fd = conn.as_raw_fd();
+
+		let mut client = Client::new(conn.clone(), Point(0, 0), &state.config.client_limits);
+
+		let display = wl::Id::new(1);
+		let registry = wl::Id::new(2);
+
+		client
+			.new_object(
+				display,
+				wl::Display::new(display, conn.clone(), state.globals.clone()),
+			)
+			.unwrap();
+		client
+			.new_object(
+				registry,
+				wl::Registry::new(registry, conn, state.globals.clone()),
+			)
+			.unwrap();
+
+		state.clients.insert(fd, client);
+
+		let name = state
+			.add_global(wl::OutputGlobal {
+				info: hw.outputs[0].clone(),
+			})
+			.unwrap();
+
+		state.remove_global(name).unwrap();
+		assert!(state.remove_global(name).is_err());
+
+		let client = state.clients.get_mut(&fd).unwrap();
+
+		let bind = |client: &mut Client, hw: &mut HwState, id| {
+			let mut registry = client.get_object_mut(registry).unwrap();
+
+			wl::Object::handle(
+				&mut *registry,
+				client,
+				hw,
+				wl_registry::Request::Bind {
+					name,
+					interface: wl_output::INTERFACE,
+					version: 1,
+					id,
+				},
+			)
+		};
+
+		let sync = |client: &mut Client, hw: &mut HwState, callback| {
+			let mut display = client.get_object_mut(display).unwrap();
+
+			wl::Object::handle(
+				&mut *display,
+				client,
+				hw,
+				wl_display::Request::Sync { callback },
+			)
+			.unwrap();
+		};
+
+		// the bind was sent before the client saw the removal
+		bind(client, &mut hw, 3).unwrap();
+		assert!(client.get_object(wl::Id::<wl::Output>::new(3)).is_ok());
+
+		sync(client, &mut hw, 4);
+		bind(client, &mut hw, 5).unwrap();
+
+		// after the second sync, the client has seen it go
+		sync(client, &mut hw, 6);
+		assert!(bind(client, &mut hw, 7).is_err());
+	}
+
+	#[test]
+	fn moving_the_last_window_away_deactivates_it() {
+		let mut state = compositor();
+		let (_peer, window) = add_toplevel(&mut state);
+
+		state
+			.changes
+			.push(Change::MoveToWorkspace(window.0, window.1, 1));
+		state.process_focus_changes().unwrap();
+
+		assert!(state.get_focused_window().is_none());
+		assert!(!is_activated(&state, window));
+	}
+}
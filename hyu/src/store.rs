@@ -1,118 +1,292 @@
 use crate::{Result, wl};
 
+/// Shared borrow of an object, keeps the object alive even if it's removed from its store.
+pub struct ObjectRef<T: 'static> {
+	// has to be dropped before the cell it borrows from
+	guard: std::cell::Ref<'static, T>,
+	_object: std::rc::Rc<std::cell::RefCell<T>>,
+}
+
+impl<T> ObjectRef<T> {
+	fn new(object: &std::rc::Rc<std::cell::RefCell<T>>) -> Result<Self> {
+		let object = object.clone();
+		let guard = object.try_borrow()?;
+
+		// the cell is kept alive by `object`, and doesn't move
+		let guard = unsafe {
+			std::mem::transmute::<std::cell::Ref<'_, T>, std::cell::Ref<'static, T>>(guard)
+		};
+
+		Ok(Self {
+			guard,
+			_object: object,
+		})
+	}
+}
+
+impl<T> std::ops::Deref for ObjectRef<T> {
+	type Target = T;
+
+	fn deref(&self) -> &T {
+		&self.guard
+	}
+}
+
+/// Mutable borrow of an object, keeps the object alive even if it's removed from its store.
+pub struct ObjectMut<T: 'static> {
+	// has to be dropped before the cell it borrows from
+	guard: std::cell::RefMut<'static, T>,
+	_object: std::rc::Rc<std::cell::RefCell<T>>,
+}
+
+impl<T> ObjectMut<T> {
+	fn new(object: &std::rc::Rc<std::cell::RefCell<T>>) -> Result<Self> {
+		let object = object.clone();
+		let guard = object.try_borrow_mut()?;
+
+		// the cell is kept alive by `object`, and doesn't move
+		let guard = unsafe {
+			std::mem::transmute::<std::cell::RefMut<'_, T>, std::cell::RefMut<'static, T>>(guard)
+		};
+
+		Ok(Self {
+			guard,
+			_object: object,
+		})
+	}
+}
+
+impl<T> std::ops::Deref for ObjectMut<T> {
+	type Target = T;
+
+	fn deref(&self) -> &T {
+		&self.guard
+	}
+}
+
+impl<T> std::ops::DerefMut for ObjectMut<T> {
+	fn deref_mut(&mut self) -> &mut T {
+		&mut self.guard
+	}
+}
+
 #[derive(Default)]
 pub struct Store<const START: u32> {
-	objects: Vec<Option<std::cell::UnsafeCell<wl::Resource>>>,
-	highest_index: u32,
+	objects: Vec<Option<wl::Resource>>,
+	/// One past the highest index that has been used.
+	next_index: u32,
+	object_count: usize,
+	/// Ids of the live objects of each type, in order.
+	by_type: std::collections::HashMap<std::any::TypeId, std::collections::BTreeSet<u32>>,
+	/// Ids that were sent in a `delete_id` since the last `wl_display.sync`.
+	deleted: Vec<u32>,
+	/// Ids that were sent in a `delete_id` before the last `wl_display.sync`.
+	acknowledging: Vec<u32>,
+	/// Ids of removed objects that `allocate_id` can hand out again.
+	free: std::collections::BTreeSet<u32>,
 }
 
-impl<'object, const START: u32> Store<START> {
+impl<const START: u32> Store<START> {
 	pub fn new() -> Self {
 		Self {
 			..Default::default()
 		}
 	}
 
-	pub fn ensure_objects_capacity(&mut self) {
-		// TODO: cleanup this mess
-		const THRESHOLD: isize = 10;
+	/// Like libwayland, new ids have to either be free ones, or the one right after the highest
+	/// that has been used. That keeps the store from growing faster than the objects in it.
+	pub fn new_object<T: wl::ResourceType>(
+		&mut self,
+		id: wl::Id<T>,
+		object: T,
+	) -> Result<ObjectMut<T>> {
+		let Some(index) = Self::id_to_index(*id) else {
+			Err(wl::ProtocolError::invalid_new_id(*id))?
+		};
 
-		if ((self.objects.len() as isize - self.highest_index as isize) - THRESHOLD) < 0 {
-			self.objects.resize_with(
-				(self.objects.len() + THRESHOLD as usize) * 2,
-				Default::default,
-			);
+		if index > self.next_index as usize || self.objects.get(index).is_some_and(Option::is_some)
+		{
+			Err(wl::ProtocolError::invalid_new_id(*id))?;
 		}
-	}
 
-	pub fn new_object<T: Into<wl::Resource>>(&mut self, id: wl::Id<T>, object: T) -> &'object mut T
-	where
-		Result<&'object mut T>: From<&'object mut wl::Resource>,
-	{
-		let index = Self::wl_id_to_index(id);
+		if index == self.objects.len() {
+			self.objects.push(None);
+		}
 
-		assert!(index < self.objects.len());
-		assert!(self.objects[index].is_none());
+		let resource = object.into();
+		let ret = ObjectMut::new(T::from_resource(&resource).unwrap())?;
 
-		self.objects[index] = Some(std::cell::UnsafeCell::new(object.into()));
+		self.objects[index] = Some(resource);
+		self.object_count += 1;
 
-		let index = index as u32;
+		self.by_type
+			.entry(std::any::TypeId::of::<T>())
+			.or_default()
+			.insert(*id);
 
-		if self.highest_index < index {
-			self.highest_index = index;
+		if index as u32 == self.next_index {
+			self.next_index += 1;
 		}
 
-		self.get_object_mut(id).unwrap()
+		self.free.remove(&*id);
+
+		Ok(ret)
+	}
+
+	/// Picks an id for an object the server creates, reusing ones the client is done with first.
+	pub fn allocate_id(&self) -> u32 {
+		self.free
+			.first()
+			.copied()
+			.unwrap_or(START + self.next_index)
+	}
+
+	/// Keeps the id of a removed object from being allocated until the client has acknowledged
+	/// the `delete_id` for it.
+	pub fn delete_id(&mut self, id: u32) {
+		self.deleted.push(id);
+	}
+
+	/// Called for each `wl_display.sync`. A client that waits for the `done` of one sync before
+	/// sending the next has read every event that was sent before the first one.
+	pub fn acknowledge_deleted_ids(&mut self) {
+		self.free.extend(self.acknowledging.drain(..));
+		std::mem::swap(&mut self.acknowledging, &mut self.deleted);
 	}
 
-	pub unsafe fn remove_object<T>(&mut self, id: wl::Id<T>) -> Result<()> {
-		let index = Self::wl_id_to_index(id);
-		assert!(self.objects[index].is_some());
-		// TODO: check that it's type T
+	pub fn remove_object<T: wl::ResourceType>(&mut self, id: wl::Id<T>) -> Result<()> {
+		let slot = Self::id_to_index(*id).and_then(|x| self.objects.get_mut(x));
+
+		let Some(slot) = slot.filter(|x| x.as_ref().is_some_and(|x| T::from_resource(x).is_some()))
+		else {
+			color_eyre::eyre::bail!(
+				"object '{}@{}' does not exist",
+				std::any::type_name::<T>(),
+				*id
+			);
+		};
+
+		*slot = None;
+		self.object_count -= 1;
+
+		if let Some(ids) = self.by_type.get_mut(&std::any::TypeId::of::<T>()) {
+			ids.remove(&*id);
+		}
 
-		self.objects[index] = None;
 		Ok(())
 	}
 
-	pub fn get_object<T>(&self, id: wl::Id<T>) -> Result<&'object T>
-	where
-		Result<&'object T>: From<&'object wl::Resource>,
-	{
-		self.get_resource(*id)
-			.ok_or_else(|| {
-				color_eyre::eyre::eyre!(
-					"object '{}@{}' does not exist",
-					std::any::type_name::<T>(),
-					*id
-				)
-			})?
-			.into()
+	/// Number of live objects.
+	pub fn object_count(&self) -> usize {
+		self.object_count
 	}
 
-	pub fn get_object_mut<T>(&self, id: wl::Id<T>) -> Result<&'object mut T>
-	where
-		Result<&'object mut T>: From<&'object mut wl::Resource>,
-	{
-		self.get_resource_mut(*id)
+	pub fn get_object<T: wl::ResourceType>(&self, id: wl::Id<T>) -> Result<ObjectRef<T>> {
+		ObjectRef::new(self.get_cell(id)?)
+	}
+
+	pub fn get_object_mut<T: wl::ResourceType>(&self, id: wl::Id<T>) -> Result<ObjectMut<T>> {
+		ObjectMut::new(self.get_cell(id)?)
+	}
+
+	pub fn get_resource(&self, id: u32) -> Option<wl::Resource> {
+		self.objects.get(Self::id_to_index(id)?)?.clone()
+	}
+
+	pub fn objects_mut<T: wl::ResourceType>(&self) -> Result<Vec<ObjectMut<T>>> {
+		let Some(ids) = self.by_type.get(&std::any::TypeId::of::<T>()) else {
+			return Ok(Vec::new());
+		};
+
+		ids.iter()
+			.map(|&id| self.get_object_mut(wl::Id::new(id)))
+			.collect()
+	}
+
+	fn get_cell<T: wl::ResourceType>(
+		&self,
+		id: wl::Id<T>,
+	) -> Result<&std::rc::Rc<std::cell::RefCell<T>>> {
+		let resource = Self::id_to_index(*id)
+			.and_then(|x| self.objects.get(x))
+			.and_then(Option::as_ref)
 			.ok_or_else(|| {
 				color_eyre::eyre::eyre!(
 					"object '{}@{}' does not exist",
 					std::any::type_name::<T>(),
 					*id
 				)
-			})?
-			.into()
+			})?;
+
+		T::from_resource(resource).ok_or_else(|| {
+			color_eyre::eyre::eyre!("resource is not of type '{}'", std::any::type_name::<T>())
+		})
 	}
 
-	pub fn get_resource(&self, id: u32) -> Option<&'object wl::Resource> {
-		self.objects
-			.get(Self::id_to_index(id))
-			.and_then(|x| x.as_ref().map(|x| unsafe { &*x.get() }))
+	fn id_to_index(id: u32) -> Option<usize> {
+		id.checked_sub(START).map(|x| x as usize)
 	}
+}
 
-	pub fn get_resource_mut(&self, id: u32) -> Option<&'object mut wl::Resource> {
-		self.objects
-			.get(Self::id_to_index(id))
-			.and_then(|x| x.as_ref().map(|x| unsafe { &mut *x.get() }))
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn callback(id: u32) -> wl::Callback {
+		let (stream, _) = std::os::unix::net::UnixStream::pair().unwrap();
+		let conn = crate::Connection::new(stream, None, usize::MAX).unwrap();
+
+		wl::Callback::new(wl::Id::new(id), std::rc::Rc::new(conn))
 	}
 
-	pub fn objects_mut<T>(&self) -> Vec<&'object mut T>
-	where
-		Result<&'object mut T>: From<&'object mut wl::Resource>,
-	{
-		self.objects
-			.iter()
-			.filter_map(|x| x.as_ref().map(|x| unsafe { &mut *x.get() }))
-			.map(Result::from)
-			.filter_map(|x| x.ok())
-			.collect()
+	#[test]
+	fn server_ids_start_at_server_id_start() {
+		let mut store = Store::<{ crate::SERVER_ID_START }>::new();
+
+		let id = store.allocate_id();
+		assert_eq!(id, 0xff000000);
+
+		store
+			.new_object(wl::Id::<wl::Callback>::new(id), callback(id))
+			.unwrap();
+		assert_eq!(store.allocate_id(), 0xff000001);
 	}
 
-	fn wl_id_to_index<T>(id: wl::Id<T>) -> usize {
-		(*id - START) as usize
+	#[test]
+	fn deleted_id_is_reused_after_acknowledgement() {
+		let mut store = Store::<{ crate::SERVER_ID_START }>::new();
+
+		let id = wl::Id::<wl::Callback>::new(store.allocate_id());
+		store.new_object(id, callback(*id)).unwrap();
+		store.remove_object(id).unwrap();
+		store.delete_id(*id);
+
+		// the client may not have read the delete_id yet
+		assert_eq!(store.allocate_id(), *id + 1);
+
+		// the sync may have been sent before it was read
+		store.acknowledge_deleted_ids();
+		assert_eq!(store.allocate_id(), *id + 1);
+
+		store.acknowledge_deleted_ids();
+		assert_eq!(store.allocate_id(), *id);
+
+		store.new_object(id, callback(*id)).unwrap();
+		assert_eq!(store.allocate_id(), *id + 1);
 	}
 
-	fn id_to_index(id: u32) -> usize {
-		(id - START) as usize
+	#[test]
+	fn live_client_id_is_rejected() {
+		let mut store = Store::<1>::new();
+
+		let id = wl::Id::<wl::Callback>::new(1);
+		store.new_object(id, callback(1)).unwrap();
+
+		assert!(store.new_object(id, callback(1)).is_err());
+		assert!(
+			store
+				.new_object(wl::Id::<wl::Callback>::new(3), callback(3))
+				.is_err()
+		);
 	}
 }
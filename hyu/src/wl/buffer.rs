@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::wl_buffer;
+
 use crate::{Client, Connection, Point, Result, gbm, renderer, state::HwState, wl};
 
 pub struct ShmBackingStorage {
@@ -182,6 +184,7 @@ pub struct DmabufBackingStorage {
 	pub image: ash::vk::Image,
 	pub image_view: ash::vk::ImageView,
 	pub gbm_buffer_object: Option<gbm::BufferObject>,
+	pub reservation: crate::Reservation,
 }
 
 pub enum BufferBackingStorage {
@@ -209,31 +212,24 @@ impl Buffer {
 	}
 
 	pub fn release(&self) -> Result<()> {
-		// https://wayland.app/protocols/wayland#wl_buffer:event:release
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 0,
-			args: (),
-		})
+		self.conn
+			.send_event(*self.object_id, wl_buffer::event::Release)
 	}
 }
 
 impl wl::Object for Buffer {
+	type Request<'a> = wl_buffer::Request;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		_params: &[u8],
+		request: wl_buffer::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/wayland#wl_buffer:request:destroy
-				unsafe {
-					client.remove_object(self.object_id)?;
-				}
+		match request {
+			wl_buffer::Request::Destroy => {
+				client.remove_object(self.object_id)?;
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in Buffer"),
 		}
 
 		Ok(())
@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::wl_callback;
+
 use crate::{Client, Connection, Result, state::HwState, wl};
 
 #[derive(Clone)]
@@ -14,29 +16,28 @@ impl Callback {
 	}
 
 	pub fn done(self, client: &mut Client, data: u32) -> Result<()> {
-		// https://wayland.app/protocols/wayland#wl_callback:event:done
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 0,
-			args: data,
-		})?;
+		self.conn.send_event(
+			*self.object_id,
+			wl_callback::event::Done {
+				callback_data: data,
+			},
+		)?;
 
-		unsafe {
-			client.remove_object(self.object_id)?;
-		}
+		client.remove_object(self.object_id)?;
 
 		Ok(())
 	}
 }
 
 impl wl::Object for Callback {
+	type Request<'a> = wl_callback::Request;
+
 	fn handle(
 		&mut self,
 		_client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		_params: &[u8],
+		request: wl_callback::Request,
 	) -> Result<()> {
-		color_eyre::eyre::bail!("unknown op '{op}' in Callback");
+		match request {}
 	}
 }
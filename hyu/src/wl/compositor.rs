@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::wl_compositor;
+
 use crate::{Client, Connection, Result, state::HwState, wl};
 
 pub struct Compositor {
@@ -13,32 +15,35 @@ impl Compositor {
 }
 
 impl wl::Object for Compositor {
+	type Request<'a> = wl_compositor::Request;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		params: &[u8],
+		request: wl_compositor::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/wayland#wl_compositor:request:create_surface
-				let id: wl::Id<wl::Surface> = wlm::decode::from_slice(params)?;
-				client.new_object(id, wl::Surface::new(id, self.conn.clone()));
+		match request {
+			wl_compositor::Request::CreateSurface { id } => {
+				let id = wl::Id::<wl::Surface>::new(id);
+				client.new_object(id, wl::Surface::new(id, self.conn.clone()))?;
 			}
-			1 => {
-				// https://wayland.app/protocols/wayland#wl_compositor:request:create_region
-				let id: wl::Id<wl::Region> = wlm::decode::from_slice(params)?;
-				client.new_object(id, wl::Region::new(id, self.conn.clone()));
+			wl_compositor::Request::CreateRegion { id } => {
+				let id = wl::Id::<wl::Region>::new(id);
+				client.new_object(id, wl::Region::new(id, self.conn.clone()))?;
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in Compositor"),
+			wl_compositor::Request::Release => Err(wl::ProtocolError::invalid_method(
+				"wl_compositor.release is not supported in this version",
+			))?,
 		}
 
 		Ok(())
 	}
 }
 
-impl wl::Global for Compositor {
+pub struct CompositorGlobal;
+
+impl wl::Global for CompositorGlobal {
 	fn get_name(&self) -> &'static str {
 		"wl_compositor"
 	}
@@ -48,7 +53,7 @@ impl wl::Global for Compositor {
 	}
 
 	fn bind(&self, client: &mut Client, object_id: u32, _version: u32) -> Result<()> {
-		client.new_object(wl::Id::new(object_id), Self::new(self.conn.clone()));
+		client.new_object(wl::Id::new(object_id), Compositor::new(client.conn.clone()))?;
 		Ok(())
 	}
 }
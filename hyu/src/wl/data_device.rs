@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::wl_data_device;
+
 use crate::{Client, Connection, Result, state::HwState, wl};
 
 pub struct DataDevice {
@@ -20,35 +22,20 @@ impl DataDevice {
 }
 
 impl wl::Object for DataDevice {
+	type Request<'a> = wl_data_device::Request;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		params: &[u8],
+		request: wl_data_device::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/wayland#wl_data_device:request:start_drag
-				let (_source, _origin, _icon, _serial): (
-					wl::Id<wl::DataSource>,
-					wl::Id<wl::Surface>,
-					wl::Id<wl::Surface>,
-					u32,
-				) = wlm::decode::from_slice(params)?;
-			}
-			1 => {
-				// https://wayland.app/protocols/wayland#wl_data_device:request:set_selection
-				let (_source, _serial): (wl::Id<wl::DataSource>, u32) =
-					wlm::decode::from_slice(params)?;
-			}
-			2 => {
-				// https://wayland.app/protocols/wayland#wl_data_device:request:release
-				unsafe {
-					client.remove_object(self.object_id)?;
-				}
+		match request {
+			wl_data_device::Request::StartDrag { .. } => {}
+			wl_data_device::Request::SetSelection { .. } => {}
+			wl_data_device::Request::Release => {
+				client.remove_object(self.object_id)?;
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in DataDevice"),
 		}
 
 		Ok(())
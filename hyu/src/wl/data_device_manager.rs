@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::wl_data_device_manager;
+
 use crate::{Client, Connection, Result, state::HwState, wl};
 
 pub struct DataDeviceManager {
@@ -13,34 +15,39 @@ impl DataDeviceManager {
 }
 
 impl wl::Object for DataDeviceManager {
+	type Request<'a> = wl_data_device_manager::Request;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		params: &[u8],
+		request: wl_data_device_manager::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/wayland#wl_data_device_manager:request:create_data_source
-				let id: wl::Id<wl::DataSource> = wlm::decode::from_slice(params)?;
-				client.new_object(id, wl::DataSource::new(id, self.conn.clone()));
+		match request {
+			wl_data_device_manager::Request::CreateDataSource { id } => {
+				let id = wl::Id::<wl::DataSource>::new(id);
+				client.new_object(id, wl::DataSource::new(id, self.conn.clone()))?;
 			}
-			1 => {
-				// https://wayland.app/protocols/wayland#wl_data_device_manager:request:get_data_device
-				let (id, seat): (wl::Id<wl::DataDevice>, wl::Id<wl::Seat>) =
-					wlm::decode::from_slice(params)?;
+			wl_data_device_manager::Request::GetDataDevice { id, seat } => {
+				let id = wl::Id::<wl::DataDevice>::new(id);
 
-				client.new_object(id, wl::DataDevice::new(id, self.conn.clone(), seat));
+				client.new_object(
+					id,
+					wl::DataDevice::new(id, self.conn.clone(), wl::Id::new(seat)),
+				)?;
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in DataDeviceManager"),
+			wl_data_device_manager::Request::Release => Err(wl::ProtocolError::invalid_method(
+				"wl_data_device_manager.release is not supported in this version",
+			))?,
 		}
 
 		Ok(())
 	}
 }
 
-impl wl::Global for DataDeviceManager {
+pub struct DataDeviceManagerGlobal;
+
+impl wl::Global for DataDeviceManagerGlobal {
 	fn get_name(&self) -> &'static str {
 		"wl_data_device_manager"
 	}
@@ -50,7 +57,10 @@ impl wl::Global for DataDeviceManager {
 	}
 
 	fn bind(&self, client: &mut Client, object_id: u32, _version: u32) -> Result<()> {
-		client.new_object(wl::Id::new(object_id), Self::new(self.conn.clone()));
+		client.new_object(
+			wl::Id::new(object_id),
+			DataDeviceManager::new(client.conn.clone()),
+		)?;
 		Ok(())
 	}
 }
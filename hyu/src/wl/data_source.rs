@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::wl_data_source;
+
 use crate::{Client, Connection, Result, state::HwState, wl};
 
 pub struct DataSource {
@@ -15,29 +17,20 @@ impl DataSource {
 }
 
 impl wl::Object for DataSource {
+	type Request<'a> = wl_data_source::Request<'a>;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		params: &[u8],
+		request: wl_data_source::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/wayland#wl_data_source:request:offer
-				let _mime_type: String = wlm::decode::from_slice(params)?;
-			}
-			1 => {
-				// https://wayland.app/protocols/wayland#wl_data_source:request:destroy
-				unsafe {
-					client.remove_object(self.object_id)?;
-				}
-			}
-			2 => {
-				// https://wayland.app/protocols/wayland#wl_data_source:request:set_actions
-				let _dnd_actions: u32 = wlm::decode::from_slice(params)?;
+		match request {
+			wl_data_source::Request::Offer { .. } => {}
+			wl_data_source::Request::Destroy => {
+				client.remove_object(self.object_id)?;
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in DataSource"),
+			wl_data_source::Request::SetActions { .. } => {}
 		}
 
 		Ok(())
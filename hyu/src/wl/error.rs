@@ -0,0 +1,81 @@
+#[derive(Debug)]
+pub struct ProtocolError {
+	pub object_id: Option<u32>,
+	pub code: u32,
+	pub message: String,
+}
+
+impl ProtocolError {
+	// https://wayland.app/protocols/wayland#wl_display:enum:error
+	pub const INVALID_OBJECT: u32 = 0;
+	pub const INVALID_METHOD: u32 = 1;
+	pub const NO_MEMORY: u32 = 2;
+	pub const IMPLEMENTATION: u32 = 3;
+
+	pub fn new(object_id: u32, code: u32, message: impl Into<String>) -> Self {
+		Self {
+			object_id: Some(object_id),
+			code,
+			message: message.into(),
+		}
+	}
+
+	pub fn invalid_object(id: u32) -> Self {
+		Self::new(1, Self::INVALID_OBJECT, format!("invalid object {id}"))
+	}
+
+	pub fn invalid_new_id(id: u32) -> Self {
+		Self::new(1, Self::INVALID_OBJECT, format!("invalid new id {id}"))
+	}
+
+	pub fn invalid_method(message: impl Into<String>) -> Self {
+		Self {
+			object_id: None,
+			code: Self::INVALID_METHOD,
+			message: message.into(),
+		}
+	}
+
+	pub fn no_memory(message: impl Into<String>) -> Self {
+		Self::new(1, Self::NO_MEMORY, message)
+	}
+
+	pub fn implementation(message: impl Into<String>) -> Self {
+		Self::new(1, Self::IMPLEMENTATION, message)
+	}
+
+	pub fn from_report(report: color_eyre::Report, object_id: u32) -> Self {
+		let report = match report.downcast::<Self>() {
+			Ok(mut error) => {
+				error.object_id.get_or_insert(object_id);
+				return error;
+			}
+			Err(report) => report,
+		};
+
+		if let Some(error) = report.downcast_ref::<wlm::error::Error>() {
+			return Self::new(
+				object_id,
+				Self::INVALID_METHOD,
+				format!("invalid arguments for object {object_id}: {error}"),
+			);
+		}
+
+		Self::implementation(report.to_string())
+	}
+}
+
+impl std::fmt::Display for ProtocolError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self.object_id {
+			Some(object_id) => write!(
+				f,
+				"error {} on object {object_id}: {}",
+				self.code, self.message
+			),
+			None => write!(f, "error {}: {}", self.code, self.message),
+		}
+	}
+}
+
+impl std::error::Error for ProtocolError {}
@@ -0,0 +1,155 @@
+use crate::wl;
+
+/// Globals advertised to every client. Names are never reused, so a late bind can't end up at a
+/// different global than the one the client was told about.
+#[derive(Default)]
+pub struct Globals {
+	globals: std::collections::BTreeMap<u32, std::rc::Rc<dyn wl::Global>>,
+	/// Clients can still bind these until they've seen the removal.
+	removed: std::collections::BTreeMap<u32, Removed>,
+	last_name: u32,
+}
+
+struct Removed {
+	global: std::rc::Rc<dyn wl::Global>,
+	/// Clients that were sent the `global_remove`, and whether they've sent a `wl_display.sync`
+	/// since. Like with deleted ids, it takes a second sync to be sure the client has seen it.
+	clients: std::collections::HashMap<std::os::fd::RawFd, bool>,
+}
+
+impl Globals {
+	pub fn insert(&mut self, global: impl wl::Global + 'static) -> u32 {
+		self.last_name += 1;
+		self.globals
+			.insert(self.last_name, std::rc::Rc::new(global));
+
+		self.last_name
+	}
+
+	/// Removes a global, `clients` are the ones that are told about it and might still bind it.
+	pub fn remove(
+		&mut self,
+		name: u32,
+		clients: impl IntoIterator<Item = std::os::fd::RawFd>,
+	) -> Option<std::rc::Rc<dyn wl::Global>> {
+		let ret = self.globals.remove(&name)?;
+
+		let clients = clients
+			.into_iter()
+			.map(|x| (x, false))
+			.collect::<std::collections::HashMap<_, _>>();
+
+		if !clients.is_empty() {
+			self.removed.insert(
+				name,
+				Removed {
+					global: ret.clone(),
+					clients,
+				},
+			);
+		}
+
+		Some(ret)
+	}
+
+	/// Called for each `wl_display.sync` of a client. Removed globals are forgotten once every
+	/// client has seen them go.
+	pub fn acknowledge_removals(&mut self, fd: std::os::fd::RawFd) {
+		self.removed.retain(|_, removed| {
+			if let Some(synced) = removed.clients.get_mut(&fd) {
+				if *synced {
+					removed.clients.remove(&fd);
+				} else {
+					*synced = true;
+				}
+			}
+
+			!removed.clients.is_empty()
+		});
+	}
+
+	/// Stops waiting for a client that disconnected.
+	pub fn remove_client(&mut self, fd: std::os::fd::RawFd) {
+		self.removed.retain(|_, removed| {
+			removed.clients.remove(&fd);
+			!removed.clients.is_empty()
+		});
+	}
+
+	pub fn get(&self, name: u32) -> Option<std::rc::Rc<dyn wl::Global>> {
+		self.globals.get(&name).cloned()
+	}
+
+	/// A removed global that the client might not have seen go yet.
+	pub fn get_removed(
+		&self,
+		name: u32,
+		fd: std::os::fd::RawFd,
+	) -> Option<std::rc::Rc<dyn wl::Global>> {
+		self.removed
+			.get(&name)
+			.filter(|x| x.clients.contains_key(&fd))
+			.map(|x| x.global.clone())
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (u32, &dyn wl::Global)> {
+		self.globals.iter().map(|(&name, global)| (name, &**global))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn globals() -> (Globals, u32) {
+		let mut globals = Globals::default();
+		let name = globals.insert(wl::ShmGlobal);
+
+		(globals, name)
+	}
+
+	#[test]
+	fn removed_until_every_client_synced_twice() {
+		let (mut globals, name) = globals();
+
+		globals.remove(name, [3, 4]);
+		assert!(globals.get(name).is_none());
+		assert!(globals.get_removed(name, 3).is_some());
+		assert!(globals.get_removed(name, 4).is_some());
+
+		// never told about it, so it can't have bound it
+		assert!(globals.get_removed(name, 5).is_none());
+
+		globals.acknowledge_removals(3);
+		assert!(globals.get_removed(name, 3).is_some());
+
+		globals.acknowledge_removals(3);
+		assert!(globals.get_removed(name, 3).is_none());
+		assert!(globals.get_removed(name, 4).is_some());
+
+		globals.acknowledge_removals(4);
+		globals.acknowledge_removals(4);
+		assert!(globals.removed.is_empty());
+	}
+
+	#[test]
+	fn disconnected_clients_are_not_waited_for() {
+		let (mut globals, name) = globals();
+
+		globals.remove(name, [3]);
+		globals.remove_client(3);
+		assert!(globals.removed.is_empty());
+
+		let name = globals.insert(wl::ShmGlobal);
+		globals.remove(name, []);
+		assert!(globals.removed.is_empty());
+	}
+
+	#[test]
+	fn names_are_not_reused() {
+		let (mut globals, name) = globals();
+
+		globals.remove(name, []);
+		assert_ne!(globals.insert(wl::ShmGlobal), name);
+	}
+}
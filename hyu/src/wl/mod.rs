@@ -5,6 +5,8 @@ mod data_device;
 mod data_device_manager;
 mod data_source;
 mod display;
+mod error;
+mod globals;
 mod id;
 mod keyboard;
 mod output;
@@ -40,6 +42,8 @@ pub use data_device::*;
 pub use data_device_manager::*;
 pub use data_source::*;
 pub use display::*;
+pub use error::*;
+pub use globals::*;
 pub use id::*;
 pub use keyboard::*;
 pub use output::*;
@@ -74,14 +78,21 @@ pub trait Global {
 	fn get_name(&self) -> &'static str;
 	fn get_version(&self) -> u32;
 	fn bind(&self, client: &mut Client, object_id: u32, version: u32) -> Result<()>;
+
+	/// Binds the global after it has been removed. The object only has to exist until the client
+	/// destroys it, so globals that can be removed should create it without sending any events.
+	fn bind_removed(&self, client: &mut Client, object_id: u32, version: u32) -> Result<()> {
+		self.bind(client, object_id, version)
+	}
 }
 
 pub trait Object {
+	type Request<'a>: wlm::Request<'a>;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		hw_state: &mut HwState,
-		op: u16,
-		params: &[u8],
+		request: Self::Request<'_>,
 	) -> Result<()>;
 }
@@ -1,15 +1,34 @@
 use std::rc::Rc;
 
-use crate::{Client, Connection, Result, state::HwState, wl};
+use wlm::protocol::wl_output;
+
+use crate::{Client, Connection, Point, Result, state::HwState, wl};
+
+#[derive(Clone)]
+pub struct OutputInfo {
+	pub position: Point,
+	pub size: Point,
+	/// Refresh rate in mHz.
+	pub refresh: u32,
+	/// Physical size in millimeters.
+	pub physical_size: Point,
+	pub make: String,
+	pub model: String,
+}
 
 pub struct Output {
 	pub object_id: wl::Id<Self>,
 	conn: Rc<Connection>,
+	pub info: OutputInfo,
 }
 
 impl Output {
-	pub fn new(object_id: wl::Id<Self>, conn: Rc<Connection>) -> Self {
-		Self { object_id, conn }
+	pub fn new(object_id: wl::Id<Self>, conn: Rc<Connection>, info: OutputInfo) -> Self {
+		Self {
+			object_id,
+			conn,
+			info,
+		}
 	}
 
 	fn geometry(
@@ -23,11 +42,9 @@ impl Output {
 		model: &str,
 		transform: i32,
 	) -> Result<()> {
-		// https://wayland.app/protocols/wayland#wl_output:event:geometry
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 0,
-			args: (
+		self.conn.send_event(
+			*self.object_id,
+			wl_output::event::Geometry {
 				x,
 				y,
 				physical_width,
@@ -36,61 +53,57 @@ impl Output {
 				make,
 				model,
 				transform,
-			),
-		})
+			},
+		)
 	}
 
 	fn mode(&self, flags: u32, width: i32, height: i32, refresh: i32) -> Result<()> {
-		// https://wayland.app/protocols/wayland#wl_output:event:mode
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 1,
-			args: (flags, width, height, refresh),
-		})
+		self.conn.send_event(
+			*self.object_id,
+			wl_output::event::Mode {
+				flags,
+				width,
+				height,
+				refresh,
+			},
+		)
 	}
 
 	pub fn done(&self) -> Result<()> {
-		// https://wayland.app/protocols/wayland#wl_output:event:done
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 2,
-			args: (),
-		})
+		self.conn
+			.send_event(*self.object_id, wl_output::event::Done)
 	}
 
 	fn scale(&self, factor: i32) -> Result<()> {
-		// https://wayland.app/protocols/wayland#wl_output:event:scale
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 3,
-			args: factor,
-		})
+		self.conn
+			.send_event(*self.object_id, wl_output::event::Scale { factor })
 	}
 }
 
 impl wl::Object for Output {
+	type Request<'a> = wl_output::Request;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		_params: &[u8],
+		request: wl_output::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/wayland#wl_output:request:release
-				unsafe {
-					client.remove_object(self.object_id)?;
-				}
+		match request {
+			wl_output::Request::Release => {
+				client.remove_object(self.object_id)?;
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in Output"),
 		}
 
 		Ok(())
 	}
 }
 
-impl wl::Global for Output {
+pub struct OutputGlobal {
+	pub info: OutputInfo,
+}
+
+impl wl::Global for OutputGlobal {
 	fn get_name(&self) -> &'static str {
 		"wl_output"
 	}
@@ -102,14 +115,42 @@ impl wl::Global for Output {
 	fn bind(&self, client: &mut Client, object_id: u32, _version: u32) -> Result<()> {
 		let output = client.new_object(
 			wl::Id::new(object_id),
-			Self::new(wl::Id::new(object_id), self.conn.clone()),
-		);
+			Output::new(
+				wl::Id::new(object_id),
+				client.conn.clone(),
+				self.info.clone(),
+			),
+		)?;
+
+		let info = &output.info;
 
-		output.geometry(0, 0, 600, 340, 0, "AUS", "ROG XG27AQM", 0)?;
-		output.mode(3, 2560, 1440, 270000)?;
+		output.geometry(
+			info.position.0,
+			info.position.1,
+			info.physical_size.0,
+			info.physical_size.1,
+			0,
+			&info.make,
+			&info.model,
+			0,
+		)?;
+		output.mode(3, info.size.0, info.size.1, info.refresh as _)?;
 		output.scale(1)?;
 		output.done()?;
 
 		Ok(())
 	}
+
+	fn bind_removed(&self, client: &mut Client, object_id: u32, _version: u32) -> Result<()> {
+		client.new_object(
+			wl::Id::new(object_id),
+			Output::new(
+				wl::Id::new(object_id),
+				client.conn.clone(),
+				self.info.clone(),
+			),
+		)?;
+
+		Ok(())
+	}
 }
@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::wl_region;
+
 use crate::{Client, Connection, Point, Result, state::HwState, wl};
 
 #[derive(Clone)]
@@ -21,30 +23,27 @@ impl Region {
 }
 
 impl wl::Object for Region {
+	type Request<'a> = wl_region::Request;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		params: &[u8],
+		request: wl_region::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/wayland#wl_region:request:destroy
-				unsafe {
-					client.remove_object(self.object_id)?;
-				}
-			}
-			1 => {
-				// https://wayland.app/protocols/wayland#wl_region:request:add
-				let (x, y, w, h): (i32, i32, i32, i32) = wlm::decode::from_slice(params)?;
-				self.areas.push((Point(x, y), Point(w, h)));
+		match request {
+			wl_region::Request::Destroy => {
+				client.remove_object(self.object_id)?;
 			}
-			2 => {
-				// https://wayland.app/protocols/wayland#wl_region:request:subtract
-				let (_x, _y, _w, _h): (i32, i32, i32, i32) = wlm::decode::from_slice(params)?;
+			wl_region::Request::Add {
+				x,
+				y,
+				width,
+				height,
+			} => {
+				self.areas.push((Point(x, y), Point(width, height)));
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in Region"),
+			wl_region::Request::Subtract { .. } => {}
 		}
 
 		Ok(())
@@ -1,57 +1,107 @@
 use std::rc::Rc;
 
+use wlm::protocol::wl_registry;
+
 use crate::{Client, Connection, Result, state::HwState, wl};
 
 pub struct Registry {
 	object_id: wl::Id<Self>,
 	conn: Rc<Connection>,
-	display: wl::Id<wl::Display>,
+	globals: Rc<std::cell::RefCell<wl::Globals>>,
 }
 
 impl Registry {
 	pub fn new(
 		object_id: wl::Id<Self>,
 		conn: Rc<Connection>,
-		display: wl::Id<wl::Display>,
+		globals: Rc<std::cell::RefCell<wl::Globals>>,
 	) -> Self {
 		Self {
 			object_id,
 			conn,
-			display,
+			globals,
 		}
 	}
 
 	pub fn global(&self, name: u32, interface: impl AsRef<str>, version: u32) -> Result<()> {
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 0,
-			args: (name, interface.as_ref(), version),
-		})
+		self.conn.send_event(
+			*self.object_id,
+			wl_registry::event::Global {
+				name,
+				interface: interface.as_ref(),
+				version,
+			},
+		)
+	}
+
+	pub fn global_remove(&self, name: u32) -> Result<()> {
+		self.conn
+			.send_event(*self.object_id, wl_registry::event::GlobalRemove { name })
 	}
 }
 
 impl wl::Object for Registry {
+	type Request<'a> = wl_registry::Request<'a>;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		params: &[u8],
+		request: wl_registry::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/wayland#wl_registry:request:bind
-				let (name, interface, version, client_object): (u32, String, u32, u32) =
-					wlm::decode::from_slice(params)?;
+		match request {
+			wl_registry::Request::Bind {
+				name,
+				interface,
+				version,
+				id,
+			} => {
+				let globals = self.globals.borrow();
+
+				let (global, removed) =
+					match (globals.get(name), globals.get_removed(name, client.fd)) {
+						(Some(global), _) => (global, false),
+						(None, Some(global)) => (global, true),
+						(None, None) => Err(wl::ProtocolError::new(
+							1,
+							wl::ProtocolError::INVALID_OBJECT,
+							format!("invalid global {interface} ({name})"),
+						))?,
+					};
+
+				drop(globals);
+
+				if interface != global.get_name() {
+					Err(wl::ProtocolError::new(
+						1,
+						wl::ProtocolError::INVALID_OBJECT,
+						format!(
+							"invalid interface for global {name}: have {}, wanted {interface}",
+							global.get_name()
+						),
+					))?;
+				}
 
-				println!(" {client_object}, {name}, {interface:?} {version}");
+				if version == 0 || version > global.get_version() {
+					Err(wl::ProtocolError::new(
+						1,
+						wl::ProtocolError::INVALID_OBJECT,
+						format!(
+							"invalid version for global {interface} ({name}): have {}, wanted {version}",
+							global.get_version()
+						),
+					))?;
+				}
 
-				let display = client.get_object(self.display)?;
+				client.request_version = version;
 
-				let global = display.get_global(name).unwrap();
-				global.bind(client, client_object, version)?;
+				// the client sent the bind before it saw the global being removed
+				if removed {
+					global.bind_removed(client, id, version)?;
+				} else {
+					global.bind(client, id, version)?;
+				}
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in Registry"),
 		}
 
 		Ok(())
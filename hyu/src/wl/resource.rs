@@ -1,22 +1,45 @@
 macro_rules! implement {
     ($($children:ident),*$(,)?) => {
+        /// Every object lives in its own cell, so it stays alive and borrowable while the store it
+        /// belongs to changes.
+        #[derive(Clone)]
         pub enum Resource {
             $(
-                $children(crate::wl::$children),
+                $children(std::rc::Rc<std::cell::RefCell<crate::wl::$children>>),
             )*
         }
 
-        impl crate::wl::Object for Resource {
-            fn handle(
-                &mut self,
+        impl Resource {
+            pub fn handle(
+                &self,
                 client: &mut crate::Client,
                 hw_state: &mut crate::state::HwState,
+                connection: &crate::Connection,
+                object: u32,
                 op: u16,
                 params: &[u8],
+                fds: &mut std::collections::VecDeque<std::os::fd::RawFd>,
             ) -> crate::Result<()> {
                 match self {
                     $(
-                        Self::$children(x) => x.handle(client, hw_state, op, params),
+                        Self::$children(x) => {
+                            let request = wlm::Request::decode(op, params, fds)?;
+                            connection.trace_request(object, &request);
+
+                            let version = connection.version(object);
+
+                            if wlm::Request::since(&request) > version {
+                                Err(crate::wl::ProtocolError::invalid_method(format!(
+                                    "{} is not supported in version {version}",
+                                    wlm::Request::name(&request),
+                                )))?;
+                            }
+
+                            client.request_version = version;
+
+                            let mut x = x.try_borrow_mut()?;
+                            crate::wl::Object::handle(&mut *x, client, hw_state, request)
+                        }
                     )*
                 }
             }
@@ -25,33 +48,15 @@ macro_rules! implement {
         $(
             impl From<crate::wl::$children> for Resource {
                 fn from(x: crate::wl::$children) -> Self {
-                    Self::$children(x)
-                }
-            }
-
-            impl From<Resource> for crate::Result<crate::wl::$children> {
-                fn from(x: Resource) -> Self {
-                    match x {
-                        Resource::$children(x) => Self::Ok(x),
-                        _ => color_eyre::eyre::bail!(concat!("resource is not of type '", stringify!($children), "'")),
-                    }
+                    Self::$children(std::rc::Rc::new(std::cell::RefCell::new(x)))
                 }
             }
 
-            impl<'a> From<&'a Resource> for crate::Result<&'a crate::wl::$children> {
-                fn from(x: &'a Resource) -> Self {
-                    match x {
-                        Resource::$children(x) => Self::Ok(x),
-                        _ => color_eyre::eyre::bail!(concat!("resource is not of type '", stringify!($children), "'")),
-                    }
-                }
-            }
-
-            impl<'a> From<&'a mut Resource> for crate::Result<&'a mut crate::wl::$children> {
-                fn from(x: &'a mut Resource) -> Self {
-                    match x {
-                        Resource::$children(x) => Self::Ok(x),
-                        _ => color_eyre::eyre::bail!(concat!("resource is not of type '", stringify!($children), "'")),
+            impl ResourceType for crate::wl::$children {
+                fn from_resource(resource: &Resource) -> Option<&std::rc::Rc<std::cell::RefCell<Self>>> {
+                    match resource {
+                        Resource::$children(x) => Some(x),
+                        _ => None,
                     }
                 }
             }
@@ -59,6 +64,11 @@ macro_rules! implement {
     }
 }
 
+/// Implemented by everything that can be stored in a `Resource`.
+pub trait ResourceType: Into<Resource> + Sized + 'static {
+	fn from_resource(resource: &Resource) -> Option<&std::rc::Rc<std::cell::RefCell<Self>>>;
+}
+
 implement![
 	Buffer,
 	Callback,
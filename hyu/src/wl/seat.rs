@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::wl_seat;
+
 use crate::{Client, Connection, Result, state::HwState, wl};
 
 pub struct Seat {
@@ -22,54 +24,54 @@ impl Seat {
 	}
 
 	fn capabilities(&self, capabilities: u32) -> Result<()> {
-		// https://wayland.app/protocols/wayland#wl_seat:event:capabilities
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 0,
-			args: capabilities,
-		})
+		self.conn.send_event(
+			*self.object_id,
+			wl_seat::event::Capabilities { capabilities },
+		)
 	}
 }
 
 impl wl::Object for Seat {
+	type Request<'a> = wl_seat::Request;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		params: &[u8],
+		request: wl_seat::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/wayland#wl_seat:request:get_pointer
-				let id: wl::Id<wl::Pointer> = wlm::decode::from_slice(params)?;
-				client.new_object(id, wl::Pointer::new(id, self.conn.clone(), self.object_id));
+		match request {
+			wl_seat::Request::GetPointer { id } => {
+				let id = wl::Id::<wl::Pointer>::new(id);
+				client.new_object(id, wl::Pointer::new(id, self.conn.clone(), self.object_id))?;
 			}
-			1 => {
-				// https://wayland.app/protocols/wayland#wl_seat:request:get_keyboard
-				let id: wl::Id<wl::Keyboard> = wlm::decode::from_slice(params)?;
+			wl_seat::Request::GetKeyboard { id } => {
+				let id = wl::Id::<wl::Keyboard>::new(id);
 
 				let mut keyboard =
 					wl::Keyboard::new(id, self.conn.clone(), self.object_id, self.keymap);
 				keyboard.keymap()?;
 				keyboard.repeat_info(33, 500)?;
 
-				client.new_object(id, keyboard);
+				client.new_object(id, keyboard)?;
 			}
-			3 => {
-				// https://wayland.app/protocols/wayland#wl_seat:request:release
-				unsafe {
-					client.remove_object(self.object_id)?;
-				}
+			wl_seat::Request::GetTouch { .. } => Err(wl::ProtocolError::implementation(
+				"wl_seat.get_touch is not supported",
+			))?,
+			wl_seat::Request::Release => {
+				client.remove_object(self.object_id)?;
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in Seat"),
 		}
 
 		Ok(())
 	}
 }
 
-impl wl::Global for Seat {
+pub struct SeatGlobal {
+	pub keymap: (std::os::fd::RawFd, u64),
+}
+
+impl wl::Global for SeatGlobal {
 	fn get_name(&self) -> &'static str {
 		"wl_seat"
 	}
@@ -81,8 +83,8 @@ impl wl::Global for Seat {
 	fn bind(&self, client: &mut Client, object_id: u32, _version: u32) -> Result<()> {
 		let seat = client.new_object(
 			wl::Id::new(object_id),
-			Self::new(wl::Id::new(object_id), self.conn.clone(), self.keymap),
-		);
+			Seat::new(wl::Id::new(object_id), client.conn.clone(), self.keymap),
+		)?;
 
 		seat.capabilities(3)
 	}
@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::wl_shm;
+
 use crate::{Client, Connection, Result, state::HwState, wl};
 
 pub struct Shm {
@@ -13,39 +15,52 @@ impl Shm {
 	}
 
 	fn format(&self, format: u32) -> Result<()> {
-		// https://wayland.app/protocols/wayland#wl_shm:event:format
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 0,
-			args: format,
-		})
+		self.conn
+			.send_event(*self.object_id, wl_shm::event::Format { format })
 	}
 }
 
 impl wl::Object for Shm {
+	type Request<'a> = wl_shm::Request;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		params: &[u8],
+		request: wl_shm::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/wayland#wl_shm:request:create_pool
-				let (id, size): (wl::Id<wl::ShmPool>, u32) = wlm::decode::from_slice(params)?;
-				let fd = client.received_fds.pop_front().unwrap();
+		match request {
+			wl_shm::Request::CreatePool { id, fd, size } => {
+				if size <= 0 {
+					nix::unistd::close(fd)?;
+
+					Err(wl::ProtocolError::new(
+						*self.object_id,
+						wl_shm::error::INVALID_STRIDE,
+						format!("invalid pool size {size}"),
+					))?;
+				}
 
-				client.new_object(id, wl::ShmPool::new(id, self.conn.clone(), fd, size)?);
+				let id = wl::Id::<wl::ShmPool>::new(id);
+				let reservation = client.reserve_shm(0)?;
+
+				client.new_object(
+					id,
+					wl::ShmPool::new(id, self.conn.clone(), fd, size as _, reservation)?,
+				)?;
+			}
+			wl_shm::Request::Release => {
+				client.remove_object(self.object_id)?;
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in Shm"),
 		}
 
 		Ok(())
 	}
 }
 
-impl wl::Global for Shm {
+pub struct ShmGlobal;
+
+impl wl::Global for ShmGlobal {
 	fn get_name(&self) -> &'static str {
 		"wl_shm"
 	}
@@ -57,11 +72,11 @@ impl wl::Global for Shm {
 	fn bind(&self, client: &mut Client, object_id: u32, _version: u32) -> Result<()> {
 		let shm = client.new_object(
 			wl::Id::new(object_id),
-			Self::new(wl::Id::new(object_id), self.conn.clone()),
-		);
+			Shm::new(wl::Id::new(object_id), client.conn.clone()),
+		)?;
 
-		shm.format(0)?;
-		shm.format(1)?;
+		shm.format(wl_shm::format::ARGB8888)?;
+		shm.format(wl_shm::format::XRGB8888)?;
 
 		Ok(())
 	}
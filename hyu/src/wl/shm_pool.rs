@@ -1,4 +1,6 @@
-use std::rc::Rc;
+use std::{os::fd::FromRawFd as _, rc::Rc};
+
+use wlm::protocol::{wl_shm, wl_shm_pool};
 
 use crate::{Client, Connection, Point, Result, state::HwState, wl};
 
@@ -10,30 +12,41 @@ unsafe impl Sync for Ptr {}
 pub struct Map {
 	ptr: Ptr,
 	size: usize,
+	reservation: crate::Reservation,
 }
 
 impl Map {
-	pub fn new(size: usize, fd: std::os::fd::RawFd) -> Result<Self> {
+	pub fn new(
+		size: usize,
+		fd: std::os::fd::RawFd,
+		mut reservation: crate::Reservation,
+	) -> Result<Self> {
+		// closed once it's mapped, or if mapping fails
+		let fd = unsafe { std::os::fd::OwnedFd::from_raw_fd(fd) };
+
+		reservation.resize(size)?;
+
 		let ptr = unsafe {
 			nix::sys::mman::mmap(
 				None,
 				std::num::NonZeroUsize::new(size).unwrap(),
 				nix::sys::mman::ProtFlags::PROT_READ,
 				nix::sys::mman::MapFlags::MAP_SHARED,
-				std::os::fd::BorrowedFd::borrow_raw(fd),
+				&fd,
 				0,
 			)?
 		};
 
-		nix::unistd::close(fd)?;
-
 		Ok(Self {
 			ptr: Ptr(ptr),
 			size,
+			reservation,
 		})
 	}
 
 	pub fn remap(&mut self, size: usize) -> Result<()> {
+		self.reservation.resize(size)?;
+
 		let ptr = unsafe {
 			nix::sys::mman::mremap(
 				self.ptr.0,
@@ -64,11 +77,11 @@ impl Drop for Map {
 }
 
 #[derive(Clone)]
-pub struct SharedMap(std::sync::Arc<std::cell::SyncUnsafeCell<Map>>);
+pub struct SharedMap(std::rc::Rc<std::cell::UnsafeCell<Map>>);
 
 impl SharedMap {
 	pub fn new(map: Map) -> Self {
-		Self(std::sync::Arc::new(std::cell::SyncUnsafeCell::new(map)))
+		Self(std::rc::Rc::new(std::cell::UnsafeCell::new(map)))
 	}
 
 	pub fn as_mut_ptr(&self) -> *mut Map {
@@ -87,12 +100,13 @@ impl ShmPool {
 		object_id: wl::Id<Self>,
 		conn: Rc<Connection>,
 		fd: std::os::fd::RawFd,
-		size: u32,
+		size: usize,
+		reservation: crate::Reservation,
 	) -> Result<Self> {
 		Ok(Self {
 			object_id,
 			conn,
-			map: SharedMap::new(Map::new(size as _, fd)?),
+			map: SharedMap::new(Map::new(size, fd, reservation)?),
 		})
 	}
 
@@ -102,24 +116,51 @@ impl ShmPool {
 }
 
 impl wl::Object for ShmPool {
+	type Request<'a> = wl_shm_pool::Request;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		params: &[u8],
+		request: wl_shm_pool::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/wayland#wl_shm_pool:request:create_buffer
-				let (id, offset, width, height, stride, format): (
-					wl::Id<wl::Buffer>,
-					i32,
-					i32,
-					i32,
-					i32,
-					u32,
-				) = wlm::decode::from_slice(params)?;
+		match request {
+			wl_shm_pool::Request::CreateBuffer {
+				id,
+				offset,
+				width,
+				height,
+				stride,
+				format,
+			} => {
+				if !matches!(format, wl_shm::format::ARGB8888 | wl_shm::format::XRGB8888) {
+					Err(wl::ProtocolError::new(
+						*self.object_id,
+						wl_shm::error::INVALID_FORMAT,
+						format!("invalid format {format:#x}"),
+					))?;
+				}
+
+				// both formats are 4 bytes per pixel, and the buffer has to be inside of the pool
+				let end = (stride as usize)
+					.checked_mul(height as usize)
+					.and_then(|x| x.checked_add(offset as usize));
+
+				if offset < 0
+					|| width <= 0 || height <= 0
+					|| stride / 4 < width
+					|| end.is_none_or(|x| x > self.get_map().len())
+				{
+					Err(wl::ProtocolError::new(
+						*self.object_id,
+						wl_shm::error::INVALID_STRIDE,
+						format!(
+							"invalid width, height or stride ({width}x{height}, {stride}) at offset {offset}"
+						),
+					))?;
+				}
+
+				let id = wl::Id::<wl::Buffer>::new(id);
 
 				client.new_object(
 					id,
@@ -134,23 +175,25 @@ impl wl::Object for ShmPool {
 							format,
 						}),
 					),
-				);
+				)?;
 			}
-			1 => {
-				// https://wayland.app/protocols/wayland#wl_shm_pool:request:destroy
-				unsafe {
-					client.remove_object(self.object_id)?;
-				}
+			wl_shm_pool::Request::Destroy => {
+				client.remove_object(self.object_id)?;
 			}
-			2 => {
-				// https://wayland.app/protocols/wayland#wl_shm_pool:request:resize
-				let size: u32 = wlm::decode::from_slice(params)?;
-
-				unsafe {
-					(*self.map.as_mut_ptr()).remap(size as _)?;
+			wl_shm_pool::Request::Resize { size } => {
+				let map = unsafe { &mut *self.map.as_mut_ptr() };
+
+				// buffers might still point into the part that would go away
+				if size < 0 || (size as usize) < map.size {
+					Err(wl::ProtocolError::new(
+						*self.object_id,
+						wl_shm::error::INVALID_STRIDE,
+						format!("shrinking pool to {size} bytes is not allowed"),
+					))?;
 				}
+
+				map.remap(size as _)?;
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in ShmPool"),
 		}
 
 		Ok(())
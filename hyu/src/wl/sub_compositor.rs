@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::wl_subcompositor;
+
 use crate::{Client, Connection, Result, state::HwState, wl};
 
 pub struct SubCompositor {
@@ -14,32 +16,31 @@ impl SubCompositor {
 }
 
 impl wl::Object for SubCompositor {
+	type Request<'a> = wl_subcompositor::Request;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		params: &[u8],
+		request: wl_subcompositor::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/wayland#wl_subcompositor:request:destroy
-				unsafe {
-					client.remove_object(self.object_id)?;
-				}
+		match request {
+			wl_subcompositor::Request::Destroy => {
+				client.remove_object(self.object_id)?;
 			}
-			1 => {
-				// https://wayland.app/protocols/wayland#wl_subcompositor:request:get_subsurface
-				let (id, surface_id, parent_id): (
-					wl::Id<wl::SubSurface>,
-					wl::Id<wl::Surface>,
-					wl::Id<wl::Surface>,
-				) = wlm::decode::from_slice(params)?;
+			wl_subcompositor::Request::GetSubsurface {
+				id,
+				surface,
+				parent,
+			} => {
+				let id = wl::Id::<wl::SubSurface>::new(id);
+				let surface_id = wl::Id::<wl::Surface>::new(surface);
+				let parent_id = wl::Id::<wl::Surface>::new(parent);
 
-				let parent = client.get_object_mut(parent_id)?;
+				let mut parent = client.get_object_mut(parent_id)?;
 				parent.push(id);
 
-				let surface = client.get_object_mut(surface_id)?;
+				let mut surface = client.get_object_mut(surface_id)?;
 				surface.set_role(wl::SurfaceRole::SubSurface {
 					mode: wl::SubSurfaceMode::Sync {
 						state_to_apply: Default::default(),
@@ -50,16 +51,17 @@ impl wl::Object for SubCompositor {
 				client.new_object(
 					id,
 					wl::SubSurface::new(id, self.conn.clone(), surface_id, parent_id),
-				);
+				)?;
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in SubCompositor"),
 		}
 
 		Ok(())
 	}
 }
 
-impl wl::Global for SubCompositor {
+pub struct SubCompositorGlobal;
+
+impl wl::Global for SubCompositorGlobal {
 	fn get_name(&self) -> &'static str {
 		"wl_subcompositor"
 	}
@@ -71,8 +73,8 @@ impl wl::Global for SubCompositor {
 	fn bind(&self, client: &mut Client, object_id: u32, _version: u32) -> Result<()> {
 		client.new_object(
 			wl::Id::new(object_id),
-			Self::new(wl::Id::new(object_id), self.conn.clone()),
-		);
+			SubCompositor::new(wl::Id::new(object_id), client.conn.clone()),
+		)?;
 		Ok(())
 	}
 }
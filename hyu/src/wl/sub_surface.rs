@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::wl_subsurface;
+
 use crate::{Client, Connection, Point, Result, state::HwState, wl};
 
 pub struct SubSurface {
@@ -29,32 +31,29 @@ impl SubSurface {
 }
 
 impl wl::Object for SubSurface {
+	type Request<'a> = wl_subsurface::Request;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		params: &[u8],
+		request: wl_subsurface::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/wayland#wl_subsurface:request:destroy
-				if let Ok(parent) = client.get_object_mut(self.parent_surface) {
+		match request {
+			wl_subsurface::Request::Destroy => {
+				if let Ok(mut parent) = client.get_object_mut(self.parent_surface) {
 					parent.children.retain(|&x| x != self.object_id);
 				}
 
-				unsafe {
-					client.remove_object(self.object_id)?;
-				}
+				client.remove_object(self.object_id)?;
 			}
-			1 => {
-				// https://wayland.app/protocols/wayland#wl_subsurface:request:set_position
-				let (x, y): (i32, i32) = wlm::decode::from_slice(params)?;
+			wl_subsurface::Request::SetPosition { x, y } => {
 				self.position = Point(x, y);
 			}
-			4 => {
-				// https://wayland.app/protocols/wayland#wl_subsurface:request:set_sync
-				let surface = client.get_object_mut(self.surface)?;
+			wl_subsurface::Request::PlaceAbove { .. } => {}
+			wl_subsurface::Request::PlaceBelow { .. } => {}
+			wl_subsurface::Request::SetSync => {
+				let mut surface = client.get_object_mut(self.surface)?;
 
 				let Some(wl::SurfaceRole::SubSurface { mode, .. }) = &mut surface.role else {
 					panic!();
@@ -64,9 +63,8 @@ impl wl::Object for SubSurface {
 					state_to_apply: Default::default(),
 				};
 			}
-			5 => {
-				// https://wayland.app/protocols/wayland#wl_subsurface:request:set_desync
-				let surface = client.get_object_mut(self.surface)?;
+			wl_subsurface::Request::SetDesync => {
+				let mut surface = client.get_object_mut(self.surface)?;
 
 				let Some(wl::SurfaceRole::SubSurface { mode, .. }) = &mut surface.role else {
 					panic!();
@@ -74,7 +72,6 @@ impl wl::Object for SubSurface {
 
 				*mode = wl::SubSurfaceMode::Desync;
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in SubSurface"),
 		}
 
 		Ok(())
@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::wl_surface;
+
 use crate::{
 	Client, Connection, Point, Result,
 	renderer::{self},
@@ -62,7 +64,7 @@ pub enum SurfaceRole {
 pub enum SurfaceRenderTexture {
 	#[default]
 	None,
-	UnattachedShmCopy((Point, renderer::vulkan::Texture)),
+	UnattachedShmCopy((Point, renderer::Texture)),
 	AttachedDmabuf(AttachedBuffer),
 }
 
@@ -178,7 +180,7 @@ impl Surface {
 
 		for i in &self.children {
 			let sub_surface = client.get_object(*i)?;
-			let surface = client.get_object_mut(sub_surface.surface)?;
+			let mut surface = client.get_object_mut(sub_surface.surface)?;
 
 			surface.frame(ms, client)?;
 		}
@@ -198,7 +200,7 @@ impl Surface {
 			let presentation_feedback = client.get_object(presentation_feedback)?;
 
 			let output = client
-				.objects_mut::<wl::Output>()
+				.objects_mut::<wl::Output>()?
 				.first()
 				.unwrap()
 				.object_id;
@@ -211,7 +213,7 @@ impl Surface {
 
 		for &child in &self.children {
 			let sub_surface = client.get_object(child)?;
-			let surface = client.get_object_mut(sub_surface.surface)?;
+			let mut surface = client.get_object_mut(sub_surface.surface)?;
 
 			surface.presentation_feedback(time, till_next_refresh, sequence, flags, client)?;
 		}
@@ -219,10 +221,10 @@ impl Surface {
 		Ok(())
 	}
 
-	pub fn vk_do_textures(
+	pub fn do_textures(
 		&mut self,
 		client: &mut Client,
-		vk: &mut renderer::vulkan::Renderer,
+		renderer: &mut dyn renderer::Renderer,
 	) -> Result<()> {
 		let Some(wl_buffer_id) = std::mem::take(&mut self.current.buffer) else {
 			return Ok(());
@@ -232,7 +234,7 @@ impl Surface {
 			match std::mem::take(&mut self.render_texture) {
 				SurfaceRenderTexture::None => {}
 				SurfaceRenderTexture::UnattachedShmCopy((_, texture)) => {
-					vk.textures_to_delete.push(texture);
+					renderer.destroy_texture(texture);
 				}
 				SurfaceRenderTexture::AttachedDmabuf(attached_buffer) => {
 					attached_buffer.release(client)?;
@@ -254,11 +256,12 @@ impl Surface {
 
 				if let &Some((size, _)) = &render_texture {
 					if size != shm_backing_storage.size {
-						render_texture = None;
+						let (_, texture) = render_texture.take().unwrap();
+						renderer.destroy_texture(texture);
 					}
 				}
 
-				shm_backing_storage.copy_into_texture(vk, &mut render_texture)?;
+				renderer.upload_shm(shm_backing_storage, &mut render_texture)?;
 
 				let Some(render_texture) = render_texture else {
 					panic!();
@@ -308,10 +311,10 @@ impl Surface {
 			if *initial_commit {
 				*initial_commit = false;
 
-				let wl_display = client.get_object_mut(wl::Id::<wl::Display>::new(1))?;
+				let serial = client.new_serial();
 
 				let wlr_layer_surface = client.get_object_mut(*wlr_layer_surface)?;
-				wlr_layer_surface.configure(wl_display.new_serial(), 0, 0)?;
+				wlr_layer_surface.configure(serial, 0, 0)?;
 
 				return Ok(());
 			}
@@ -334,7 +337,7 @@ impl Surface {
 
 		self.pending.apply_to(&mut self.current);
 
-		self.vk_do_textures(client, &mut hw_state.drm.vulkan)?;
+		self.do_textures(client, hw_state.renderer())?;
 
 		self.depth_first_sub_tree(client, &mut |client, _, surface| {
 			let Some(SurfaceRole::SubSurface { mode, .. }) = &mut surface.role else {
@@ -344,7 +347,7 @@ impl Surface {
 			// TODO: check if parent should override mode
 			if let SubSurfaceMode::Sync { state_to_apply } = mode {
 				state_to_apply.apply_to(&mut surface.current);
-				surface.vk_do_textures(client, &mut hw_state.drm.vulkan)?;
+				surface.do_textures(client, hw_state.renderer())?;
 			}
 
 			Ok(())
@@ -359,10 +362,10 @@ impl Surface {
 		callback: &mut impl FnMut(&mut Client, &mut wl::SubSurface, &mut wl::Surface) -> Result<()>,
 	) -> Result<()> {
 		for &child in &self.children {
-			let sub_surface = client.get_object_mut(child)?;
-			let surface = client.get_object_mut(sub_surface.surface)?;
+			let mut sub_surface = client.get_object_mut(child)?;
+			let mut surface = client.get_object_mut(sub_surface.surface)?;
 
-			callback(client, sub_surface, surface)?;
+			callback(client, &mut sub_surface, &mut surface)?;
 			surface.depth_first_sub_tree(client, callback)?;
 		}
 
@@ -371,16 +374,16 @@ impl Surface {
 }
 
 impl wl::Object for Surface {
+	type Request<'a> = wl_surface::Request;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		hw_state: &mut HwState,
-		op: u16,
-		params: &[u8],
+		request: wl_surface::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/wayland#wl_surface:request:destroy
+		match request {
+			wl_surface::Request::Destroy => {
 				if let SurfaceRenderTexture::AttachedDmabuf(attached_buffer) = &self.render_texture
 				{
 					attached_buffer.release(client)?;
@@ -398,67 +401,42 @@ impl wl::Object for Surface {
 					.changes
 					.push(state::Change::RemoveSurface(client.fd, self.object_id));
 
-				unsafe {
-					client.remove_object(self.object_id)?;
-				}
+				client.remove_object(self.object_id)?;
 			}
-			1 => {
-				// https://wayland.app/protocols/wayland#wl_surface:request:attach
-				let (buffer, x, y): (wl::Id<wl::Buffer>, i32, i32) =
-					wlm::decode::from_slice(params)?;
-
-				self.pending.buffer = Some(buffer);
+			wl_surface::Request::Attach { buffer, x, y } => {
+				self.pending.buffer = Some(wl::Id::new(buffer.unwrap_or(0)));
 				self.pending.offset = Some((x, y));
 			}
-			2 => {
-				// https://wayland.app/protocols/wayland#wl_surface:request:damage
-				let (_x, _y, _w, _h): (i32, i32, i32, i32) = wlm::decode::from_slice(params)?;
-			}
-			3 => {
-				// https://wayland.app/protocols/wayland#wl_surface:request:frame
-				let callback: wl::Id<wl::Callback> = wlm::decode::from_slice(params)?;
-				client.new_object(callback, wl::Callback::new(callback, self.conn.clone()));
+			wl_surface::Request::Damage { .. } => {}
+			wl_surface::Request::Frame { callback } => {
+				let callback = wl::Id::<wl::Callback>::new(callback);
+				client.new_object(callback, wl::Callback::new(callback, self.conn.clone()))?;
 
 				self.pending.frame_callbacks.push(callback);
 			}
-			4 => {
-				// https://wayland.app/protocols/wayland#wl_surface:request:set_opaque_region
-				let _region: wl::Id<wl::Region> = wlm::decode::from_slice(params)?;
-			}
-			5 => {
-				// https://wayland.app/protocols/wayland#wl_surface:request:set_input_region
-				let region: wl::Id<wl::Region> = wlm::decode::from_slice(params)?;
-
-				let region = if region.is_null() {
-					wl::Region::new(wl::Id::null(), self.conn.clone())
-				} else {
-					client.get_object(region)?.clone()
+			wl_surface::Request::SetOpaqueRegion { .. } => {}
+			wl_surface::Request::SetInputRegion { region } => {
+				let region = match region {
+					Some(region) => client
+						.get_object(wl::Id::<wl::Region>::new(region))?
+						.clone(),
+					None => wl::Region::new(wl::Id::null(), self.conn.clone()),
 				};
 
 				self.pending.input_region = Some(region);
 			}
-			6 => {
+			wl_surface::Request::Commit => {
 				self.commit(client, hw_state)?;
 			}
-			7 => {
-				// https://wayland.app/protocols/wayland#wl_surface:request:set_buffer_transform
-				let _transform: i32 = wlm::decode::from_slice(params)?;
-			}
-			8 => {
-				// https://wayland.app/protocols/wayland#wl_surface:request:set_buffer_scale
-				let _scale: i32 = wlm::decode::from_slice(params)?;
-			}
-			9 => {
-				// https://wayland.app/protocols/wayland#wl_surface:request:damage_buffer
-				let (_x, _y, _width, _height): (i32, i32, i32, i32) =
-					wlm::decode::from_slice(params)?;
-			}
-			10 => {
-				// https://wayland.app/protocols/wayland#wl_surface:request:offset
-				let (x, y): (i32, i32) = wlm::decode::from_slice(params)?;
+			wl_surface::Request::SetBufferTransform { .. } => {}
+			wl_surface::Request::SetBufferScale { .. } => {}
+			wl_surface::Request::DamageBuffer { .. } => {}
+			wl_surface::Request::Offset { x, y } => {
 				self.pending.offset = Some((x, y));
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in Surface"),
+			wl_surface::Request::GetRelease { .. } => Err(wl::ProtocolError::invalid_method(
+				"wl_surface.get_release is not supported in this version",
+			))?,
 		}
 
 		Ok(())
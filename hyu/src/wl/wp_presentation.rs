@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::wp_presentation;
+
 use crate::{Client, Connection, Result, state::HwState, wl};
 
 pub struct WpPresentation {
@@ -13,51 +15,46 @@ impl WpPresentation {
 	}
 
 	pub fn clock_id(&self, clock_id: u32) -> Result<()> {
-		// https://wayland.app/protocols/presentation-time#wp_presentation:event:clock_id
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 0,
-			args: clock_id,
-		})
+		self.conn.send_event(
+			*self.object_id,
+			wp_presentation::event::ClockId { clk_id: clock_id },
+		)
 	}
 }
 
 impl wl::Object for WpPresentation {
+	type Request<'a> = wp_presentation::Request;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		params: &[u8],
+		request: wp_presentation::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/presentation-time#wp_presentation:request:destroy
-				unsafe {
-					client.remove_object(self.object_id)?;
-				}
+		match request {
+			wp_presentation::Request::Destroy => {
+				client.remove_object(self.object_id)?;
 			}
-			1 => {
-				// https://wayland.app/protocols/presentation-time#wp_presentation:request:feedback
-				let (surface, callback): (wl::Id<wl::Surface>, wl::Id<wl::WpPresentationFeedback>) =
-					wlm::decode::from_slice(params)?;
+			wp_presentation::Request::Feedback { surface, callback } => {
+				let callback = wl::Id::<wl::WpPresentationFeedback>::new(callback);
 
 				client.new_object(
 					callback,
 					wl::WpPresentationFeedback::new(callback, self.conn.clone()),
-				);
+				)?;
 
-				let surface = client.get_object_mut(surface)?;
+				let mut surface = client.get_object_mut(wl::Id::<wl::Surface>::new(surface))?;
 				surface.pending.presentation_feedback = Some(callback);
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in WpPresentation"),
 		}
 
 		Ok(())
 	}
 }
 
-impl wl::Global for WpPresentation {
+pub struct WpPresentationGlobal;
+
+impl wl::Global for WpPresentationGlobal {
 	fn get_name(&self) -> &'static str {
 		"wp_presentation"
 	}
@@ -68,7 +65,10 @@ impl wl::Global for WpPresentation {
 
 	fn bind(&self, client: &mut Client, object_id: u32, _version: u32) -> Result<()> {
 		let object_id = wl::Id::new(object_id);
-		let object = client.new_object(object_id, Self::new(object_id, self.conn.clone()));
+		let object = client.new_object(
+			object_id,
+			WpPresentation::new(object_id, client.conn.clone()),
+		)?;
 
 		object.clock_id(1)?;
 
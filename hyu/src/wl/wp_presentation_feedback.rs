@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::wp_presentation_feedback;
+
 use crate::{Client, Connection, Result, state::HwState, wl};
 
 pub struct WpPresentationFeedback {
@@ -13,12 +15,10 @@ impl WpPresentationFeedback {
 	}
 
 	pub fn sync_output(&self, output: wl::Id<wl::Output>) -> Result<()> {
-		// https://wayland.app/protocols/presentation-time#wp_presentation_feedback:event:sync_output
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 0,
-			args: output,
-		})
+		self.conn.send_event(
+			*self.object_id,
+			wp_presentation_feedback::event::SyncOutput { output: *output },
+		)
 	}
 
 	pub fn presented(
@@ -29,37 +29,34 @@ impl WpPresentationFeedback {
 		sequence: u64,
 		flags: u32,
 	) -> Result<()> {
-		// https://wayland.app/protocols/presentation-time#wp_presentation_feedback:event:presented
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 1,
-			args: (
-				((time.as_secs() >> 32) & 0xFFFFFFFF) as u32,
-				(time.as_secs() & 0xFFFFFFFF) as u32,
-				(((time.as_nanos() % 1_000_000_000) as u64) & 0xFFFFFFFF) as u32,
-				till_next_refresh.as_nanos() as u32,
-				((sequence >> 32) & 0xFFFFFFFF) as u32,
-				(sequence & 0xFFFFFFFF) as u32,
+		self.conn.send_event(
+			*self.object_id,
+			wp_presentation_feedback::event::Presented {
+				tv_sec_hi: ((time.as_secs() >> 32) & 0xFFFFFFFF) as u32,
+				tv_sec_lo: (time.as_secs() & 0xFFFFFFFF) as u32,
+				tv_nsec: (((time.as_nanos() % 1_000_000_000) as u64) & 0xFFFFFFFF) as u32,
+				refresh: till_next_refresh.as_nanos() as u32,
+				seq_hi: ((sequence >> 32) & 0xFFFFFFFF) as u32,
+				seq_lo: (sequence & 0xFFFFFFFF) as u32,
 				flags,
-			),
-		})?;
+			},
+		)?;
 
-		unsafe {
-			client.remove_object(self.object_id)?;
-		}
+		client.remove_object(self.object_id)?;
 
 		Ok(())
 	}
 }
 
 impl wl::Object for WpPresentationFeedback {
+	type Request<'a> = wp_presentation_feedback::Request;
+
 	fn handle(
 		&mut self,
 		_client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		_params: &[u8],
+		request: wp_presentation_feedback::Request,
 	) -> Result<()> {
-		color_eyre::eyre::bail!("unknown op '{op}' in WpPresentationFeedback");
+		match request {}
 	}
 }
@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::xdg_popup;
+
 use crate::{Client, Connection, Point, Result, state::HwState, wl};
 
 pub struct XdgPopup {
@@ -29,66 +31,62 @@ impl XdgPopup {
 	}
 
 	pub fn configure(&mut self, client: &mut Client, position: Point, size: Point) -> Result<()> {
-		// https://wayland.app/protocols/xdg-shell#xdg_popup:event:configure
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 0,
-			args: (position.0, position.1, size.0, size.1),
-		})?;
+		self.send_configure(position, size)?;
+
+		let mut xdg_surface = client.get_object_mut(self.xdg_surface)?;
+		xdg_surface.configure()
+	}
+
+	/// Sends only the popup's half of a configure, the `xdg_surface` has to follow it up.
+	pub fn send_configure(&mut self, position: Point, size: Point) -> Result<()> {
+		self.conn.send_event(
+			*self.object_id,
+			xdg_popup::event::Configure {
+				x: position.0,
+				y: position.1,
+				width: size.0,
+				height: size.1,
+			},
+		)?;
 
 		self.position = position;
 		self.size = size;
 
-		let xdg_surface = client.get_object_mut(self.xdg_surface)?;
-		xdg_surface.configure()
+		Ok(())
 	}
 
 	pub fn repositioned(&self, token: u32) -> Result<()> {
-		// https://wayland.app/protocols/xdg-shell#xdg_popup:event:repositioned
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 2,
-			args: token,
-		})
+		self.conn
+			.send_event(*self.object_id, xdg_popup::event::Repositioned { token })
 	}
 }
 
 impl wl::Object for XdgPopup {
+	type Request<'a> = xdg_popup::Request;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		params: &[u8],
+		request: xdg_popup::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_popup:request:destroy
-				let parent = client.get_object_mut(self.parent_xdg_surface)?;
+		match request {
+			xdg_popup::Request::Destroy => {
+				let mut parent = client.get_object_mut(self.parent_xdg_surface)?;
 				parent.popups.retain(|&x| x != self.object_id);
 
-				unsafe {
-					client.remove_object(self.object_id)?;
-				}
+				client.remove_object(self.object_id)?;
 			}
-			1 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_popup:request:grab
-				let (_seat, _serial): (wl::Id<wl::Seat>, u32) = wlm::decode::from_slice(params)?;
-			}
-			2 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_popup:request:reposition
-				let (positioner, token): (wl::Id<wl::XdgPositioner>, u32) =
-					wlm::decode::from_slice(params)?;
-
+			xdg_popup::Request::Grab { .. } => {}
+			xdg_popup::Request::Reposition { positioner, token } => {
 				self.repositioned(token)?;
 
-				let positioner = client.get_object(positioner)?;
+				let positioner = client.get_object(wl::Id::<wl::XdgPositioner>::new(positioner))?;
 				let parent_xdg_surface = client.get_object(self.parent_xdg_surface)?;
 
-				let (position, size) = positioner.finalize(parent_xdg_surface)?;
+				let (position, size) = positioner.finalize(&parent_xdg_surface)?;
 				self.configure(client, position, size)?;
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in XdgPopup"),
 		}
 
 		Ok(())
@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::xdg_positioner;
+
 use crate::{Client, Connection, Point, Result, state::HwState, wl};
 
 pub enum Direction {
@@ -98,61 +100,42 @@ impl XdgPositioner {
 }
 
 impl wl::Object for XdgPositioner {
+	type Request<'a> = xdg_positioner::Request;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		params: &[u8],
+		request: xdg_positioner::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_positioner:request:destroy
-				unsafe {
-					client.remove_object(self.object_id)?;
-				}
+		match request {
+			xdg_positioner::Request::Destroy => {
+				client.remove_object(self.object_id)?;
 			}
-			1 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_positioner:request:set_size
-				let (width, height): (i32, i32) = wlm::decode::from_slice(params)?;
+			xdg_positioner::Request::SetSize { width, height } => {
 				self.size = Some(Point(width, height));
 			}
-			2 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_positioner:request:set_anchor_rect
-				let (x, y, width, height): (i32, i32, i32, i32) = wlm::decode::from_slice(params)?;
+			xdg_positioner::Request::SetAnchorRect {
+				x,
+				y,
+				width,
+				height,
+			} => {
 				self.anchor_rect = Some((Point(x, y), Point(width, height)));
 			}
-			3 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_positioner:request:set_anchor
-				let anchor: u32 = wlm::decode::from_slice(params)?;
+			xdg_positioner::Request::SetAnchor { anchor } => {
 				self.anchor = Direction::try_from_index(anchor);
 			}
-			4 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_positioner:request:set_gravity
-				let gravity: u32 = wlm::decode::from_slice(params)?;
+			xdg_positioner::Request::SetGravity { gravity } => {
 				self.gravity = Direction::try_from_index(gravity);
 			}
-			5 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_positioner:request:set_constraint_adjustment
-				let _constraint_adjustment: u32 = wlm::decode::from_slice(params)?;
-			}
-			6 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_positioner:request:set_offset
-				let offset: (i32, i32) = wlm::decode::from_slice(params)?;
-				self.offset = Some(Point(offset.0, offset.1));
-			}
-			7 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_positioner:request:set_reactive
-			}
-			8 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_positioner:request:set_parent_size
-				let (_parent_width, _parent_height): (i32, i32) = wlm::decode::from_slice(params)?;
-			}
-			9 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_positioner:request:set_parent_configure
-				let _serial: u32 = wlm::decode::from_slice(params)?;
+			xdg_positioner::Request::SetConstraintAdjustment { .. } => {}
+			xdg_positioner::Request::SetOffset { x, y } => {
+				self.offset = Some(Point(x, y));
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in XdgPositioner"),
+			xdg_positioner::Request::SetReactive => {}
+			xdg_positioner::Request::SetParentSize { .. } => {}
+			xdg_positioner::Request::SetParentConfigure { .. } => {}
 		}
 
 		Ok(())
@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::xdg_surface;
+
 use crate::{Client, Connection, Point, Result, state::HwState, wl};
 
 pub struct XdgSurface {
@@ -30,13 +32,9 @@ impl XdgSurface {
 	}
 
 	pub fn configure(&mut self) -> Result<()> {
-		// https://wayland.app/protocols/xdg-shell#xdg_surface:event:configure
 		let serial = self.serial();
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 0,
-			args: serial,
-		})
+		self.conn
+			.send_event(*self.object_id, xdg_surface::event::Configure { serial })
 	}
 
 	fn serial(&mut self) -> u32 {
@@ -48,23 +46,20 @@ impl XdgSurface {
 }
 
 impl wl::Object for XdgSurface {
+	type Request<'a> = xdg_surface::Request;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		params: &[u8],
+		request: xdg_surface::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_surface:request:destroy
-				unsafe {
-					client.remove_object(self.object_id)?;
-				}
+		match request {
+			xdg_surface::Request::Destroy => {
+				client.remove_object(self.object_id)?;
 			}
-			1 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_surface:request:get_toplevel
-				let id: wl::Id<wl::XdgToplevel> = wlm::decode::from_slice(params)?;
+			xdg_surface::Request::GetToplevel { id } => {
+				let id = wl::Id::<wl::XdgToplevel>::new(id);
 
 				let xdg_toplevel = wl::XdgToplevel::new(
 					client,
@@ -75,51 +70,51 @@ impl wl::Object for XdgSurface {
 					client.fd,
 				);
 
-				let xdg_toplevel = client.new_object(id, xdg_toplevel);
+				let xdg_toplevel = client.new_object(id, xdg_toplevel)?;
 
-				xdg_toplevel.configure(client)?;
+				xdg_toplevel.send_configure()?;
+				self.configure()?;
 
-				let surface = client.get_object_mut(self.surface)?;
+				let mut surface = client.get_object_mut(self.surface)?;
 				surface.set_role(wl::SurfaceRole::XdgToplevel)?;
 			}
-			2 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_surface:request:get_popup
-				let (id, parent, positioner): (
-					wl::Id<wl::XdgPopup>,
-					wl::Id<wl::XdgSurface>,
-					wl::Id<wl::XdgPositioner>,
-				) = wlm::decode::from_slice(params)?;
-
-				let xdg_popup = client.new_object(
+			xdg_surface::Request::GetPopup {
+				id,
+				parent,
+				positioner,
+			} => {
+				let id = wl::Id::<wl::XdgPopup>::new(id);
+				let parent = wl::Id::<wl::XdgSurface>::new(parent.unwrap_or(0));
+
+				let mut xdg_popup = client.new_object(
 					id,
 					wl::XdgPopup::new(id, self.conn.clone(), self.object_id, parent),
-				);
+				)?;
 
-				let positioner = client.get_object(positioner)?;
+				let positioner = client.get_object(wl::Id::<wl::XdgPositioner>::new(positioner))?;
 
-				let parent_xdg_surface = client.get_object_mut(parent)?;
+				let mut parent_xdg_surface = client.get_object_mut(parent)?;
 				parent_xdg_surface.popups.push(id);
 
-				let (position, size) = positioner.finalize(parent_xdg_surface)?;
+				let (position, size) = positioner.finalize(&parent_xdg_surface)?;
 
-				xdg_popup.configure(client, position, size)?;
+				xdg_popup.send_configure(position, size)?;
+				self.configure()?;
 
-				let surface = client.get_object_mut(self.surface)?;
+				let mut surface = client.get_object_mut(self.surface)?;
 				surface.set_role(wl::SurfaceRole::XdgPopup)?;
 			}
-			3 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_surface:request:set_window_geometry
-				let (x, y, width, height): (i32, i32, i32, i32) = wlm::decode::from_slice(params)?;
-
+			xdg_surface::Request::SetWindowGeometry {
+				x,
+				y,
+				width,
+				height,
+			} => {
 				// TODO: double buffer
 				self.position = Point(x, y);
 				self.size = Point(width, height);
 			}
-			4 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_surface:request:ack_configure
-				let _serial: u32 = wlm::decode::from_slice(params)?;
-			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in XdgSurface"),
+			xdg_surface::Request::AckConfigure { .. } => {}
 		}
 
 		Ok(())
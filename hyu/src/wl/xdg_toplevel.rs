@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::xdg_toplevel;
+
 use crate::{
 	Client, Connection, Point, Result,
 	state::{self, HwState},
@@ -15,6 +17,11 @@ pub struct XdgToplevel {
 	pub position: Point,
 	pub size: Option<Point>,
 	pub states: Vec<u32>,
+	/// Drawn with the urgent border color until it's focused. Nothing asks for attention yet,
+	/// that needs a protocol like xdg-activation.
+	pub urgent: bool,
+	/// Left out of the layout, keeps its own position and size.
+	pub floating: bool,
 }
 
 impl XdgToplevel {
@@ -37,39 +44,42 @@ impl XdgToplevel {
 			position,
 			size: None,
 			states: Vec::new(),
+			urgent: false,
+			floating: false,
 		}
 	}
 
 	pub fn configure(&self, client: &mut Client) -> Result<()> {
-		let size = self.size.unwrap_or(Point(0, 0));
-
-		// https://wayland.app/protocols/xdg-shell#xdg_toplevel:event:configure
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 0,
-			args: (size.0, size.1, &self.states),
-		})?;
+		self.send_configure()?;
 
-		let xdg_surface = client.get_object_mut(self.surface)?;
+		let mut xdg_surface = client.get_object_mut(self.surface)?;
 		xdg_surface.configure()
 	}
 
+	/// Sends only the toplevel's half of a configure, the `xdg_surface` has to follow it up.
+	pub fn send_configure(&self) -> Result<()> {
+		let size = self.size.unwrap_or(Point(0, 0));
+
+		self.conn.send_event(
+			*self.object_id,
+			xdg_toplevel::event::Configure {
+				width: size.0,
+				height: size.1,
+				states: self.states.as_slice().into(),
+			},
+		)
+	}
+
 	pub fn close(&self) -> Result<()> {
-		// https://wayland.app/protocols/xdg-shell#xdg_toplevel:event:close
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 1,
-			args: (),
-		})
+		self.conn
+			.send_event(*self.object_id, xdg_toplevel::event::Close)
 	}
 
 	pub fn configure_bounds(&self, width: i32, height: i32) -> Result<()> {
-		// https://wayland.app/protocols/xdg-shell#xdg_toplevel:event:configure_bounds
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 2,
-			args: (width, height),
-		})
+		self.conn.send_event(
+			*self.object_id,
+			xdg_toplevel::event::ConfigureBounds { width, height },
+		)
 	}
 
 	pub fn add_state(&mut self, state: u32) {
@@ -81,80 +91,94 @@ impl XdgToplevel {
 	pub fn remove_state(&mut self, state: u32) {
 		self.states.retain(|&x| x != state);
 	}
+
+	pub fn set_floating(&mut self, floating: bool) {
+		self.floating = floating;
+
+		if floating {
+			self.remove_state(xdg_toplevel::state::MAXIMIZED);
+		} else {
+			self.add_state(xdg_toplevel::state::MAXIMIZED);
+		}
+	}
 }
 
 impl wl::Object for XdgToplevel {
+	type Request<'a> = xdg_toplevel::Request<'a>;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		params: &[u8],
+		request: xdg_toplevel::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_toplevel:request:destroy
+		match request {
+			xdg_toplevel::Request::Destroy => {
 				client
 					.changes
 					.push(state::Change::RemoveToplevel(client.fd, self.object_id));
 
-				unsafe {
-					client.remove_object(self.object_id)?;
-				}
-			}
-			1 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_toplevel:request:set_parent
-				let _parent: u32 = wlm::decode::from_slice(params)?;
-			}
-			2 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_toplevel:request:set_title
-				let title: String = wlm::decode::from_slice(params)?;
-				self.title = title;
-			}
-			3 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_toplevel:request:set_app_id
-				let app_id: String = wlm::decode::from_slice(params)?;
-				self.app_id = app_id;
-			}
-			5 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_toplevel:request:move
-				let (_seat, _serial): (wl::Id<wl::Seat>, u32) = wlm::decode::from_slice(params)?;
-			}
-			6 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_toplevel:request:resize
-				let (_seat, _serial, _edges): (wl::Id<wl::Seat>, u32, u32) =
-					wlm::decode::from_slice(params)?;
+				client.remove_object(self.object_id)?;
 			}
-			7 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_toplevel:request:set_max_size
-				let (_width, _height): (i32, i32) = wlm::decode::from_slice(params)?;
+			xdg_toplevel::Request::SetParent { .. } => {}
+			xdg_toplevel::Request::SetTitle { title } => {
+				title.clone_into(&mut self.title);
 			}
-			8 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_toplevel:request:set_min_size
-				let (_width, _height): (u32, u32) = wlm::decode::from_slice(params)?;
+			xdg_toplevel::Request::SetAppId { app_id } => {
+				app_id.clone_into(&mut self.app_id);
 			}
-			9 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_toplevel:request:set_maximized
+			xdg_toplevel::Request::ShowWindowMenu { .. } => {}
+			xdg_toplevel::Request::Move { serial, .. } => {
+				client.changes.push(state::Change::StartGrab(
+					client.fd,
+					self.object_id,
+					serial,
+					state::GrabKind::Move,
+				));
 			}
-			10 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_toplevel:request:unset_maximized
-			}
-			11 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_toplevel:request:set_fullscreen
-				let _output: wl::Id<wl::Output> = wlm::decode::from_slice(params)?;
+			xdg_toplevel::Request::Resize { serial, edges, .. } => {
+				use xdg_toplevel::resize_edge;
 
-				self.add_state(2);
-				self.configure(client)?;
+				if !matches!(
+					edges,
+					resize_edge::NONE
+						| resize_edge::TOP | resize_edge::BOTTOM
+						| resize_edge::LEFT
+						| resize_edge::TOP_LEFT
+						| resize_edge::BOTTOM_LEFT
+						| resize_edge::RIGHT
+						| resize_edge::TOP_RIGHT
+						| resize_edge::BOTTOM_RIGHT
+				) {
+					Err(wl::ProtocolError::new(
+						*self.object_id,
+						xdg_toplevel::error::INVALID_RESIZE_EDGE,
+						format!("invalid resize edge {edges}"),
+					))?;
+				}
+
+				if edges != resize_edge::NONE {
+					client.changes.push(state::Change::StartGrab(
+						client.fd,
+						self.object_id,
+						serial,
+						state::GrabKind::Resize(edges),
+					));
+				}
 			}
-			12 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_toplevel:request:unset_fullscreen
-				self.remove_state(2);
+			xdg_toplevel::Request::SetMaxSize { .. } => {}
+			xdg_toplevel::Request::SetMinSize { .. } => {}
+			xdg_toplevel::Request::SetMaximized => {}
+			xdg_toplevel::Request::UnsetMaximized => {}
+			xdg_toplevel::Request::SetFullscreen { .. } => {
+				self.add_state(xdg_toplevel::state::FULLSCREEN);
 				self.configure(client)?;
 			}
-			13 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_toplevel:request:set_minimized
+			xdg_toplevel::Request::UnsetFullscreen => {
+				self.remove_state(xdg_toplevel::state::FULLSCREEN);
+				self.configure(client)?;
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in XdgToplevel"),
+			xdg_toplevel::Request::SetMinimized => {}
 		}
 
 		Ok(())
@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::xdg_wm_base;
+
 use crate::{Client, Connection, Result, state::HwState, wl};
 
 pub struct XdgWmBase {
@@ -13,54 +15,46 @@ impl XdgWmBase {
 	}
 
 	pub fn ping(&self, serial: u32) -> Result<()> {
-		// https://wayland.app/protocols/xdg-shell#xdg_wm_base:event:ping
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 0,
-			args: serial,
-		})
+		self.conn
+			.send_event(*self.object_id, xdg_wm_base::event::Ping { serial })
 	}
 }
 
 impl wl::Object for XdgWmBase {
+	type Request<'a> = xdg_wm_base::Request;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		params: &[u8],
+		request: xdg_wm_base::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_wm_base:request:destroy
-				unsafe {
-					client.remove_object(self.object_id)?;
-				}
+		match request {
+			xdg_wm_base::Request::Destroy => {
+				client.remove_object(self.object_id)?;
 			}
-			1 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_wm_base:request:create_positioner
-				let id: wl::Id<wl::XdgPositioner> = wlm::decode::from_slice(params)?;
-				client.new_object(id, wl::XdgPositioner::new(id, self.conn.clone()));
+			xdg_wm_base::Request::CreatePositioner { id } => {
+				let id = wl::Id::<wl::XdgPositioner>::new(id);
+				client.new_object(id, wl::XdgPositioner::new(id, self.conn.clone()))?;
 			}
-			2 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_wm_base:request:get_xdg_surface
-				let (id, surface): (wl::Id<wl::XdgSurface>, wl::Id<wl::Surface>) =
-					wlm::decode::from_slice(params)?;
+			xdg_wm_base::Request::GetXdgSurface { id, surface } => {
+				let id = wl::Id::<wl::XdgSurface>::new(id);
 
-				client.new_object(id, wl::XdgSurface::new(id, self.conn.clone(), surface));
+				client.new_object(
+					id,
+					wl::XdgSurface::new(id, self.conn.clone(), wl::Id::new(surface)),
+				)?;
 			}
-			3 => {
-				// https://wayland.app/protocols/xdg-shell#xdg_wm_base:request:pong
-				let _serial: u32 = wlm::decode::from_slice(params)?;
-			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in XdgWmBase"),
+			xdg_wm_base::Request::Pong { .. } => {}
 		}
 
 		Ok(())
 	}
 }
 
-impl wl::Global for XdgWmBase {
+pub struct XdgWmBaseGlobal;
+
+impl wl::Global for XdgWmBaseGlobal {
 	fn get_name(&self) -> &'static str {
 		"xdg_wm_base"
 	}
@@ -72,8 +66,8 @@ impl wl::Global for XdgWmBase {
 	fn bind(&self, client: &mut Client, object_id: u32, _version: u32) -> Result<()> {
 		client.new_object(
 			wl::Id::new(object_id),
-			Self::new(wl::Id::new(object_id), self.conn.clone()),
-		);
+			XdgWmBase::new(wl::Id::new(object_id), client.conn.clone()),
+		)?;
 		Ok(())
 	}
 }
@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::zwlr_layer_shell_v1;
+
 use crate::{Client, Connection, Result, state::HwState, wl};
 
 pub struct ZwlrLayerShellV1 {
@@ -14,46 +16,38 @@ impl ZwlrLayerShellV1 {
 }
 
 impl wl::Object for ZwlrLayerShellV1 {
+	type Request<'a> = zwlr_layer_shell_v1::Request<'a>;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		params: &[u8],
+		request: zwlr_layer_shell_v1::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/wlr-layer-shell-unstable-v1#zwlr_layer_shell_v1:request:get_layer_surface
-				let (id, surface, _output, _layer, _namespace): (
-					wl::Id<wl::ZwlrLayerSurfaceV1>,
-					wl::Id<wl::Surface>,
-					wl::Id<wl::Output>,
-					u32,
-					String,
-				) = wlm::decode::from_slice(params)?;
+		match request {
+			zwlr_layer_shell_v1::Request::GetLayerSurface { id, surface, .. } => {
+				let id = wl::Id::<wl::ZwlrLayerSurfaceV1>::new(id);
 
-				client.new_object(id, wl::ZwlrLayerSurfaceV1::new(id, self.conn.clone()));
+				client.new_object(id, wl::ZwlrLayerSurfaceV1::new(id, self.conn.clone()))?;
 
-				let wl_surface = client.get_object_mut(surface)?;
+				let mut wl_surface = client.get_object_mut(wl::Id::<wl::Surface>::new(surface))?;
 				wl_surface.set_role(wl::SurfaceRole::LayerSurface {
 					wlr_layer_surface: id,
 					initial_commit: true,
 				})?;
 			}
-			1 => {
-				// https://wayland.app/protocols/wlr-layer-shell-unstable-v1#zwlr_layer_shell_v1:request:destroy
-				unsafe {
-					client.remove_object(self.object_id)?;
-				}
+			zwlr_layer_shell_v1::Request::Destroy => {
+				client.remove_object(self.object_id)?;
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in ZwlrLayerShellV1"),
 		}
 
 		Ok(())
 	}
 }
 
-impl wl::Global for ZwlrLayerShellV1 {
+pub struct ZwlrLayerShellV1Global;
+
+impl wl::Global for ZwlrLayerShellV1Global {
 	fn get_name(&self) -> &'static str {
 		"zwlr_layer_shell_v1"
 	}
@@ -63,8 +57,8 @@ impl wl::Global for ZwlrLayerShellV1 {
 	}
 
 	fn bind(&self, client: &mut Client, object_id: u32, _version: u32) -> Result<()> {
-		let id = wl::Id::<Self>::new(object_id);
-		client.new_object(id, Self::new(id, self.conn.clone()));
+		let id = wl::Id::<ZwlrLayerShellV1>::new(object_id);
+		client.new_object(id, ZwlrLayerShellV1::new(id, client.conn.clone()))?;
 
 		Ok(())
 	}
@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::zwlr_layer_surface_v1;
+
 use crate::{Client, Connection, Result, state::HwState, wl};
 
 pub struct ZwlrLayerSurfaceV1 {
@@ -13,50 +15,43 @@ impl ZwlrLayerSurfaceV1 {
 	}
 
 	pub fn configure(&self, serial: u32, width: u32, height: u32) -> Result<()> {
-		// https://wayland.app/protocols/wlr-layer-shell-unstable-v1#zwlr_layer_surface_v1:event:configure
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 0,
-			args: (serial, width, height),
-		})
+		self.conn.send_event(
+			*self.object_id,
+			zwlr_layer_surface_v1::event::Configure {
+				serial,
+				width,
+				height,
+			},
+		)
 	}
 }
 
 impl wl::Object for ZwlrLayerSurfaceV1 {
+	type Request<'a> = zwlr_layer_surface_v1::Request;
+
 	fn handle(
 		&mut self,
-		_client: &mut Client,
+		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		params: &[u8],
+		request: zwlr_layer_surface_v1::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/wlr-layer-shell-unstable-v1#zwlr_layer_surface_v1:request:set_size
-				let (_width, _height): (u32, u32) = wlm::decode::from_slice(params)?;
-			}
-			1 => {
-				// https://wayland.app/protocols/wlr-layer-shell-unstable-v1#zwlr_layer_surface_v1:request:set_anchor
-				let _anchor: u32 = wlm::decode::from_slice(params)?;
-			}
-			2 => {
-				// https://wayland.app/protocols/wlr-layer-shell-unstable-v1#zwlr_layer_surface_v1:request:set_exclusive_zone
-				let _zone: i32 = wlm::decode::from_slice(params)?;
-			}
-			3 => {
-				// https://wayland.app/protocols/wlr-layer-shell-unstable-v1#zwlr_layer_surface_v1:request:set_margin
-				let (_top, _right, _bottom, _left): (i32, i32, i32, i32) =
-					wlm::decode::from_slice(params)?;
-			}
-			4 => {
-				// https://wayland.app/protocols/wlr-layer-shell-unstable-v1#zwlr_layer_surface_v1:request:set_keyboard_interactivity
-				let _keyboard_interactivity: u32 = wlm::decode::from_slice(params)?;
+		match request {
+			zwlr_layer_surface_v1::Request::SetSize { .. } => {}
+			zwlr_layer_surface_v1::Request::SetAnchor { .. } => {}
+			zwlr_layer_surface_v1::Request::SetExclusiveZone { .. } => {}
+			zwlr_layer_surface_v1::Request::SetMargin { .. } => {}
+			zwlr_layer_surface_v1::Request::SetKeyboardInteractivity { .. } => {}
+			zwlr_layer_surface_v1::Request::GetPopup { .. } => {
+				Err(wl::ProtocolError::implementation(
+					"zwlr_layer_surface_v1.get_popup is not supported",
+				))?
 			}
-			6 => {
-				// https://wayland.app/protocols/wlr-layer-shell-unstable-v1#zwlr_layer_surface_v1:request:ack_configure
-				let _serial: u32 = wlm::decode::from_slice(params)?;
+			zwlr_layer_surface_v1::Request::AckConfigure { .. } => {}
+			zwlr_layer_surface_v1::Request::Destroy => {
+				client.remove_object(self.object_id)?;
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in ZwlrLayerSurfaceV1"),
+			zwlr_layer_surface_v1::Request::SetLayer { .. } => {}
+			zwlr_layer_surface_v1::Request::SetExclusiveEdge { .. } => {}
 		}
 
 		Ok(())
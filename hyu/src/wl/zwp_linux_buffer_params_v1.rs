@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::zwp_linux_buffer_params_v1;
+
 use crate::{Client, Connection, Point, Result, state::HwState, wl};
 
 #[derive(Debug)]
@@ -18,11 +20,16 @@ pub struct Plane {
 	pub stride: u32,
 }
 
+const MAX_PLANES: usize = 4;
+
 pub struct ZwpLinuxBufferParamsV1 {
 	object_id: wl::Id<Self>,
 	conn: Rc<Connection>,
 	modifier: Option<u64>,
-	planes: Vec<Plane>,
+	/// Indexed by `plane_idx`.
+	planes: [Option<Plane>; MAX_PLANES],
+	/// A buffer has been created already, the params can't be used again.
+	used: bool,
 }
 
 impl ZwpLinuxBufferParamsV1 {
@@ -31,101 +38,184 @@ impl ZwpLinuxBufferParamsV1 {
 			object_id,
 			conn,
 			modifier: None,
-			planes: Vec::new(),
+			planes: Default::default(),
+			used: false,
 		}
 	}
 }
 
+fn fd_size(fd: std::os::fd::RawFd) -> Result<u64> {
+	let fd = unsafe { std::os::fd::BorrowedFd::borrow_raw(fd) };
+	Ok(nix::unistd::lseek(fd, 0, nix::unistd::Whence::SeekEnd)? as _)
+}
+
 impl wl::Object for ZwpLinuxBufferParamsV1 {
+	type Request<'a> = zwp_linux_buffer_params_v1::Request<'a>;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		hw_state: &mut HwState,
-		op: u16,
-		params: &[u8],
+		request: zwp_linux_buffer_params_v1::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/linux-dmabuf-v1#zwp_linux_buffer_params_v1:request:destroy
-				unsafe {
-					client.remove_object(self.object_id)?;
-				}
+		match request {
+			zwp_linux_buffer_params_v1::Request::Destroy => {
+				client.remove_object(self.object_id)?;
 			}
-			1 => {
-				// https://wayland.app/protocols/linux-dmabuf-v1#zwp_linux_buffer_params_v1:request:add
-				let (_plane_idx, offset, stride, modifier_hi, modifier_lo): (
-					u32,
-					u32,
-					u32,
-					u32,
-					u32,
-				) = wlm::decode::from_slice(params)?;
-
-				let fd = client.received_fds.pop_front().unwrap();
+			zwp_linux_buffer_params_v1::Request::Add {
+				fd,
+				plane_idx,
+				offset,
+				stride,
+				modifier_hi,
+				modifier_lo,
+			} => {
+				use zwp_linux_buffer_params_v1::error;
 
 				let modifier = ((modifier_hi as u64) << 32) | modifier_lo as u64;
-
-				if let Some(other_plane_mod) = &self.modifier {
-					if modifier != *other_plane_mod {
-						panic!();
-					}
+				let index = plane_idx as usize;
+
+				let error = if self.used {
+					Some((error::ALREADY_USED, "params were already used".to_owned()))
+				} else if index >= MAX_PLANES {
+					Some((
+						error::PLANE_IDX,
+						format!("plane index {plane_idx} is too big"),
+					))
+				} else if self.planes[index].is_some() {
+					Some((
+						error::PLANE_SET,
+						format!("plane {plane_idx} is already set"),
+					))
+				} else if self.modifier.is_some_and(|x| x != modifier) {
+					Some((
+						error::INVALID_FORMAT,
+						"planes have different modifiers".to_owned(),
+					))
 				} else {
-					self.modifier = Some(modifier);
+					None
+				};
+
+				if let Some((code, message)) = error {
+					nix::unistd::close(fd)?;
+					Err(wl::ProtocolError::new(*self.object_id, code, message))?;
 				}
 
-				self.planes.push(Plane { fd, offset, stride });
+				self.modifier = Some(modifier);
+				self.planes[index] = Some(Plane { fd, offset, stride });
 			}
-			3 => {
-				// https://wayland.app/protocols/linux-dmabuf-v1#zwp_linux_buffer_params_v1:request:create_immed
-				let (buffer_id, width, height, format, _flags): (
-					wl::Id<wl::Buffer>,
-					i32,
-					i32,
-					u32,
-					u32,
-				) = wlm::decode::from_slice(params)?;
-
-				assert!(!self.planes.is_empty());
-				assert!(self.modifier.is_some());
-
-				let modifier = self.modifier.unwrap();
-
-				let (image, image_view) = hw_state.drm.vulkan.create_image_from_dmabuf(
-					width as _,
-					height as _,
-					match format {
-						0x34325241 | 0x34325258 => ash::vk::Format::B8G8R8A8_UNORM,
-						0x34324241 => ash::vk::Format::R8G8B8A8_UNORM,
-						_ => panic!("{}", format),
-					},
-					modifier,
-					&self.planes,
-				)?;
+			zwp_linux_buffer_params_v1::Request::Create { .. } => {
+				Err(wl::ProtocolError::implementation(
+					"zwp_linux_buffer_params_v1.create is not supported",
+				))?
+			}
+			zwp_linux_buffer_params_v1::Request::CreateImmed {
+				buffer_id,
+				width,
+				height,
+				format,
+				..
+			} => {
+				let buffer_id = wl::Id::<wl::Buffer>::new(buffer_id);
+
+				if std::mem::replace(&mut self.used, true) {
+					Err(wl::ProtocolError::new(
+						*self.object_id,
+						zwp_linux_buffer_params_v1::error::ALREADY_USED,
+						"params were already used",
+					))?;
+				}
+
+				// the planes have to start at 0, without gaps
+				let planes = self
+					.planes
+					.iter_mut()
+					.map_while(Option::take)
+					.collect::<Vec<_>>();
+
+				if planes.is_empty() || self.planes.iter().any(Option::is_some) {
+					Err(wl::ProtocolError::new(
+						*self.object_id,
+						zwp_linux_buffer_params_v1::error::INCOMPLETE,
+						"missing planes",
+					))?;
+				}
+
+				// every plane sets it
+				let modifier = self.modifier.unwrap_or_default();
+
+				if width <= 0 || height <= 0 {
+					Err(wl::ProtocolError::new(
+						*self.object_id,
+						zwp_linux_buffer_params_v1::error::INVALID_DIMENSIONS,
+						format!("invalid buffer size {width}x{height}"),
+					))?;
+				}
+
+				// reading past the end of a dmabuf raises SIGBUS
+				for plane in &planes {
+					let end = plane.offset as u64 + plane.stride as u64 * height as u64;
+
+					if end > fd_size(plane.fd)? {
+						Err(wl::ProtocolError::new(
+							*self.object_id,
+							zwp_linux_buffer_params_v1::error::OUT_OF_BOUNDS,
+							format!(
+								"plane with offset {} and stride {} is out of bounds of its dmabuf",
+								plane.offset, plane.stride
+							),
+						))?;
+					}
+				}
+
+				// planes often share one fd
+				let mut fds = planes.iter().map(|x| x.fd).collect::<Vec<_>>();
+				fds.sort_unstable();
+				fds.dedup();
+
+				let mut size = 0usize;
+
+				for fd in fds {
+					size = size.saturating_add(fd_size(fd)? as _);
+				}
+
+				let reservation = client.reserve_dmabuf(size)?;
 
 				let attributes = DmabufAttributes {
 					width: width as _,
 					height: height as _,
 					format,
 					modifier,
-					planes: std::mem::take(&mut self.planes),
+					planes,
+				};
+
+				let mut backing_storage = wl::DmabufBackingStorage {
+					size: Point(width, height),
+					attributes,
+					image: ash::vk::Image::null(),
+					image_view: ash::vk::ImageView::null(),
+					gbm_buffer_object: None,
+					reservation,
 				};
 
+				if let Err(err) = hw_state.renderer().import_dmabuf(&mut backing_storage) {
+					Err(wl::ProtocolError::new(
+						*self.object_id,
+						zwp_linux_buffer_params_v1::error::INVALID_WL_BUFFER,
+						format!("importing the dmabuf failed: {err}"),
+					))?;
+				}
+
 				client.new_object(
 					buffer_id,
 					wl::Buffer::new(
 						buffer_id,
 						self.conn.clone(),
-						wl::BufferBackingStorage::Dmabuf(wl::DmabufBackingStorage {
-							size: Point(width, height),
-							attributes,
-							image,
-							image_view,
-							gbm_buffer_object: None,
-						}),
+						wl::BufferBackingStorage::Dmabuf(backing_storage),
 					),
-				);
+				)?;
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in ZwpLinuxBufferParamsV1"),
+			zwp_linux_buffer_params_v1::Request::SetSamplingDevice { .. } => {}
 		}
 
 		Ok(())
@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::zwp_linux_dmabuf_feedback_v1;
+
 use crate::{Client, Connection, Result, state::HwState, wl};
 
 pub struct ZwpLinuxDmabufFeedbackV1 {
@@ -22,92 +24,77 @@ impl ZwpLinuxDmabufFeedbackV1 {
 	}
 
 	pub fn done(&self) -> Result<()> {
-		// https://wayland.app/protocols/linux-dmabuf-v1#zwp_linux_dmabuf_feedback_v1:event:done
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 0,
-			args: (),
-		})
+		self.conn
+			.send_event(*self.object_id, zwp_linux_dmabuf_feedback_v1::event::Done)
 	}
 
 	pub fn format_table(&self) -> Result<()> {
 		let (fd, size) = self.formats;
 
-		// https://wayland.app/protocols/linux-dmabuf-v1#zwp_linux_dmabuf_feedback_v1:event:format_table
-		self.conn.send_message_with_fd(
-			wlm::Message {
-				object_id: *self.object_id,
-				op: 1,
-				args: size as u32,
+		self.conn.send_event(
+			*self.object_id,
+			zwp_linux_dmabuf_feedback_v1::event::FormatTable {
+				fd,
+				size: size as u32,
 			},
-			fd,
-		)?;
-
-		Ok(())
+		)
 	}
 
 	pub fn main_device(&self, device: &[nix::libc::dev_t]) -> Result<()> {
-		// https://wayland.app/protocols/linux-dmabuf-v1#zwp_linux_dmabuf_feedback_v1:event:main_device
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 2,
-			args: device,
-		})
+		self.conn.send_event(
+			*self.object_id,
+			zwp_linux_dmabuf_feedback_v1::event::MainDevice {
+				device: device.into(),
+			},
+		)
 	}
 
 	pub fn tranche_done(&self) -> Result<()> {
-		// https://wayland.app/protocols/linux-dmabuf-v1#zwp_linux_dmabuf_feedback_v1:event:tranche_done
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 3,
-			args: (),
-		})
+		self.conn.send_event(
+			*self.object_id,
+			zwp_linux_dmabuf_feedback_v1::event::TrancheDone,
+		)
 	}
 
 	pub fn tranche_target_device(&self, device: &[nix::libc::dev_t]) -> Result<()> {
-		// https://wayland.app/protocols/linux-dmabuf-v1#zwp_linux_dmabuf_feedback_v1:event:tranche_target_device
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 4,
-			args: device,
-		})
+		self.conn.send_event(
+			*self.object_id,
+			zwp_linux_dmabuf_feedback_v1::event::TrancheTargetDevice {
+				device: device.into(),
+			},
+		)
 	}
 
 	pub fn tranche_formats(&self, indices: &[u16]) -> Result<()> {
-		// https://wayland.app/protocols/linux-dmabuf-v1#zwp_linux_dmabuf_feedback_v1:event:tranche_formats
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 5,
-			args: indices,
-		})
+		self.conn.send_event(
+			*self.object_id,
+			zwp_linux_dmabuf_feedback_v1::event::TrancheFormats {
+				indices: indices.into(),
+			},
+		)
 	}
 
 	pub fn tranche_flags(&self, flags: u32) -> Result<()> {
-		// https://wayland.app/protocols/linux-dmabuf-v1#zwp_linux_dmabuf_feedback_v1:event:tranche_flags
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 6,
-			args: flags,
-		})
+		self.conn.send_event(
+			*self.object_id,
+			zwp_linux_dmabuf_feedback_v1::event::TrancheFlags { flags },
+		)
 	}
 }
 
 impl wl::Object for ZwpLinuxDmabufFeedbackV1 {
+	type Request<'a> = zwp_linux_dmabuf_feedback_v1::Request;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		_params: &[u8],
+		request: zwp_linux_dmabuf_feedback_v1::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/linux-dmabuf-v1#zwp_linux_dmabuf_feedback_v1:request:destroy
-				unsafe {
-					client.remove_object(self.object_id)?;
-				}
+		match request {
+			zwp_linux_dmabuf_feedback_v1::Request::Destroy => {
+				client.remove_object(self.object_id)?;
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in ZwpLinuxDmabufFeedbackV1"),
 		}
 
 		Ok(())
@@ -4,6 +4,8 @@ use std::{
 	rc::Rc,
 };
 
+use wlm::protocol::zwp_linux_dmabuf_v1;
+
 use crate::{Client, Config, Connection, Result, state::HwState, wl};
 
 struct Format {
@@ -76,73 +78,49 @@ impl ZwpLinuxDmabufV1 {
 	}
 
 	pub fn format(&self, format: u32) -> Result<()> {
-		// https://wayland.app/protocols/linux-dmabuf-v1#zwp_linux_dmabuf_v1:event:format
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 0,
-			args: format,
-		})
+		self.conn.send_event(
+			*self.object_id,
+			zwp_linux_dmabuf_v1::event::Format { format },
+		)
 	}
 
 	pub fn modifier(&self, format: u32, modifier_hi: u32, modifier_lo: u32) -> Result<()> {
-		// https://wayland.app/protocols/linux-dmabuf-v1#zwp_linux_dmabuf_v1:event:modifier
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 1,
-			args: (format, modifier_hi, modifier_lo),
-		})
+		self.conn.send_event(
+			*self.object_id,
+			zwp_linux_dmabuf_v1::event::Modifier {
+				format,
+				modifier_hi,
+				modifier_lo,
+			},
+		)
 	}
 }
 
 impl wl::Object for ZwpLinuxDmabufV1 {
+	type Request<'a> = zwp_linux_dmabuf_v1::Request;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		params: &[u8],
+		request: zwp_linux_dmabuf_v1::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/linux-dmabuf-v1#zwp_linux_dmabuf_v1:request:destroy
-				unsafe {
-					client.remove_object(self.object_id)?;
-				}
-			}
-			1 => {
-				// https://wayland.app/protocols/linux-dmabuf-v1#zwp_linux_dmabuf_v1:request:create_params
-				let id: wl::Id<wl::ZwpLinuxBufferParamsV1> = wlm::decode::from_slice(params)?;
-				client.new_object(id, wl::ZwpLinuxBufferParamsV1::new(id, self.conn.clone()));
+		match request {
+			zwp_linux_dmabuf_v1::Request::Destroy => {
+				client.remove_object(self.object_id)?;
 			}
-			2 => {
-				// https://wayland.app/protocols/linux-dmabuf-v1#zwp_linux_dmabuf_v1:request:get_default_feedback
-				let id: wl::Id<wl::ZwpLinuxDmabufFeedbackV1> = wlm::decode::from_slice(params)?;
-				let feedback = client.new_object(
-					id,
-					wl::ZwpLinuxDmabufFeedbackV1::new(id, self.conn.clone(), self.formats),
-				);
-
-				feedback.format_table()?;
-
-				let dev = nix::sys::stat::stat(&self.config.card)?.st_rdev;
-				feedback.main_device(&[dev])?;
-
-				feedback.tranche_target_device(&[dev])?;
-				feedback.tranche_flags(1 << 0)?;
-				feedback.tranche_formats(&(0..(FORMATS.len() as u16)).collect::<Vec<_>>())?;
-				feedback.tranche_done()?;
-
-				feedback.done()?;
+			zwp_linux_dmabuf_v1::Request::CreateParams { params_id } => {
+				let id = wl::Id::<wl::ZwpLinuxBufferParamsV1>::new(params_id);
+				client.new_object(id, wl::ZwpLinuxBufferParamsV1::new(id, self.conn.clone()))?;
 			}
-			3 => {
-				// https://wayland.app/protocols/linux-dmabuf-v1#zwp_linux_dmabuf_v1:request:get_surface_feedback
-				let (id, _surface): (wl::Id<wl::ZwpLinuxDmabufFeedbackV1>, wl::Id<wl::Surface>) =
-					wlm::decode::from_slice(params)?;
+			zwp_linux_dmabuf_v1::Request::GetDefaultFeedback { id }
+			| zwp_linux_dmabuf_v1::Request::GetSurfaceFeedback { id, .. } => {
+				let id = wl::Id::<wl::ZwpLinuxDmabufFeedbackV1>::new(id);
 
 				let feedback = client.new_object(
 					id,
 					wl::ZwpLinuxDmabufFeedbackV1::new(id, self.conn.clone(), self.formats),
-				);
+				)?;
 
 				feedback.format_table()?;
 
@@ -156,14 +134,17 @@ impl wl::Object for ZwpLinuxDmabufV1 {
 
 				feedback.done()?;
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in ZwpLinuxDmabufV1"),
 		}
 
 		Ok(())
 	}
 }
 
-impl wl::Global for ZwpLinuxDmabufV1 {
+pub struct ZwpLinuxDmabufV1Global {
+	pub config: &'static Config,
+}
+
+impl wl::Global for ZwpLinuxDmabufV1Global {
 	fn get_name(&self) -> &'static str {
 		"zwp_linux_dmabuf_v1"
 	}
@@ -174,12 +155,15 @@ impl wl::Global for ZwpLinuxDmabufV1 {
 
 	fn bind(&self, client: &mut Client, object_id: u32, version: u32) -> Result<()> {
 		let id = wl::Id::new(object_id);
-		let object = client.new_object(id, Self::new(id, self.conn.clone(), self.config)?);
-
-		assert!(version >= 3);
+		let object = client.new_object(
+			id,
+			ZwpLinuxDmabufV1::new(id, client.conn.clone(), self.config)?,
+		)?;
 
-		if version == 3 {
+		// newer versions get the formats through feedback objects
+		if version < 4 {
 			for format in FORMATS {
+				object.format(format.format)?;
 				object.modifier(
 					format.format,
 					((format.modifier >> 32) & 0xFFFF_FFFF) as u32,
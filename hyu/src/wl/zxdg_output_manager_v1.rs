@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::zxdg_output_manager_v1;
+
 use crate::{Client, Connection, Result, state::HwState, wl};
 
 pub struct ZxdgOutputManagerV1 {
@@ -19,29 +21,27 @@ impl ZxdgOutputManagerV1 {
 }
 
 impl wl::Object for ZxdgOutputManagerV1 {
+	type Request<'a> = zxdg_output_manager_v1::Request;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		params: &[u8],
+		request: zxdg_output_manager_v1::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/xdg-output-unstable-v1#zxdg_output_manager_v1:request:destroy
-				unsafe {
-					client.remove_object(self.object_id)?;
-				}
+		match request {
+			zxdg_output_manager_v1::Request::Destroy => {
+				client.remove_object(self.object_id)?;
 			}
-			1 => {
-				// https://wayland.app/protocols/xdg-output-unstable-v1#zxdg_output_manager_v1:request:get_xdg_output
-				let (id, output): (wl::Id<wl::ZxdgOutputV1>, wl::Id<wl::Output>) =
-					wlm::decode::from_slice(params)?;
+			zxdg_output_manager_v1::Request::GetXdgOutput { id, output } => {
+				let id = wl::Id::<wl::ZxdgOutputV1>::new(id);
+				let output = wl::Id::<wl::Output>::new(output);
+				let info = client.get_object(output)?.info.clone();
 
 				let xdg_output =
-					client.new_object(id, wl::ZxdgOutputV1::new(id, self.conn.clone(), output));
-				xdg_output.logical_position(0, 0)?;
-				xdg_output.logical_size(2560, 1440)?;
+					client.new_object(id, wl::ZxdgOutputV1::new(id, self.conn.clone(), output))?;
+				xdg_output.logical_position(info.position.0, info.position.1)?;
+				xdg_output.logical_size(info.size.0, info.size.1)?;
 
 				if self.version < 3 {
 					xdg_output.done()?;
@@ -50,14 +50,15 @@ impl wl::Object for ZxdgOutputManagerV1 {
 					wl_output.done()?;
 				}
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in ZxdgOutputManagerV1"),
 		}
 
 		Ok(())
 	}
 }
 
-impl wl::Global for ZxdgOutputManagerV1 {
+pub struct ZxdgOutputManagerV1Global;
+
+impl wl::Global for ZxdgOutputManagerV1Global {
 	fn get_name(&self) -> &'static str {
 		"zxdg_output_manager_v1"
 	}
@@ -67,8 +68,11 @@ impl wl::Global for ZxdgOutputManagerV1 {
 	}
 
 	fn bind(&self, client: &mut Client, object_id: u32, version: u32) -> Result<()> {
-		let id = wl::Id::<Self>::new(object_id);
-		client.new_object(id, Self::new(id, self.conn.clone(), version));
+		let id = wl::Id::<ZxdgOutputManagerV1>::new(object_id);
+		client.new_object(
+			id,
+			ZxdgOutputManagerV1::new(id, client.conn.clone(), version),
+		)?;
 
 		Ok(())
 	}
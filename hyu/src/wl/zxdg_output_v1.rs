@@ -1,5 +1,7 @@
 use std::rc::Rc;
 
+use wlm::protocol::zxdg_output_v1;
+
 use crate::{Client, Connection, Result, state::HwState, wl};
 
 pub struct ZxdgOutputV1 {
@@ -23,49 +25,38 @@ impl ZxdgOutputV1 {
 	}
 
 	pub fn logical_position(&self, x: i32, y: i32) -> Result<()> {
-		// https://wayland.app/protocols/xdg-output-unstable-v1#zxdg_output_v1:event:logical_position
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 0,
-			args: (x, y),
-		})
+		self.conn.send_event(
+			*self.object_id,
+			zxdg_output_v1::event::LogicalPosition { x, y },
+		)
 	}
 
 	pub fn logical_size(&self, width: i32, height: i32) -> Result<()> {
-		// https://wayland.app/protocols/xdg-output-unstable-v1#zxdg_output_v1:event:logical_size
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 1,
-			args: (width, height),
-		})
+		self.conn.send_event(
+			*self.object_id,
+			zxdg_output_v1::event::LogicalSize { width, height },
+		)
 	}
 
 	pub fn done(&self) -> Result<()> {
-		// https://wayland.app/protocols/xdg-output-unstable-v1#zxdg_output_v1:event:done
-		self.conn.send_message(wlm::Message {
-			object_id: *self.object_id,
-			op: 2,
-			args: (),
-		})
+		self.conn
+			.send_event(*self.object_id, zxdg_output_v1::event::Done)
 	}
 }
 
 impl wl::Object for ZxdgOutputV1 {
+	type Request<'a> = zxdg_output_v1::Request;
+
 	fn handle(
 		&mut self,
 		client: &mut Client,
 		_hw_state: &mut HwState,
-		op: u16,
-		_params: &[u8],
+		request: zxdg_output_v1::Request,
 	) -> Result<()> {
-		match op {
-			0 => {
-				// https://wayland.app/protocols/xdg-output-unstable-v1#zxdg_output_v1:request:destroy
-				unsafe {
-					client.remove_object(self.object_id)?;
-				}
+		match request {
+			zxdg_output_v1::Request::Destroy => {
+				client.remove_object(self.object_id)?;
 			}
-			_ => color_eyre::eyre::bail!("unknown op '{op}' in ZxdgOutputV1"),
 		}
 
 		Ok(())
@@ -0,0 +1,130 @@
+use wlm::protocol::{wl_callback, wl_surface, xdg_surface, xdg_toplevel};
+
+/// A compositor running the headless backend, killed when dropped.
+struct Compositor {
+	child: std::process::Child,
+	dir: std::path::PathBuf,
+}
+
+impl Compositor {
+	fn spawn(name: &str) -> Self {
+		let dir = std::env::temp_dir().join(format!("hyu-{name}-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+
+		// `HOME` points at an empty directory, so the default config is used
+		let child = std::process::Command::new(env!("CARGO_BIN_EXE_hyu"))
+			.env("HYU_BACKEND", "headless")
+			.env("HYU_SOCKET", "wayland-test")
+			.env("XDG_RUNTIME_DIR", &dir)
+			.env("HOME", &dir)
+			.env_remove("WAYLAND_DEBUG")
+			.stdout(std::process::Stdio::null())
+			.spawn()
+			.unwrap();
+
+		Self { child, dir }
+	}
+
+	fn connect(&mut self) -> wlm::client::Client {
+		let path = self.dir.join("wayland-test");
+		let start = std::time::Instant::now();
+
+		while !path.exists() {
+			if let Some(status) = self.child.try_wait().unwrap() {
+				panic!("compositor exited with {status}");
+			}
+
+			assert!(
+				start.elapsed() < std::time::Duration::from_secs(10),
+				"compositor never created its socket"
+			);
+
+			std::thread::sleep(std::time::Duration::from_millis(10));
+		}
+
+		wlm::client::Client::connect(path).unwrap()
+	}
+}
+
+impl Drop for Compositor {
+	fn drop(&mut self) {
+		let _ = self.child.kill();
+		let _ = self.child.wait();
+		let _ = std::fs::remove_dir_all(&self.dir);
+	}
+}
+
+/// Waits for the next configure of the toplevel, returns its size and the serial to ack.
+fn next_configure(
+	client: &mut wlm::client::Client,
+	toplevel: &wlm::client::Toplevel,
+) -> ((i32, i32), u32) {
+	let event = client
+		.wait_for::<xdg_toplevel::event::Configure>(toplevel.toplevel)
+		.unwrap();
+	let event = event.decode::<xdg_toplevel::event::Configure>().unwrap();
+	let size = (event.width, event.height);
+
+	let event = client
+		.wait_for::<xdg_surface::event::Configure>(toplevel.xdg_surface)
+		.unwrap();
+	let serial = event
+		.decode::<xdg_surface::event::Configure>()
+		.unwrap()
+		.serial;
+
+	(size, serial)
+}
+
+#[test]
+fn toplevel_is_tiled_and_mapped() {
+	let mut compositor = Compositor::spawn("toplevel");
+	let mut client = compositor.connect();
+
+	let toplevel = client.create_toplevel().unwrap();
+
+	// the first configure leaves the size to the client, the next one comes from the layout
+	let (size, _) = next_configure(&mut client, &toplevel);
+	assert_eq!(size, (0, 0));
+
+	// the default config has a single 1920x1080 output, without gaps or borders
+	let (size, serial) = next_configure(&mut client, &toplevel);
+	assert_eq!(size, (1920, 1080));
+
+	client
+		.send(
+			toplevel.xdg_surface,
+			&xdg_surface::Request::AckConfigure { serial },
+		)
+		.unwrap();
+
+	let buffer = client
+		.create_shm_buffer(size.0, size.1, 0xff336699)
+		.unwrap();
+
+	let callback = client.new_id(wl_callback::INTERFACE);
+	client
+		.send(toplevel.surface, &wl_surface::Request::Frame { callback })
+		.unwrap();
+	client
+		.send(
+			toplevel.surface,
+			&wl_surface::Request::Attach {
+				buffer: Some(buffer),
+				x: 0,
+				y: 0,
+			},
+		)
+		.unwrap();
+	client
+		.send(toplevel.surface, &wl_surface::Request::Commit)
+		.unwrap();
+
+	// only mapped windows are rendered, and get their frame callbacks
+	client
+		.wait_for::<wl_callback::event::Done>(callback)
+		.unwrap();
+
+	// the compositor is still happy with the client
+	client.roundtrip().unwrap();
+}
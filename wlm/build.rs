@@ -0,0 +1,800 @@
+use std::fmt::Write as _;
+
+const PROTOCOLS: &[&str] = &[
+	"wayland",
+	"xdg-shell",
+	"linux-dmabuf-v1",
+	"presentation-time",
+	"xdg-output-unstable-v1",
+	"wlr-layer-shell-unstable-v1",
+];
+
+/// Arrays that hold something other than bytes, by interface, message and argument, with the type
+/// of their elements.
+const TYPED_ARRAYS: &[(&str, &str, &str, &str)] = &[
+	("wl_keyboard", "enter", "keys", "u32"),
+	("xdg_toplevel", "configure", "states", "u32"),
+	("xdg_toplevel", "wm_capabilities", "capabilities", "u32"),
+	(
+		"zwp_linux_dmabuf_feedback_v1",
+		"main_device",
+		"device",
+		"u64",
+	),
+	(
+		"zwp_linux_dmabuf_feedback_v1",
+		"tranche_target_device",
+		"device",
+		"u64",
+	),
+	(
+		"zwp_linux_dmabuf_feedback_v1",
+		"tranche_formats",
+		"indices",
+		"u16",
+	),
+];
+
+struct Arg {
+	name: String,
+	ty: String,
+	interface: Option<String>,
+	allow_null: bool,
+	summary: Option<String>,
+	/// Element type of an array, if it's not bytes.
+	element: Option<&'static str>,
+}
+
+struct Message {
+	name: String,
+	since: u32,
+	summary: Option<String>,
+	args: Vec<Arg>,
+}
+
+struct Entry {
+	name: String,
+	value: String,
+	summary: Option<String>,
+}
+
+struct Enum {
+	name: String,
+	entries: Vec<Entry>,
+}
+
+struct Interface {
+	name: String,
+	version: u32,
+	summary: Option<String>,
+	requests: Vec<Message>,
+	events: Vec<Message>,
+	enums: Vec<Enum>,
+}
+
+fn main() {
+	let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR").unwrap());
+	let mut out = String::new();
+
+	let mut event_fds = Vec::new();
+
+	for protocol in PROTOCOLS {
+		let path = format!("protocols/{protocol}.xml");
+		println!("cargo::rerun-if-changed={path}");
+
+		let source = std::fs::read_to_string(&path).unwrap();
+		let document = roxmltree::Document::parse(&source).unwrap();
+
+		for interface in document
+			.root_element()
+			.children()
+			.filter(|x| x.has_tag_name("interface"))
+		{
+			let interface = parse_interface(interface);
+			write_interface(&mut out, protocol, &interface);
+
+			for (op, event) in interface.events.iter().enumerate() {
+				let fds = event.args.iter().filter(|x| x.ty == "fd").count();
+
+				if fds != 0 {
+					event_fds.push(format!("(\"{}\", {op}) => {fds},", interface.name));
+				}
+			}
+		}
+	}
+
+	writeln!(out, "/// Number of fds sent along with an event.").unwrap();
+	writeln!(
+		out,
+		"pub fn event_fds(interface: &str, op: u16) -> usize {{"
+	)
+	.unwrap();
+	writeln!(out, "\tmatch (interface, op) {{").unwrap();
+
+	for line in event_fds {
+		writeln!(out, "\t\t{line}").unwrap();
+	}
+
+	writeln!(out, "\t\t_ => 0,").unwrap();
+	writeln!(out, "\t}}").unwrap();
+	writeln!(out, "}}").unwrap();
+
+	std::fs::write(out_dir.join("protocol.rs"), out).unwrap();
+}
+
+fn summary(node: roxmltree::Node) -> Option<String> {
+	node.attribute("summary")
+		.or_else(|| {
+			node.children()
+				.find(|x| x.has_tag_name("description"))
+				.and_then(|x| x.attribute("summary"))
+		})
+		.map(|x| x.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+fn parse_interface(node: roxmltree::Node) -> Interface {
+	let interface = node.attribute("name").unwrap();
+
+	let parse_message = |node: roxmltree::Node| {
+		let message = node.attribute("name").unwrap();
+
+		Message {
+			name: message.to_string(),
+			since: node.attribute("since").map_or(1, |x| x.parse().unwrap()),
+			summary: summary(node),
+			args: node
+				.children()
+				.filter(|x| x.has_tag_name("arg"))
+				.map(|x| {
+					let name = x.attribute("name").unwrap();
+
+					Arg {
+						name: name.to_string(),
+						ty: x.attribute("type").unwrap().to_string(),
+						interface: x.attribute("interface").map(str::to_string),
+						allow_null: x.attribute("allow-null") == Some("true"),
+						summary: summary(x),
+						element: TYPED_ARRAYS
+							.iter()
+							.find(|x| (x.0, x.1, x.2) == (interface, message, name))
+							.map(|x| x.3),
+					}
+				})
+				.collect(),
+		}
+	};
+
+	Interface {
+		name: interface.to_string(),
+		version: node.attribute("version").unwrap().parse().unwrap(),
+		summary: summary(node),
+		requests: node
+			.children()
+			.filter(|x| x.has_tag_name("request"))
+			.map(parse_message)
+			.collect(),
+		events: node
+			.children()
+			.filter(|x| x.has_tag_name("event"))
+			.map(parse_message)
+			.collect(),
+		enums: node
+			.children()
+			.filter(|x| x.has_tag_name("enum"))
+			.map(|x| Enum {
+				name: x.attribute("name").unwrap().to_string(),
+				entries: x
+					.children()
+					.filter(|x| x.has_tag_name("entry"))
+					.map(|x| Entry {
+						name: x.attribute("name").unwrap().to_string(),
+						value: x.attribute("value").unwrap().to_string(),
+						summary: summary(x),
+					})
+					.collect(),
+			})
+			.collect(),
+	}
+}
+
+fn camel_case(name: &str) -> String {
+	name.split('_')
+		.map(|x| {
+			let mut chars = x.chars();
+			chars
+				.next()
+				.map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+				.unwrap_or_default()
+		})
+		.collect()
+}
+
+fn field_name(name: &str) -> String {
+	match name {
+		"type" | "move" | "ref" | "mod" | "fn" | "impl" | "use" | "in" | "loop" | "match" => {
+			format!("r#{name}")
+		}
+		_ => name.to_string(),
+	}
+}
+
+fn const_name(name: &str) -> String {
+	let name = name.to_ascii_uppercase();
+
+	if name.starts_with(|x: char| x.is_ascii_digit()) {
+		format!("_{name}")
+	} else {
+		name
+	}
+}
+
+fn write_doc(out: &mut String, indent: &str, summary: &Option<String>, link: Option<&str>) {
+	if let Some(summary) = summary {
+		writeln!(out, "{indent}/// {summary}").unwrap();
+	}
+
+	if let Some(link) = link {
+		if summary.is_some() {
+			writeln!(out, "{indent}///").unwrap();
+		}
+
+		writeln!(out, "{indent}/// <{link}>").unwrap();
+	}
+}
+
+fn arg_type(arg: &Arg) -> &'static str {
+	match (arg.ty.as_str(), arg.allow_null) {
+		("int", _) => "i32",
+		("uint", _) => "u32",
+		("fixed", _) => "crate::Fixed",
+		("string", false) => "&'a str",
+		("string", true) => "Option<&'a str>",
+		("object", false) | ("new_id", _) => "u32",
+		("object", true) => "Option<u32>",
+		// the elements aren't necessarily aligned in the message, so they're decoded into a copy
+		("array", _) => match arg.element {
+			Some("u16") => "std::borrow::Cow<'a, [u16]>",
+			Some("u32") => "std::borrow::Cow<'a, [u32]>",
+			Some("u64") => "std::borrow::Cow<'a, [u64]>",
+			Some(element) => panic!("unknown array element type '{element}'"),
+			None => "&'a [u8]",
+		},
+		("fd", _) => "std::os::fd::RawFd",
+		(ty, _) => panic!("unknown argument type '{ty}'"),
+	}
+}
+
+fn write_decode(out: &mut String, interface: &Interface, unknown_op: &str) {
+	writeln!(out, "\t\t\tmatch op {{").unwrap();
+
+	for (op, request) in interface.requests.iter().enumerate() {
+		let wire_args = request
+			.args
+			.iter()
+			.filter(|x| x.ty != "fd")
+			.collect::<Vec<_>>();
+
+		writeln!(out, "\t\t\t\t{op} => {{").unwrap();
+
+		let mut names = Vec::new();
+		let mut types = Vec::new();
+
+		for arg in &wire_args {
+			if is_untyped_new_id(arg) {
+				names.push("interface".to_string());
+				types.push("&str");
+				names.push("version".to_string());
+				types.push("u32");
+			}
+
+			names.push(field_name(&arg.name));
+			types.push(arg_type(arg));
+		}
+
+		writeln!(
+			out,
+			"\t\t\t\t\tlet ({}): ({}) = crate::decode::from_slice(params)?;",
+			names.iter().map(|x| format!("{x},")).collect::<String>(),
+			types.iter().map(|x| format!("{x},")).collect::<String>(),
+		)
+		.unwrap();
+
+		for arg in request.args.iter().filter(|x| x.ty == "fd") {
+			writeln!(
+				out,
+				"\t\t\t\t\tlet {} = fds.pop_front().ok_or(crate::error::Error::MissingFd)?;",
+				field_name(&arg.name)
+			)
+			.unwrap();
+		}
+
+		let fields = request
+			.args
+			.iter()
+			.flat_map(|arg| {
+				let field = field_name(&arg.name);
+
+				if is_untyped_new_id(arg) {
+					vec!["interface".to_string(), "version".to_string(), field]
+				} else {
+					vec![field]
+				}
+			})
+			.collect::<Vec<_>>();
+
+		if fields.is_empty() {
+			writeln!(out, "\t\t\t\t\tOk(Self::{})", camel_case(&request.name)).unwrap();
+		} else {
+			writeln!(
+				out,
+				"\t\t\t\t\tOk(Self::{} {{ {} }})",
+				camel_case(&request.name),
+				fields.join(", ")
+			)
+			.unwrap();
+		}
+
+		writeln!(out, "\t\t\t\t}}").unwrap();
+	}
+
+	writeln!(out, "\t\t\t\t_ => {unknown_op},").unwrap();
+	writeln!(out, "\t\t\t}}").unwrap();
+	writeln!(out, "\t\t}}").unwrap();
+}
+
+/// An untyped `new_id` is sent as the interface name, the version and the id.
+fn is_untyped_new_id(arg: &Arg) -> bool {
+	arg.ty == "new_id" && arg.interface.is_none()
+}
+
+/// Writes a message the way libwayland prints it with `WAYLAND_DEBUG`, assuming each argument
+/// is bound to a variable of the same name.
+fn write_display(out: &mut String, indent: &str, message: &Message) {
+	writeln!(out, "{indent}f.write_str(\"{}(\")?;", message.name).unwrap();
+
+	for (i, arg) in message.args.iter().enumerate() {
+		let name = field_name(&arg.name);
+		let interface = arg.interface.as_deref().unwrap_or("[unknown]");
+
+		if i != 0 {
+			writeln!(out, r#"{indent}f.write_str(", ")?;"#).unwrap();
+		}
+
+		let line = match (arg.ty.as_str(), arg.allow_null) {
+			("int" | "uint", _) => format!(r#"write!(f, "{{}}", {name})?;"#),
+			("fixed", _) => format!(r#"write!(f, "{{:.6}}", {name}.to_f64())?;"#),
+			("string", false) => format!(r#"write!(f, "\"{{}}\"", {name})?;"#),
+			("string", true) => format!(
+				r#"match {name} {{ Some(x) => write!(f, "\"{{}}\"", x)?, None => f.write_str("nil")? }}"#
+			),
+			("object", false) => format!(r#"write!(f, "{interface}#{{}}", {name})?;"#),
+			("object", true) => format!(
+				r#"match {name} {{ Some(x) => write!(f, "{interface}#{{}}", x)?, None => f.write_str("nil")? }}"#
+			),
+			("new_id", _) if is_untyped_new_id(arg) => format!(
+				r#"write!(f, "\"{{}}\", {{}}, new id [unknown]#{{}}", interface, version, {name})?;"#
+			),
+			("new_id", _) => format!(r#"write!(f, "new id {interface}#{{}}", {name})?;"#),
+			("array", _) => format!(r#"write!(f, "array[{{}}]", {name}.len())?;"#),
+			("fd", _) => format!(r#"write!(f, "fd {{}}", {name})?;"#),
+			(ty, _) => panic!("unknown argument type '{ty}'"),
+		};
+
+		writeln!(out, "{indent}{line}").unwrap();
+	}
+
+	writeln!(out, r#"{indent}f.write_str(")")"#).unwrap();
+}
+
+/// fds are sent out of band, and byte arrays need their length prefix. Typed arrays get it from
+/// being serialized as a sequence.
+fn write_serde_attributes(out: &mut String, indent: &str, arg: &Arg) {
+	match arg.ty.as_str() {
+		"fd" => writeln!(out, "{indent}#[serde(skip)]").unwrap(),
+		"array" if arg.element.is_none() => writeln!(
+			out,
+			"{indent}#[serde(serialize_with = \"crate::encode::serialize_array\")]"
+		)
+		.unwrap(),
+		_ => {}
+	}
+}
+
+/// Whether any of the arguments borrow from the message buffer.
+fn borrows(args: &[Arg]) -> bool {
+	args.iter()
+		.any(|x| matches!(x.ty.as_str(), "string" | "array") || is_untyped_new_id(x))
+}
+
+fn write_interface(out: &mut String, protocol: &str, interface: &Interface) {
+	let link = |kind: &str, name: &str| {
+		format!(
+			"https://wayland.app/protocols/{protocol}#{}:{kind}:{name}",
+			interface.name
+		)
+	};
+
+	write_doc(out, "", &interface.summary, None);
+	writeln!(out, "pub mod {} {{", interface.name).unwrap();
+	writeln!(out, "\tpub const INTERFACE: &str = \"{}\";", interface.name).unwrap();
+	writeln!(out, "\tpub const VERSION: u32 = {};", interface.version).unwrap();
+
+	for e in &interface.enums {
+		writeln!(out).unwrap();
+		write_doc(out, "\t", &None, Some(&link("enum", &e.name)));
+		writeln!(out, "\tpub mod {} {{", e.name).unwrap();
+
+		for entry in &e.entries {
+			write_doc(out, "\t\t", &entry.summary, None);
+			writeln!(
+				out,
+				"\t\tpub const {}: u32 = {};",
+				const_name(&entry.name),
+				entry.value
+			)
+			.unwrap();
+		}
+
+		writeln!(out, "\t}}").unwrap();
+	}
+
+	writeln!(out).unwrap();
+	writeln!(out, "\t#[derive(Debug, serde::Serialize)]").unwrap();
+	writeln!(out, "\t#[serde(untagged)]").unwrap();
+	let lifetime = if interface.requests.iter().any(|x| borrows(&x.args)) {
+		"<'a>"
+	} else {
+		""
+	};
+
+	writeln!(out, "\tpub enum Request{lifetime} {{").unwrap();
+
+	for request in &interface.requests {
+		write_doc(
+			out,
+			"\t\t",
+			&request.summary,
+			Some(&link("request", &request.name)),
+		);
+
+		if request.args.is_empty() {
+			writeln!(out, "\t\t{},", camel_case(&request.name)).unwrap();
+			continue;
+		}
+
+		writeln!(out, "\t\t{} {{", camel_case(&request.name)).unwrap();
+
+		for arg in &request.args {
+			if is_untyped_new_id(arg) {
+				writeln!(out, "\t\t\tinterface: &'a str,").unwrap();
+				writeln!(out, "\t\t\tversion: u32,").unwrap();
+			}
+
+			write_doc(out, "\t\t\t", &arg.summary, None);
+			write_serde_attributes(out, "\t\t\t", arg);
+
+			writeln!(out, "\t\t\t{}: {},", field_name(&arg.name), arg_type(arg)).unwrap();
+		}
+
+		writeln!(out, "\t\t}},").unwrap();
+	}
+
+	writeln!(out, "\t}}").unwrap();
+	writeln!(out).unwrap();
+
+	writeln!(
+		out,
+		"\timpl<'a> crate::Request<'a> for Request{lifetime} {{"
+	)
+	.unwrap();
+	writeln!(out, "\t\tconst INTERFACE: &'static str = INTERFACE;").unwrap();
+	writeln!(out).unwrap();
+	writeln!(out, "\t\tfn decode(").unwrap();
+	writeln!(out, "\t\t\top: u16,").unwrap();
+	writeln!(out, "\t\t\tparams: &'a [u8],").unwrap();
+	writeln!(
+		out,
+		"\t\t\tfds: &mut std::collections::VecDeque<std::os::fd::RawFd>,"
+	)
+	.unwrap();
+	writeln!(out, "\t\t) -> crate::error::Result<Self> {{").unwrap();
+
+	let unknown_op = "Err(crate::error::Error::UnknownOpcode { interface: INTERFACE, op })";
+
+	if interface.requests.is_empty() {
+		writeln!(out, "\t\t\tlet _ = (params, fds);").unwrap();
+		writeln!(out, "\t\t\t{unknown_op}").unwrap();
+		writeln!(out, "\t\t}}").unwrap();
+	} else if interface
+		.requests
+		.iter()
+		.all(|x| x.args.iter().all(|x| x.ty != "fd"))
+	{
+		writeln!(out, "\t\t\tlet _ = fds;").unwrap();
+	}
+
+	if !interface.requests.is_empty() {
+		write_decode(out, interface, unknown_op);
+	}
+
+	for (function, ty, values) in [
+		(
+			"op",
+			"u16",
+			(0..interface.requests.len())
+				.map(|x| x.to_string())
+				.collect::<Vec<_>>(),
+		),
+		(
+			"name",
+			"&'static str",
+			interface
+				.requests
+				.iter()
+				.map(|x| format!("\"{}\"", x.name))
+				.collect(),
+		),
+		(
+			"since",
+			"u32",
+			interface
+				.requests
+				.iter()
+				.map(|x| x.since.to_string())
+				.collect(),
+		),
+	] {
+		writeln!(out).unwrap();
+		writeln!(out, "\t\tfn {function}(&self) -> {ty} {{").unwrap();
+
+		if interface.requests.is_empty() {
+			writeln!(out, "\t\t\tmatch *self {{}}").unwrap();
+		} else {
+			writeln!(out, "\t\t\tmatch self {{").unwrap();
+
+			for (request, value) in interface.requests.iter().zip(values) {
+				let pattern = if request.args.is_empty() {
+					""
+				} else {
+					" { .. }"
+				};
+
+				writeln!(
+					out,
+					"\t\t\t\tSelf::{}{pattern} => {value},",
+					camel_case(&request.name)
+				)
+				.unwrap();
+			}
+
+			writeln!(out, "\t\t\t}}").unwrap();
+		}
+
+		writeln!(out, "\t\t}}").unwrap();
+	}
+
+	let fds = interface
+		.requests
+		.iter()
+		.filter_map(|request| {
+			let fds = request
+				.args
+				.iter()
+				.filter(|x| x.ty == "fd")
+				.map(|x| field_name(&x.name))
+				.collect::<Vec<_>>();
+
+			(!fds.is_empty()).then(|| (camel_case(&request.name), fds))
+		})
+		.collect::<Vec<_>>();
+
+	if !fds.is_empty() {
+		writeln!(out).unwrap();
+		writeln!(out, "\t\tfn fds(&self) -> Vec<std::os::fd::RawFd> {{").unwrap();
+		writeln!(out, "\t\t\tmatch self {{").unwrap();
+
+		for (name, fds) in fds {
+			writeln!(
+				out,
+				"\t\t\t\tSelf::{name} {{ {}, .. }} => vec![{}],",
+				fds.join(", "),
+				fds.iter()
+					.map(|x| format!("*{x}"))
+					.collect::<Vec<_>>()
+					.join(", ")
+			)
+			.unwrap();
+		}
+
+		writeln!(out, "\t\t\t\t_ => Vec::new(),").unwrap();
+		writeln!(out, "\t\t\t}}").unwrap();
+		writeln!(out, "\t\t}}").unwrap();
+	}
+
+	writeln!(out, "\t}}").unwrap();
+	writeln!(out).unwrap();
+
+	writeln!(
+		out,
+		"\timpl std::fmt::Display for Request{} {{",
+		if lifetime.is_empty() { "" } else { "<'_>" }
+	)
+	.unwrap();
+	writeln!(
+		out,
+		"\t\tfn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {{"
+	)
+	.unwrap();
+
+	if interface.requests.is_empty() {
+		writeln!(out, "\t\t\tlet _ = f;").unwrap();
+		writeln!(out, "\t\t\tmatch *self {{}}").unwrap();
+	} else {
+		writeln!(out, "\t\t\tmatch self {{").unwrap();
+
+		for request in &interface.requests {
+			let fields = request
+				.args
+				.iter()
+				.flat_map(|arg| {
+					let field = field_name(&arg.name);
+
+					if is_untyped_new_id(arg) {
+						vec!["interface".to_string(), "version".to_string(), field]
+					} else {
+						vec![field]
+					}
+				})
+				.collect::<Vec<_>>();
+
+			let pattern = if fields.is_empty() {
+				String::new()
+			} else {
+				format!(" {{ {} }}", fields.join(", "))
+			};
+
+			writeln!(
+				out,
+				"\t\t\t\tSelf::{}{pattern} => {{",
+				camel_case(&request.name)
+			)
+			.unwrap();
+			write_display(out, "\t\t\t\t\t", request);
+			writeln!(out, "\t\t\t\t}}").unwrap();
+		}
+
+		writeln!(out, "\t\t\t}}").unwrap();
+	}
+
+	writeln!(out, "\t\t}}").unwrap();
+	writeln!(out, "\t}}").unwrap();
+	writeln!(out).unwrap();
+
+	writeln!(out, "\tpub mod event {{").unwrap();
+
+	for (op, event) in interface.events.iter().enumerate() {
+		let name = camel_case(&event.name);
+		let lifetime = if borrows(&event.args) { "<'a>" } else { "" };
+
+		if op != 0 {
+			writeln!(out).unwrap();
+		}
+
+		write_doc(
+			out,
+			"\t\t",
+			&event.summary,
+			Some(&link("event", &event.name)),
+		);
+		writeln!(out, "\t\t#[derive(serde::Serialize, serde::Deserialize)]").unwrap();
+
+		if event.args.is_empty() {
+			writeln!(out, "\t\tpub struct {name};").unwrap();
+		} else {
+			writeln!(out, "\t\tpub struct {name}{lifetime} {{").unwrap();
+
+			for arg in &event.args {
+				write_doc(out, "\t\t\t", &arg.summary, None);
+
+				write_serde_attributes(out, "\t\t\t", arg);
+
+				if arg.ty == "string" && arg.allow_null {
+					writeln!(out, "\t\t\t#[serde(borrow)]").unwrap();
+				}
+
+				writeln!(
+					out,
+					"\t\t\tpub {}: {},",
+					field_name(&arg.name),
+					arg_type(arg)
+				)
+				.unwrap();
+			}
+
+			writeln!(out, "\t\t}}").unwrap();
+		}
+
+		writeln!(out).unwrap();
+		writeln!(
+			out,
+			"\t\timpl crate::Event for {name}{} {{",
+			if borrows(&event.args) { "<'_>" } else { "" }
+		)
+		.unwrap();
+		writeln!(
+			out,
+			"\t\t\tconst INTERFACE: &'static str = super::INTERFACE;"
+		)
+		.unwrap();
+		writeln!(out, "\t\t\tconst NAME: &'static str = \"{}\";", event.name).unwrap();
+		writeln!(out, "\t\t\tconst OP: u16 = {op};").unwrap();
+		writeln!(out, "\t\t\tconst SINCE: u32 = {};", event.since).unwrap();
+
+		let fds = event
+			.args
+			.iter()
+			.filter(|x| x.ty == "fd")
+			.map(|x| format!("self.{}", field_name(&x.name)))
+			.collect::<Vec<_>>();
+
+		if !fds.is_empty() {
+			writeln!(out).unwrap();
+			writeln!(out, "\t\t\tfn fds(&self) -> Vec<std::os::fd::RawFd> {{").unwrap();
+			writeln!(out, "\t\t\t\tvec![{}]", fds.join(", ")).unwrap();
+			writeln!(out, "\t\t\t}}").unwrap();
+			writeln!(out).unwrap();
+			writeln!(
+				out,
+				"\t\t\tfn set_fds(&mut self, fds: &mut std::collections::VecDeque<std::os::fd::RawFd>) -> crate::error::Result<()> {{"
+			)
+			.unwrap();
+
+			for fd in &fds {
+				writeln!(
+					out,
+					"\t\t\t\t{fd} = fds.pop_front().ok_or(crate::error::Error::MissingFd)?;"
+				)
+				.unwrap();
+			}
+
+			writeln!(out, "\t\t\t\tOk(())").unwrap();
+			writeln!(out, "\t\t\t}}").unwrap();
+		}
+
+		writeln!(out, "\t\t}}").unwrap();
+		writeln!(out).unwrap();
+		writeln!(
+			out,
+			"\t\timpl std::fmt::Display for {name}{} {{",
+			if borrows(&event.args) { "<'_>" } else { "" }
+		)
+		.unwrap();
+		writeln!(
+			out,
+			"\t\t\tfn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {{"
+		)
+		.unwrap();
+
+		if !event.args.is_empty() {
+			writeln!(
+				out,
+				"\t\t\t\tlet Self {{ {} }} = self;",
+				event
+					.args
+					.iter()
+					.map(|x| field_name(&x.name))
+					.collect::<Vec<_>>()
+					.join(", ")
+			)
+			.unwrap();
+		}
+
+		write_display(out, "\t\t\t\t", event);
+		writeln!(out, "\t\t\t}}").unwrap();
+		writeln!(out, "\t\t}}").unwrap();
+	}
+
+	writeln!(out, "\t}}").unwrap();
+	writeln!(out, "}}").unwrap();
+	writeln!(out).unwrap();
+}
@@ -0,0 +1,491 @@
+//! A small blocking Wayland client, enough to drive a compositor without a real toolkit.
+
+use std::os::fd::AsRawFd as _;
+
+use crate::{
+	error::{Error, Result},
+	protocol::{
+		wl_callback, wl_compositor, wl_display, wl_registry, wl_shm, wl_shm_pool, wl_surface,
+		xdg_popup, xdg_positioner, xdg_surface, xdg_toplevel, xdg_wm_base,
+	},
+};
+
+const DISPLAY_ID: u32 = 1;
+const HEADER_SIZE: usize = 4 + 2 + 2;
+
+pub struct Global {
+	pub name: u32,
+	pub interface: String,
+	pub version: u32,
+}
+
+/// An event that has been read from the socket, but not decoded yet.
+pub struct RawEvent {
+	pub object_id: u32,
+	pub interface: &'static str,
+	pub op: u16,
+	pub params: Vec<u8>,
+	pub fds: Vec<std::os::fd::RawFd>,
+}
+
+impl RawEvent {
+	pub fn is<E: crate::Event>(&self) -> bool {
+		self.interface == E::INTERFACE && self.op == E::OP
+	}
+
+	pub fn decode<'a, E: crate::Event + serde::Deserialize<'a>>(&'a self) -> Result<E> {
+		if !self.is::<E>() {
+			return Err(Error::Message(format!(
+				"expected {}.{}, got op {} on {}#{}",
+				E::INTERFACE,
+				E::NAME,
+				self.op,
+				self.interface,
+				self.object_id
+			)));
+		}
+
+		let mut ret: E = crate::decode::from_slice(&self.params)?;
+		ret.set_fds(&mut self.fds.iter().copied().collect())?;
+
+		Ok(ret)
+	}
+}
+
+pub struct Toplevel {
+	pub surface: u32,
+	pub xdg_surface: u32,
+	pub toplevel: u32,
+}
+
+pub struct Popup {
+	pub surface: u32,
+	pub xdg_surface: u32,
+	pub popup: u32,
+}
+
+pub struct Client {
+	stream: std::os::unix::net::UnixStream,
+	next_id: u32,
+	objects: std::collections::HashMap<u32, &'static str>,
+	registry: u32,
+	globals: Vec<Global>,
+	bound: std::collections::HashMap<&'static str, u32>,
+	input: Vec<u8>,
+	fds: std::collections::VecDeque<std::os::fd::RawFd>,
+	events: std::collections::VecDeque<RawEvent>,
+}
+
+impl std::os::fd::AsRawFd for Client {
+	fn as_raw_fd(&self) -> std::os::fd::RawFd {
+		self.stream.as_raw_fd()
+	}
+}
+
+impl Client {
+	/// Connects to the compositor listening on `path`, and waits for the initial globals.
+	pub fn connect(path: impl AsRef<std::path::Path>) -> Result<Self> {
+		let mut ret = Self {
+			stream: std::os::unix::net::UnixStream::connect(path)?,
+			next_id: DISPLAY_ID + 1,
+			objects: [(DISPLAY_ID, wl_display::INTERFACE)].into(),
+			registry: 0,
+			globals: Vec::new(),
+			bound: Default::default(),
+			input: Vec::new(),
+			fds: Default::default(),
+			events: Default::default(),
+		};
+
+		ret.registry = ret.new_id(wl_registry::INTERFACE);
+		ret.send(
+			DISPLAY_ID,
+			&wl_display::Request::GetRegistry {
+				registry: ret.registry,
+			},
+		)?;
+
+		ret.roundtrip()?;
+		Ok(ret)
+	}
+
+	/// Connects to `$WAYLAND_DISPLAY` in `$XDG_RUNTIME_DIR`, the same way libwayland does.
+	pub fn connect_to_env() -> Result<Self> {
+		let display = std::env::var("WAYLAND_DISPLAY").unwrap_or_else(|_| "wayland-0".into());
+
+		if display.starts_with('/') {
+			return Self::connect(display);
+		}
+
+		let runtime_dir = std::env::var("XDG_RUNTIME_DIR")
+			.map_err(|_| Error::Message("XDG_RUNTIME_DIR is not set".to_string()))?;
+
+		Self::connect(std::path::PathBuf::from_iter([runtime_dir, display]))
+	}
+
+	pub fn globals(&self) -> &[Global] {
+		&self.globals
+	}
+
+	pub fn new_id(&mut self, interface: &'static str) -> u32 {
+		let id = self.next_id;
+		self.next_id += 1;
+
+		self.objects.insert(id, interface);
+		id
+	}
+
+	pub fn send<'a, R: crate::Request<'a>>(&mut self, object_id: u32, request: &R) -> Result<()> {
+		let bytes = crate::Message {
+			object_id,
+			op: request.op(),
+			args: request,
+		}
+		.to_vec()?;
+
+		let fds = request.fds();
+		let cmsgs = if fds.is_empty() {
+			Vec::new()
+		} else {
+			vec![nix::sys::socket::ControlMessage::ScmRights(&fds)]
+		};
+
+		nix::sys::socket::sendmsg::<()>(
+			self.stream.as_raw_fd(),
+			&[std::io::IoSlice::new(&bytes)],
+			&cmsgs,
+			nix::sys::socket::MsgFlags::empty(),
+			None,
+		)
+		.map_err(std::io::Error::from)?;
+
+		Ok(())
+	}
+
+	/// Binds the global implementing `interface`, at `version` or the highest version the
+	/// compositor supports, whichever is lower.
+	pub fn bind(&mut self, interface: &'static str, version: u32) -> Result<u32> {
+		let Some(global) = self.globals.iter().find(|x| x.interface == interface) else {
+			return Err(Error::Message(format!("no global implements {interface}")));
+		};
+
+		let (name, version) = (global.name, global.version.min(version));
+		let id = self.new_id(interface);
+
+		self.send(
+			self.registry,
+			&wl_registry::Request::Bind {
+				name,
+				interface,
+				version,
+				id,
+			},
+		)?;
+
+		Ok(id)
+	}
+
+	/// Like [`Client::bind`], but only binds `interface` the first time it's asked for.
+	pub fn global(&mut self, interface: &'static str, version: u32) -> Result<u32> {
+		if let Some(id) = self.bound.get(interface) {
+			return Ok(*id);
+		}
+
+		let id = self.bind(interface, version)?;
+		self.bound.insert(interface, id);
+
+		Ok(id)
+	}
+
+	/// Waits until the compositor has handled every request sent so far.
+	pub fn roundtrip(&mut self) -> Result<()> {
+		let callback = self.new_id(wl_callback::INTERFACE);
+		self.send(DISPLAY_ID, &wl_display::Request::Sync { callback })?;
+
+		self.wait_for::<wl_callback::event::Done>(callback)?;
+		Ok(())
+	}
+
+	/// Returns the oldest event that hasn't been waited for yet, blocking until one arrives.
+	pub fn next_event(&mut self) -> Result<RawEvent> {
+		loop {
+			if let Some(event) = self.events.pop_front() {
+				return Ok(event);
+			}
+
+			self.read()?;
+		}
+	}
+
+	/// Reads whatever the compositor has sent so far. Only blocks if nothing has been sent, so
+	/// this is meant to be called once the socket is readable.
+	pub fn dispatch(&mut self) -> Result<()> {
+		self.read()
+	}
+
+	/// Returns the oldest queued event, without reading from the socket.
+	pub fn poll_event(&mut self) -> Option<RawEvent> {
+		self.events.pop_front()
+	}
+
+	/// Blocks until `object_id` receives an `E`. Other events are queued for later.
+	pub fn wait_for<E: crate::Event>(&mut self, object_id: u32) -> Result<RawEvent> {
+		loop {
+			if let Some(position) = self
+				.events
+				.iter()
+				.position(|x| x.object_id == object_id && x.is::<E>())
+			{
+				return Ok(self.events.remove(position).unwrap());
+			}
+
+			self.read()?;
+		}
+	}
+
+	fn read(&mut self) -> Result<()> {
+		let mut buffer = [0u8; 0x1000];
+		let mut cmsg_buffer = nix::cmsg_space!([std::os::fd::RawFd; 28]);
+
+		let (len, fds) = {
+			let mut iov = [std::io::IoSliceMut::new(&mut buffer)];
+			let message = nix::sys::socket::recvmsg::<()>(
+				self.stream.as_raw_fd(),
+				&mut iov,
+				Some(&mut cmsg_buffer),
+				nix::sys::socket::MsgFlags::empty(),
+			)
+			.map_err(std::io::Error::from)?;
+
+			let mut fds = Vec::new();
+
+			for cmsg in message.cmsgs().map_err(std::io::Error::from)? {
+				if let nix::sys::socket::ControlMessageOwned::ScmRights(x) = cmsg {
+					fds.extend(x);
+				}
+			}
+
+			(message.bytes, fds)
+		};
+
+		if len == 0 {
+			return Err(Error::Message(
+				"compositor closed the connection".to_string(),
+			));
+		}
+
+		self.input.extend(&buffer[..len]);
+		self.fds.extend(fds);
+
+		while self.input.len() >= HEADER_SIZE {
+			let object_id = u32::from_ne_bytes(self.input[0..4].try_into().unwrap());
+			let op = u16::from_ne_bytes(self.input[4..6].try_into().unwrap());
+			let size = u16::from_ne_bytes(self.input[6..8].try_into().unwrap()) as usize;
+
+			if size < HEADER_SIZE {
+				return Err(Error::Message(format!("event with invalid size {size}")));
+			}
+
+			if self.input.len() < size {
+				break;
+			}
+
+			let params = self.input[HEADER_SIZE..size].to_vec();
+			self.input.drain(..size);
+
+			let interface = self.objects.get(&object_id).copied().unwrap_or("[unknown]");
+			let fds = crate::protocol::event_fds(interface, op);
+
+			if self.fds.len() < fds {
+				return Err(Error::MissingFd);
+			}
+
+			let fds = self.fds.drain(..fds).collect();
+
+			self.handle(RawEvent {
+				object_id,
+				interface,
+				op,
+				params,
+				fds,
+			})?;
+		}
+
+		Ok(())
+	}
+
+	fn handle(&mut self, event: RawEvent) -> Result<()> {
+		if event.is::<wl_display::event::Error>() {
+			let error = event.decode::<wl_display::event::Error>()?;
+
+			return Err(Error::Protocol {
+				object_id: error.object_id,
+				code: error.code,
+				message: error.message.to_string(),
+			});
+		} else if event.is::<wl_display::event::DeleteId>() {
+			let id = event.decode::<wl_display::event::DeleteId>()?.id;
+			self.objects.remove(&id);
+		} else if event.is::<wl_registry::event::Global>() {
+			let global = event.decode::<wl_registry::event::Global>()?;
+
+			self.globals.push(Global {
+				name: global.name,
+				interface: global.interface.to_string(),
+				version: global.version,
+			});
+		} else if event.is::<wl_registry::event::GlobalRemove>() {
+			let name = event.decode::<wl_registry::event::GlobalRemove>()?.name;
+			self.globals.retain(|x| x.name != name);
+		} else if event.is::<xdg_wm_base::event::Ping>() {
+			let serial = event.decode::<xdg_wm_base::event::Ping>()?.serial;
+			self.send(event.object_id, &xdg_wm_base::Request::Pong { serial })?;
+		} else {
+			self.events.push_back(event);
+		}
+
+		Ok(())
+	}
+
+	pub fn create_surface(&mut self) -> Result<u32> {
+		let compositor = self.global(wl_compositor::INTERFACE, wl_compositor::VERSION)?;
+
+		let id = self.new_id(wl_surface::INTERFACE);
+		self.send(compositor, &wl_compositor::Request::CreateSurface { id })?;
+
+		Ok(id)
+	}
+
+	/// Creates an ARGB8888 buffer filled with `color`.
+	pub fn create_shm_buffer(&mut self, width: i32, height: i32, color: u32) -> Result<u32> {
+		use std::os::unix::fs::FileExt as _;
+
+		let shm = self.global(wl_shm::INTERFACE, 1)?;
+
+		let stride = width * 4;
+		let size = stride * height;
+
+		let file = std::fs::File::from(
+			nix::sys::memfd::memfd_create(c"wlm-shm", nix::sys::memfd::MFdFlags::MFD_CLOEXEC)
+				.map_err(std::io::Error::from)?,
+		);
+
+		let pixels = std::iter::repeat_n(color.to_ne_bytes(), (width * height) as usize)
+			.flatten()
+			.collect::<Vec<_>>();
+		file.write_all_at(&pixels, 0)?;
+
+		let pool = self.new_id(wl_shm_pool::INTERFACE);
+		self.send(
+			shm,
+			&wl_shm::Request::CreatePool {
+				id: pool,
+				fd: file.as_raw_fd(),
+				size,
+			},
+		)?;
+
+		let id = self.new_id(crate::protocol::wl_buffer::INTERFACE);
+		self.send(
+			pool,
+			&wl_shm_pool::Request::CreateBuffer {
+				id,
+				offset: 0,
+				width,
+				height,
+				stride,
+				format: wl_shm::format::ARGB8888,
+			},
+		)?;
+
+		self.send(pool, &wl_shm_pool::Request::Destroy)?;
+		Ok(id)
+	}
+
+	/// Creates a toplevel and commits its surface, the compositor answers with a configure.
+	pub fn create_toplevel(&mut self) -> Result<Toplevel> {
+		let wm_base = self.global(xdg_wm_base::INTERFACE, xdg_wm_base::VERSION)?;
+		let surface = self.create_surface()?;
+
+		let xdg_surface = self.new_id(xdg_surface::INTERFACE);
+		self.send(
+			wm_base,
+			&xdg_wm_base::Request::GetXdgSurface {
+				id: xdg_surface,
+				surface,
+			},
+		)?;
+
+		let toplevel = self.new_id(xdg_toplevel::INTERFACE);
+		self.send(
+			xdg_surface,
+			&xdg_surface::Request::GetToplevel { id: toplevel },
+		)?;
+		self.send(surface, &wl_surface::Request::Commit)?;
+
+		Ok(Toplevel {
+			surface,
+			xdg_surface,
+			toplevel,
+		})
+	}
+
+	/// Creates a popup of `parent`, an xdg_surface, anchored to the given rectangle.
+	pub fn create_popup(
+		&mut self,
+		parent: u32,
+		(x, y): (i32, i32),
+		(width, height): (i32, i32),
+	) -> Result<Popup> {
+		let wm_base = self.global(xdg_wm_base::INTERFACE, xdg_wm_base::VERSION)?;
+
+		let positioner = self.new_id(xdg_positioner::INTERFACE);
+		self.send(
+			wm_base,
+			&xdg_wm_base::Request::CreatePositioner { id: positioner },
+		)?;
+		self.send(
+			positioner,
+			&xdg_positioner::Request::SetSize { width, height },
+		)?;
+		self.send(
+			positioner,
+			&xdg_positioner::Request::SetAnchorRect {
+				x,
+				y,
+				width: 1,
+				height: 1,
+			},
+		)?;
+
+		let surface = self.create_surface()?;
+
+		let xdg_surface = self.new_id(xdg_surface::INTERFACE);
+		self.send(
+			wm_base,
+			&xdg_wm_base::Request::GetXdgSurface {
+				id: xdg_surface,
+				surface,
+			},
+		)?;
+
+		let popup = self.new_id(xdg_popup::INTERFACE);
+		self.send(
+			xdg_surface,
+			&xdg_surface::Request::GetPopup {
+				id: popup,
+				parent: Some(parent),
+				positioner,
+			},
+		)?;
+
+		self.send(positioner, &xdg_positioner::Request::Destroy)?;
+		self.send(surface, &wl_surface::Request::Commit)?;
+
+		Ok(Popup {
+			surface,
+			xdg_surface,
+			popup,
+		})
+	}
+}
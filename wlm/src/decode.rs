@@ -8,7 +8,7 @@ pub fn from_slice<'a, T: serde::Deserialize<'a>>(input: &'a [u8]) -> Result<T> {
 	if deserializer.input.is_empty() {
 		Ok(ret)
 	} else {
-		Err(Error::Message("TrailingData".to_string()))
+		Err(Error::TrailingData(deserializer.input.len()))
 	}
 }
 
@@ -16,7 +16,66 @@ pub struct Deserializer<'de> {
 	input: &'de [u8],
 }
 
-impl Deserializer<'_> {}
+impl<'de> Deserializer<'de> {
+	fn take(&mut self, n: usize) -> Result<&'de [u8]> {
+		if self.input.len() < n {
+			return Err(Error::UnexpectedEof {
+				needed: n,
+				remaining: self.input.len(),
+			});
+		}
+
+		let (ret, rest) = self.input.split_at(n);
+		self.input = rest;
+
+		Ok(ret)
+	}
+
+	fn take_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+		let mut ret = [0u8; N];
+		ret.copy_from_slice(self.take(N)?);
+
+		Ok(ret)
+	}
+
+	fn peek_u32(&self) -> Result<u32> {
+		let Some(bytes) = self.input.first_chunk::<4>() else {
+			return Err(Error::UnexpectedEof {
+				needed: 4,
+				remaining: self.input.len(),
+			});
+		};
+
+		Ok(u32::from_ne_bytes(*bytes))
+	}
+
+	fn read_u32(&mut self) -> Result<u32> {
+		Ok(u32::from_ne_bytes(self.take_array()?))
+	}
+
+	/// Reads a length-prefixed byte array, skipping the padding up to the next 32-bit boundary.
+	fn read_array(&mut self) -> Result<&'de [u8]> {
+		let size = self.read_u32()? as usize;
+		let ret = self.take(size)?;
+
+		self.take(size.next_multiple_of(4) - size)?;
+		Ok(ret)
+	}
+
+	fn read_str(&mut self) -> Result<&'de str> {
+		let bytes = self.read_array()?;
+
+		let Some((&0, bytes)) = bytes.split_last() else {
+			return Err(if bytes.is_empty() {
+				Error::UnexpectedNull
+			} else {
+				Error::MissingNulTerminator
+			});
+		};
+
+		std::str::from_utf8(bytes).map_err(Error::InvalidUtf8)
+	}
+}
 
 impl<'de> serde::de::Deserializer<'de> for &mut Deserializer<'de> {
 	type Error = Error;
@@ -25,176 +84,172 @@ impl<'de> serde::de::Deserializer<'de> for &mut Deserializer<'de> {
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		Err(Error::Unsupported("any"))
 	}
 
 	fn deserialize_bool<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		Err(Error::Unsupported("bool"))
 	}
 
-	fn deserialize_i8<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+	fn deserialize_i8<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		visitor.visit_i8(i8::from_ne_bytes(self.take_array()?))
 	}
 
-	fn deserialize_i16<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+	fn deserialize_i16<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		visitor.visit_i16(i16::from_ne_bytes(self.take_array()?))
 	}
 
 	fn deserialize_i32<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		let value = i32::from_ne_bytes(self.input[..4].try_into()?);
-		self.input = &self.input[4..];
-		visitor.visit_i32(value)
+		visitor.visit_i32(i32::from_ne_bytes(self.take_array()?))
 	}
 
-	fn deserialize_i64<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+	fn deserialize_i64<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		visitor.visit_i64(i64::from_ne_bytes(self.take_array()?))
 	}
 
-	fn deserialize_u8<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+	fn deserialize_u8<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		visitor.visit_u8(u8::from_ne_bytes(self.take_array()?))
 	}
 
-	fn deserialize_u16<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+	fn deserialize_u16<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		visitor.visit_u16(u16::from_ne_bytes(self.take_array()?))
 	}
 
 	fn deserialize_u32<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		let value = u32::from_ne_bytes(self.input[..4].try_into()?);
-		self.input = &self.input[4..];
-		visitor.visit_u32(value)
+		visitor.visit_u32(self.read_u32()?)
 	}
 
-	fn deserialize_u64<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+	fn deserialize_u64<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		visitor.visit_u64(u64::from_ne_bytes(self.take_array()?))
 	}
 
 	fn deserialize_f32<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		Err(Error::Unsupported("f32"))
 	}
 
 	fn deserialize_f64<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		Err(Error::Unsupported("f64"))
 	}
 
 	fn deserialize_char<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		Err(Error::Unsupported("char"))
 	}
 
-	fn deserialize_str<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+	fn deserialize_str<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		visitor.visit_borrowed_str(self.read_str()?)
 	}
 
 	fn deserialize_string<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		let size = u32::from_ne_bytes(self.input[..4].try_into()?);
-		self.input = &self.input[4..];
-
-		let value = String::from(std::str::from_utf8(&self.input[..size as usize - 1]).unwrap());
-		self.input = &self.input[size as _..];
-
-		if size % 4 != 0 {
-			self.input = &self.input[(4 - (size % 4)) as _..];
-		}
-
-		visitor.visit_string(value)
+		visitor.visit_string(self.read_str()?.to_string())
 	}
 
-	fn deserialize_bytes<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+	fn deserialize_bytes<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		visitor.visit_borrowed_bytes(self.read_array()?)
 	}
 
-	fn deserialize_byte_buf<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+	fn deserialize_byte_buf<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		visitor.visit_byte_buf(self.read_array()?.to_vec())
 	}
 
-	fn deserialize_option<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+	fn deserialize_option<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		// null objects and null strings are both sent as a single zero
+		if self.peek_u32()? == 0 {
+			self.take(4)?;
+			visitor.visit_none()
+		} else {
+			visitor.visit_some(self)
+		}
 	}
 
-	fn deserialize_unit<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+	fn deserialize_unit<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		visitor.visit_unit()
 	}
 
 	fn deserialize_unit_struct<V>(
 		self,
 		_name: &'static str,
-		_visitor: V,
+		visitor: V,
 	) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		visitor.visit_unit()
 	}
 
 	fn deserialize_newtype_struct<V>(
 		self,
 		_name: &'static str,
-		_visitor: V,
+		visitor: V,
 	) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		visitor.visit_newtype_struct(self)
 	}
 
-	fn deserialize_seq<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
+	fn deserialize_seq<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		visitor.visit_seq(ArrayAccess {
+			deserializer: Deserializer {
+				input: self.read_array()?,
+			},
+		})
 	}
 
 	fn deserialize_tuple<V>(
@@ -212,31 +267,31 @@ impl<'de> serde::de::Deserializer<'de> for &mut Deserializer<'de> {
 		self,
 		_name: &'static str,
 		_len: usize,
-		_visitor: V,
+		visitor: V,
 	) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		visitor.visit_seq(self)
 	}
 
 	fn deserialize_map<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		Err(Error::Unsupported("map"))
 	}
 
 	fn deserialize_struct<V>(
 		self,
 		_name: &'static str,
 		_fields: &'static [&'static str],
-		_visitor: V,
+		visitor: V,
 	) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		visitor.visit_seq(self)
 	}
 
 	fn deserialize_enum<V>(
@@ -248,21 +303,21 @@ impl<'de> serde::de::Deserializer<'de> for &mut Deserializer<'de> {
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		Err(Error::Unsupported("enum"))
 	}
 
 	fn deserialize_identifier<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		Err(Error::Unsupported("identifier"))
 	}
 
 	fn deserialize_ignored_any<V>(self, _visitor: V) -> std::result::Result<V::Value, Self::Error>
 	where
 		V: serde::de::Visitor<'de>,
 	{
-		todo!()
+		Err(Error::Unsupported("ignored_any"))
 	}
 }
 
@@ -276,3 +331,156 @@ impl<'de> serde::de::SeqAccess<'de> for &mut Deserializer<'de> {
 		seed.deserialize(&mut **self).map(Some)
 	}
 }
+
+/// Decodes the elements of an `array` argument, such as a list of `u32` states or keys.
+struct ArrayAccess<'de> {
+	deserializer: Deserializer<'de>,
+}
+
+impl<'de> serde::de::SeqAccess<'de> for ArrayAccess<'de> {
+	type Error = Error;
+
+	fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+	where
+		T: serde::de::DeserializeSeed<'de>,
+	{
+		if self.deserializer.input.is_empty() {
+			return Ok(None);
+		}
+
+		seed.deserialize(&mut self.deserializer).map(Some)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::from_slice;
+	use crate::{Fixed, encode::to_vec, error::Error, protocol};
+
+	fn words(words: &[u32]) -> Vec<u8> {
+		words.iter().flat_map(|x| x.to_ne_bytes()).collect()
+	}
+
+	#[test]
+	fn truncated() {
+		for len in 0..4 {
+			assert!(matches!(
+				from_slice::<u32>(&[0; 3][..len]),
+				Err(Error::UnexpectedEof { needed: 4, remaining }) if remaining == len
+			));
+
+			assert!(matches!(
+				from_slice::<Fixed>(&[0; 3][..len]),
+				Err(Error::UnexpectedEof { needed: 4, .. })
+			));
+		}
+
+		let bytes = to_vec(&(1u32, "hello", -1i32)).unwrap();
+
+		for len in 0..bytes.len() {
+			assert!(matches!(
+				from_slice::<(u32, &str, i32)>(&bytes[..len]),
+				Err(Error::UnexpectedEof { .. })
+			));
+		}
+	}
+
+	#[test]
+	fn trailing() {
+		assert!(matches!(
+			from_slice::<u32>(&[0; 5]),
+			Err(Error::TrailingData(1))
+		));
+		assert!(matches!(
+			from_slice::<(u32, u32)>(&[0; 12]),
+			Err(Error::TrailingData(4))
+		));
+	}
+
+	#[test]
+	fn misaligned() {
+		// "abc\0" fits exactly, "abcd\0" needs three bytes of padding
+		let mut bytes = to_vec(&"abcd").unwrap();
+		bytes.truncate(bytes.len() - 1);
+
+		assert!(matches!(
+			from_slice::<&str>(&bytes),
+			Err(Error::UnexpectedEof {
+				needed: 3,
+				remaining: 2
+			})
+		));
+
+		let mut bytes = words(&[5]);
+		bytes.extend([1, 2, 3, 4, 5]);
+
+		assert!(matches!(
+			from_slice::<protocol::wl_keyboard::event::Enter>(&[words(&[0, 0]), bytes].concat()),
+			Err(Error::UnexpectedEof {
+				needed: 3,
+				remaining: 0
+			})
+		));
+	}
+
+	#[test]
+	fn array_too_long() {
+		let mut bytes = words(&[0, 0, 8]);
+		bytes.extend([1, 2, 3, 4]);
+
+		assert!(matches!(
+			from_slice::<protocol::wl_keyboard::event::Enter>(&bytes),
+			Err(Error::UnexpectedEof {
+				needed: 8,
+				remaining: 4
+			})
+		));
+	}
+
+	#[test]
+	fn array_element_cut_off() {
+		// six bytes, so the second element only has half of its bytes
+		let mut bytes = words(&[6]);
+		bytes.extend([1, 0, 0, 0, 2, 0, 0, 0]);
+
+		assert!(matches!(
+			from_slice::<Vec<u32>>(&bytes),
+			Err(Error::UnexpectedEof {
+				needed: 4,
+				remaining: 2
+			})
+		));
+
+		assert_eq!(from_slice::<Vec<u16>>(&bytes).unwrap(), [1, 0, 2]);
+	}
+
+	#[test]
+	fn string_without_nul() {
+		let mut bytes = words(&[4]);
+		bytes.extend(b"abcd");
+
+		assert!(matches!(
+			from_slice::<&str>(&bytes),
+			Err(Error::MissingNulTerminator)
+		));
+	}
+
+	#[test]
+	fn null_string() {
+		assert!(matches!(
+			from_slice::<&str>(&[0; 4]),
+			Err(Error::UnexpectedNull)
+		));
+	}
+
+	#[test]
+	fn invalid_utf8() {
+		let mut bytes = words(&[4]);
+		bytes.extend([b'a', 0xff, 0xfe, 0]);
+
+		assert!(matches!(
+			from_slice::<&str>(&bytes),
+			Err(Error::InvalidUtf8(_))
+		));
+	}
+}
@@ -4,6 +4,13 @@ pub struct Serializer {
 	output: Vec<u8>,
 }
 
+pub fn serialize_array<S: serde::Serializer>(
+	value: &[u8],
+	serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+	serializer.serialize_bytes(value)
+}
+
 pub fn to_vec(value: &impl serde::Serialize) -> Result<Vec<u8>> {
 	let mut serializer = Serializer { output: Vec::new() };
 	value.serialize(&mut serializer)?;
@@ -22,24 +29,26 @@ impl<'a> serde::ser::Serializer for &'a mut Serializer {
 
 	type SerializeTupleStruct = Self;
 
-	type SerializeTupleVariant = Self;
+	type SerializeTupleVariant = serde::ser::Impossible<(), Error>;
 
-	type SerializeMap = Self;
+	type SerializeMap = serde::ser::Impossible<(), Error>;
 
 	type SerializeStruct = Self;
 
-	type SerializeStructVariant = Self;
+	type SerializeStructVariant = serde::ser::Impossible<(), Error>;
 
 	fn serialize_bool(self, _v: bool) -> Result<()> {
-		todo!()
+		Err(Error::Unsupported("bool"))
 	}
 
-	fn serialize_i8(self, _v: i8) -> Result<()> {
-		todo!()
+	fn serialize_i8(self, v: i8) -> Result<()> {
+		self.output.extend(v.to_ne_bytes());
+		Ok(())
 	}
 
-	fn serialize_i16(self, _v: i16) -> Result<()> {
-		todo!()
+	fn serialize_i16(self, v: i16) -> Result<()> {
+		self.output.extend(v.to_ne_bytes());
+		Ok(())
 	}
 
 	fn serialize_i32(self, v: i32) -> Result<()> {
@@ -47,12 +56,14 @@ impl<'a> serde::ser::Serializer for &'a mut Serializer {
 		Ok(())
 	}
 
-	fn serialize_i64(self, _v: i64) -> Result<()> {
-		todo!()
+	fn serialize_i64(self, v: i64) -> Result<()> {
+		self.output.extend(v.to_ne_bytes());
+		Ok(())
 	}
 
-	fn serialize_u8(self, _v: u8) -> Result<()> {
-		todo!()
+	fn serialize_u8(self, v: u8) -> Result<()> {
+		self.output.extend(v.to_ne_bytes());
+		Ok(())
 	}
 
 	fn serialize_u16(self, v: u16) -> Result<()> {
@@ -71,42 +82,53 @@ impl<'a> serde::ser::Serializer for &'a mut Serializer {
 	}
 
 	fn serialize_f32(self, _v: f32) -> Result<()> {
-		todo!()
+		Err(Error::Unsupported("f32"))
 	}
 
 	fn serialize_f64(self, _v: f64) -> Result<()> {
-		todo!()
+		Err(Error::Unsupported("f64"))
 	}
 
 	fn serialize_char(self, _v: char) -> Result<()> {
-		todo!()
+		Err(Error::Unsupported("char"))
 	}
 
 	fn serialize_str(self, v: &str) -> Result<()> {
-		let size = v.len() as u32 + 1;
+		let size = u32::try_from(v.len() + 1).map_err(|_| Error::MessageTooLarge(v.len() + 1))?;
 
 		self.output.extend(size.to_ne_bytes());
 
 		self.output.extend(v.as_bytes());
 		self.output.push(0);
 
-		if size % 4 != 0 {
+		if !size.is_multiple_of(4) {
 			self.output.extend((0..(4 - (size % 4))).map(|_| 0u8));
 		}
 
 		Ok(())
 	}
 
-	fn serialize_bytes(self, _v: &[u8]) -> Result<()> {
-		todo!()
+	fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+		let size = u32::try_from(v.len()).map_err(|_| Error::MessageTooLarge(v.len()))?;
+
+		self.output.extend(size.to_ne_bytes());
+		self.output.extend(v);
+
+		if !size.is_multiple_of(4) {
+			self.output.extend((0..(4 - (size % 4))).map(|_| 0u8));
+		}
+
+		Ok(())
 	}
 
 	fn serialize_none(self) -> Result<()> {
-		todo!()
+		// null objects and null strings are both sent as a single zero
+		self.output.extend(0u32.to_ne_bytes());
+		Ok(())
 	}
 
-	fn serialize_some<T: ?Sized + serde::Serialize>(self, _value: &T) -> Result<()> {
-		todo!()
+	fn serialize_some<T: ?Sized + serde::Serialize>(self, value: &T) -> Result<()> {
+		value.serialize(self)
 	}
 
 	fn serialize_unit(self) -> Result<()> {
@@ -114,7 +136,7 @@ impl<'a> serde::ser::Serializer for &'a mut Serializer {
 	}
 
 	fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
-		todo!()
+		Ok(())
 	}
 
 	fn serialize_unit_variant(
@@ -123,15 +145,15 @@ impl<'a> serde::ser::Serializer for &'a mut Serializer {
 		_variant_index: u32,
 		_variant: &'static str,
 	) -> Result<()> {
-		todo!()
+		Err(Error::Unsupported("enum"))
 	}
 
 	fn serialize_newtype_struct<T: ?Sized + serde::Serialize>(
 		self,
 		_name: &'static str,
-		_value: &T,
+		value: &T,
 	) -> Result<()> {
-		todo!()
+		value.serialize(self)
 	}
 
 	fn serialize_newtype_variant<T: ?Sized + serde::Serialize>(
@@ -141,7 +163,7 @@ impl<'a> serde::ser::Serializer for &'a mut Serializer {
 		_variant: &'static str,
 		_value: &T,
 	) -> Result<()> {
-		todo!()
+		Err(Error::Unsupported("enum"))
 	}
 
 	fn serialize_seq(
@@ -166,7 +188,7 @@ impl<'a> serde::ser::Serializer for &'a mut Serializer {
 		_name: &'static str,
 		_len: usize,
 	) -> std::result::Result<Self::SerializeTupleStruct, Self::Error> {
-		todo!()
+		Ok(self)
 	}
 
 	fn serialize_tuple_variant(
@@ -176,14 +198,14 @@ impl<'a> serde::ser::Serializer for &'a mut Serializer {
 		_variant: &'static str,
 		_len: usize,
 	) -> std::result::Result<Self::SerializeTupleVariant, Self::Error> {
-		todo!()
+		Err(Error::Unsupported("enum"))
 	}
 
 	fn serialize_map(
 		self,
 		_len: Option<usize>,
 	) -> std::result::Result<Self::SerializeMap, Self::Error> {
-		todo!()
+		Err(Error::Unsupported("map"))
 	}
 
 	fn serialize_struct(
@@ -201,7 +223,7 @@ impl<'a> serde::ser::Serializer for &'a mut Serializer {
 		_variant: &'static str,
 		_len: usize,
 	) -> std::result::Result<Self::SerializeStructVariant, Self::Error> {
-		todo!()
+		Err(Error::Unsupported("enum"))
 	}
 }
 
@@ -224,44 +246,12 @@ impl serde::ser::SerializeTupleStruct for &mut Serializer {
 
 	type Error = Error;
 
-	fn serialize_field<T: ?Sized + serde::Serialize>(&mut self, _value: &T) -> Result<()> {
-		todo!()
-	}
-
-	fn end(self) -> Result<()> {
-		todo!()
-	}
-}
-
-impl serde::ser::SerializeTupleVariant for &mut Serializer {
-	type Ok = ();
-
-	type Error = Error;
-
-	fn serialize_field<T: ?Sized + serde::Serialize>(&mut self, _value: &T) -> Result<()> {
-		todo!()
-	}
-
-	fn end(self) -> Result<()> {
-		todo!()
-	}
-}
-
-impl serde::ser::SerializeMap for &mut Serializer {
-	type Ok = ();
-
-	type Error = Error;
-
-	fn serialize_key<T: ?Sized + serde::Serialize>(&mut self, _key: &T) -> Result<()> {
-		todo!()
-	}
-
-	fn serialize_value<T: ?Sized + serde::Serialize>(&mut self, _value: &T) -> Result<()> {
-		todo!()
+	fn serialize_field<T: ?Sized + serde::Serialize>(&mut self, value: &T) -> Result<()> {
+		value.serialize(&mut **self)
 	}
 
 	fn end(self) -> Result<()> {
-		todo!()
+		Ok(())
 	}
 }
 
@@ -284,24 +274,6 @@ impl serde::ser::SerializeStruct for &mut Serializer {
 	}
 }
 
-impl serde::ser::SerializeStructVariant for &mut Serializer {
-	type Ok = ();
-
-	type Error = Error;
-
-	fn serialize_field<T: ?Sized + serde::Serialize>(
-		&mut self,
-		_key: &'static str,
-		_value: &T,
-	) -> Result<()> {
-		todo!()
-	}
-
-	fn end(self) -> Result<()> {
-		todo!()
-	}
-}
-
 pub struct ArraySerializer<'a> {
 	storage: Vec<u8>,
 	output: &'a mut Vec<u8>,
@@ -321,15 +293,329 @@ impl serde::ser::SerializeSeq for ArraySerializer<'_> {
 	}
 
 	fn end(self) -> Result<Self::Ok> {
-		let len = self.storage.len() as u32;
+		let len = u32::try_from(self.storage.len())
+			.map_err(|_| Error::MessageTooLarge(self.storage.len()))?;
 
 		self.output.extend(len.to_ne_bytes());
 		self.output.extend(self.storage);
 
-		if len % 4 != 0 {
+		if !len.is_multiple_of(4) {
 			self.output.extend((0..(4 - (len % 4))).map(|_| 0u8));
 		}
 
 		Ok(())
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use crate::{Fixed, Request, decode, protocol};
+
+	fn round_trip<T>(value: T)
+	where
+		T: serde::Serialize + for<'a> serde::Deserialize<'a> + PartialEq + std::fmt::Debug,
+	{
+		let bytes = super::to_vec(&value).unwrap();
+		assert!(bytes.len().is_multiple_of(4));
+		assert_eq!(decode::from_slice::<T>(&bytes).unwrap(), value);
+	}
+
+	/// xorshift, so the generated values are the same on every run.
+	fn generate(count: usize) -> impl Iterator<Item = u32> {
+		let mut state = 0x2545_f491u32;
+
+		std::iter::repeat_with(move || {
+			state ^= state << 13;
+			state ^= state >> 17;
+			state ^= state << 5;
+			state
+		})
+		.take(count)
+	}
+
+	/// Every value close to zero and to the ends of the range, and a lot of generated ones.
+	fn u32_values() -> impl Iterator<Item = u32> {
+		(0..=0x1000)
+			.chain(u32::MAX - 0x1000..=u32::MAX)
+			.chain((0..32).map(|x| 1 << x))
+			.chain(generate(0x10000))
+	}
+
+	#[test]
+	fn int() {
+		for value in u32_values() {
+			round_trip(value as i32);
+			round_trip((value as i32).wrapping_neg());
+		}
+	}
+
+	#[test]
+	fn uint() {
+		for value in u32_values() {
+			round_trip(value);
+		}
+	}
+
+	#[test]
+	fn fixed() {
+		for value in u32_values() {
+			round_trip(Fixed(value as i32));
+		}
+
+		for value in [0.0, 1.5, -1.5, 100.25, -8000.0] {
+			assert_eq!(Fixed::from_f64(value).to_f64(), value);
+		}
+
+		assert_eq!(Fixed::from(3), Fixed::from_f64(3.0));
+	}
+
+	#[test]
+	fn string() {
+		for value in ["", "a", "ab", "abc", "abcd", "wl_compositor", "ünïcödé"] {
+			let bytes = super::to_vec(&value).unwrap();
+
+			// length including the nul, the string, the nul, then padding
+			assert_eq!(bytes.len(), 4 + (value.len() + 1).next_multiple_of(4));
+			assert_eq!(bytes[..4], (value.len() as u32 + 1).to_ne_bytes());
+			assert_eq!(decode::from_slice::<&str>(&bytes).unwrap(), value);
+		}
+	}
+
+	#[test]
+	fn string_padding() {
+		// one, two, three and four byte characters, so every length of padding is hit with each
+		for c in ['a', 'ü', '€', '🦀'] {
+			for count in 0..8 {
+				let value = String::from_iter(std::iter::repeat_n(c, count));
+				let bytes = super::to_vec(&value.as_str()).unwrap();
+
+				let padding = (4 - (value.len() + 1) % 4) % 4;
+				assert_eq!(bytes.len(), 4 + value.len() + 1 + padding);
+
+				// the nul and the padding are all zero
+				assert!(bytes[4 + value.len()..].iter().all(|&x| x == 0));
+
+				assert_eq!(decode::from_slice::<&str>(&bytes).unwrap(), value);
+				assert_eq!(decode::from_slice::<String>(&bytes).unwrap(), value);
+			}
+		}
+	}
+
+	#[test]
+	fn nullable() {
+		assert_eq!(super::to_vec(&None::<&str>).unwrap(), 0u32.to_ne_bytes());
+		assert_eq!(decode::from_slice::<Option<&str>>(&[0; 4]).unwrap(), None);
+
+		let bytes = super::to_vec(&Some("abc")).unwrap();
+		assert_eq!(
+			decode::from_slice::<Option<&str>>(&bytes).unwrap(),
+			Some("abc")
+		);
+
+		round_trip(None::<u32>);
+
+		for value in u32_values().filter(|&x| x != 0) {
+			round_trip(Some(value));
+		}
+	}
+
+	#[test]
+	fn nullable_zero() {
+		// a null object is sent as id 0, so an object with id 0 can't be told apart from null
+		assert_eq!(super::to_vec(&Some(0u32)).unwrap(), 0u32.to_ne_bytes());
+		assert_eq!(
+			decode::from_slice::<Option<u32>>(&super::to_vec(&Some(0u32)).unwrap()).unwrap(),
+			None
+		);
+	}
+
+	#[test]
+	fn nullable_object() {
+		for buffer in [None, Some(1), Some(0xff00_0000), Some(u32::MAX)] {
+			let request = protocol::wl_surface::Request::Attach {
+				buffer,
+				x: -4,
+				y: 7,
+			};
+			let bytes = super::to_vec(&request).unwrap();
+			assert_eq!(bytes.len(), 12);
+
+			let decoded = protocol::wl_surface::Request::decode(
+				request.op(),
+				&bytes,
+				&mut Default::default(),
+			)
+			.unwrap();
+
+			let protocol::wl_surface::Request::Attach {
+				buffer: decoded,
+				x,
+				y,
+			} = decoded
+			else {
+				panic!("decoded {decoded:?}");
+			};
+
+			assert_eq!((decoded, x, y), (buffer, -4, 7));
+		}
+	}
+
+	#[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+	struct Bytes<'a> {
+		#[serde(serialize_with = "super::serialize_array")]
+		bytes: &'a [u8],
+	}
+
+	#[test]
+	fn array() {
+		let keys = [1, 2, 3, 4, 5, 6, 7, 8, 9];
+
+		for len in 0..=keys.len() {
+			let event = protocol::wl_keyboard::event::Enter {
+				serial: 42,
+				surface: 3,
+				keys: keys[..len].into(),
+			};
+
+			let bytes = super::to_vec(&event).unwrap();
+			assert_eq!(bytes.len(), 12 + len * 4);
+
+			let decoded: protocol::wl_keyboard::event::Enter = decode::from_slice(&bytes).unwrap();
+			assert_eq!(decoded.serial, 42);
+			assert_eq!(decoded.surface, 3);
+			assert_eq!(*decoded.keys, keys[..len]);
+		}
+	}
+
+	#[test]
+	fn array_generated() {
+		let values = generate(0x1000).collect::<Vec<_>>();
+
+		for len in (0..=64).chain([255, 256, 257, 0x1000 - 1, 0x1000]) {
+			let values = values[..len].to_vec();
+
+			let bytes = super::to_vec(&values).unwrap();
+
+			// the length is in bytes
+			assert_eq!(bytes[..4], (len as u32 * 4).to_ne_bytes());
+			assert_eq!(bytes.len(), 4 + len * 4);
+
+			round_trip(values.clone());
+
+			let shorts = values.iter().map(|&x| x as u16).collect::<Vec<_>>();
+			let bytes = super::to_vec(&shorts).unwrap();
+
+			// odd numbers of 16-bit elements get padded
+			assert_eq!(bytes[..4], (len as u32 * 2).to_ne_bytes());
+			assert_eq!(bytes.len(), 4 + (len * 2).next_multiple_of(4));
+
+			round_trip(shorts);
+
+			let bytes = values.iter().map(|&x| x as u8).collect::<Vec<_>>();
+			let value = Bytes { bytes: &bytes };
+			let encoded = super::to_vec(&value).unwrap();
+
+			assert_eq!(encoded[..4], (len as u32).to_ne_bytes());
+			assert_eq!(encoded.len(), 4 + len.next_multiple_of(4));
+			assert_eq!(decode::from_slice::<Bytes>(&encoded).unwrap(), value);
+		}
+	}
+
+	#[test]
+	fn typed_arrays() {
+		let states = [
+			protocol::xdg_toplevel::state::MAXIMIZED,
+			protocol::xdg_toplevel::state::ACTIVATED,
+		];
+
+		let event = protocol::xdg_toplevel::event::Configure {
+			width: 1920,
+			height: 1080,
+			states: states[..].into(),
+		};
+
+		let bytes = super::to_vec(&event).unwrap();
+		assert_eq!(bytes.len(), 12 + 8);
+
+		let decoded: protocol::xdg_toplevel::event::Configure = decode::from_slice(&bytes).unwrap();
+		assert_eq!(*decoded.states, states);
+
+		let event = protocol::zwp_linux_dmabuf_feedback_v1::event::MainDevice {
+			device: [0x1234_5678_9abc_def0][..].into(),
+		};
+
+		let bytes = super::to_vec(&event).unwrap();
+		assert_eq!(bytes.len(), 4 + 8);
+		assert_eq!(bytes[4..], 0x1234_5678_9abc_def0u64.to_ne_bytes());
+	}
+
+	#[test]
+	fn object() {
+		let request = protocol::wl_display::Request::GetRegistry { registry: 2 };
+		let bytes = super::to_vec(&request).unwrap();
+
+		let decoded =
+			protocol::wl_display::Request::decode(request.op(), &bytes, &mut Default::default())
+				.unwrap();
+
+		let protocol::wl_display::Request::GetRegistry { registry } = decoded else {
+			panic!("decoded {decoded:?}");
+		};
+
+		assert_eq!(registry, 2);
+	}
+
+	#[test]
+	fn new_id() {
+		// untyped new_ids carry the interface and version in front of the id
+		let request = protocol::wl_registry::Request::Bind {
+			name: 1,
+			interface: "wl_compositor",
+			version: 6,
+			id: 3,
+		};
+
+		let bytes = super::to_vec(&request).unwrap();
+
+		let decoded =
+			protocol::wl_registry::Request::decode(request.op(), &bytes, &mut Default::default())
+				.unwrap();
+
+		let protocol::wl_registry::Request::Bind {
+			name,
+			interface,
+			version,
+			id,
+		} = decoded;
+
+		assert_eq!((name, interface, version, id), (1, "wl_compositor", 6, 3));
+	}
+
+	#[test]
+	fn fd() {
+		let request = protocol::wl_shm::Request::CreatePool {
+			id: 4,
+			fd: 10,
+			size: 4096,
+		};
+
+		// fds travel out of band, so only the other arguments are encoded
+		let bytes = super::to_vec(&request).unwrap();
+		assert_eq!(bytes.len(), 8);
+
+		let mut fds = [10].into();
+		let decoded = protocol::wl_shm::Request::decode(request.op(), &bytes, &mut fds).unwrap();
+
+		let protocol::wl_shm::Request::CreatePool { id, fd, size } = decoded else {
+			panic!("decoded {decoded:?}");
+		};
+
+		assert_eq!((id, fd, size), (4, 10, 4096));
+		assert!(fds.is_empty());
+
+		assert!(matches!(
+			protocol::wl_shm::Request::decode(request.op(), &bytes, &mut fds),
+			Err(crate::error::Error::MissingFd)
+		));
+	}
+}
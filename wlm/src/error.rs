@@ -3,6 +3,33 @@ pub type Result<T> = std::result::Result<T, Error>;
 #[derive(Debug)]
 pub enum Error {
 	Message(String),
+	UnexpectedEof {
+		needed: usize,
+		remaining: usize,
+	},
+	TrailingData(usize),
+	InvalidUtf8(std::str::Utf8Error),
+	MissingNulTerminator,
+	UnexpectedNull,
+	MissingFd,
+	MessageTooLarge(usize),
+	UnknownOpcode {
+		interface: &'static str,
+		op: u16,
+	},
+	Unsupported(&'static str),
+	Io(std::io::Error),
+	Protocol {
+		object_id: u32,
+		code: u32,
+		message: String,
+	},
+}
+
+impl From<std::io::Error> for Error {
+	fn from(value: std::io::Error) -> Self {
+		Self::Io(value)
+	}
 }
 
 impl serde::ser::Error for Error {
@@ -24,15 +51,29 @@ impl serde::de::Error for Error {
 }
 
 impl std::fmt::Display for Error {
-	fn fmt(&self, _f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		todo!()
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Message(message) => f.write_str(message),
+			Self::UnexpectedEof { needed, remaining } => write!(
+				f,
+				"unexpected end of message, needed {needed} bytes but only {remaining} remain"
+			),
+			Self::TrailingData(len) => write!(f, "{len} bytes of trailing data after arguments"),
+			Self::InvalidUtf8(error) => write!(f, "string is not valid utf-8: {error}"),
+			Self::MissingNulTerminator => f.write_str("string is not nul terminated"),
+			Self::UnexpectedNull => f.write_str("null value for a non-nullable argument"),
+			Self::MissingFd => f.write_str("message did not come with enough fds"),
+			Self::MessageTooLarge(len) => write!(f, "message of {len} bytes is too large"),
+			Self::UnknownOpcode { interface, op } => write!(f, "unknown op '{op}' in {interface}"),
+			Self::Unsupported(what) => write!(f, "{what} is not a wayland wire type"),
+			Self::Io(error) => write!(f, "{error}"),
+			Self::Protocol {
+				object_id,
+				code,
+				message,
+			} => write!(f, "protocol error {code} on object {object_id}: {message}"),
+		}
 	}
 }
 
 impl std::error::Error for Error {}
-
-impl From<std::array::TryFromSliceError> for Error {
-	fn from(value: std::array::TryFromSliceError) -> Self {
-		Self::Message(value.to_string())
-	}
-}
@@ -0,0 +1,31 @@
+/// 24.8 signed fixed-point number, as used by the `fixed` wire type.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Fixed(pub i32);
+
+impl Fixed {
+	pub fn from_f64(value: f64) -> Self {
+		Self((value * 256.0) as i32)
+	}
+
+	pub fn to_f64(self) -> f64 {
+		self.0 as f64 / 256.0
+	}
+}
+
+impl From<i32> for Fixed {
+	fn from(value: i32) -> Self {
+		Self(value << 8)
+	}
+}
+
+impl serde::Serialize for Fixed {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_i32(self.0)
+	}
+}
+
+impl<'de> serde::Deserialize<'de> for Fixed {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		Ok(Self(i32::deserialize(deserializer)?))
+	}
+}
@@ -0,0 +1,36 @@
+pub trait Request<'a>: Sized + serde::Serialize + std::fmt::Display {
+	const INTERFACE: &'static str;
+
+	fn decode(
+		op: u16,
+		params: &'a [u8],
+		fds: &mut std::collections::VecDeque<std::os::fd::RawFd>,
+	) -> crate::error::Result<Self>;
+
+	fn op(&self) -> u16;
+	fn name(&self) -> &'static str;
+	fn since(&self) -> u32;
+
+	fn fds(&self) -> Vec<std::os::fd::RawFd> {
+		Vec::new()
+	}
+}
+
+pub trait Event: serde::Serialize + std::fmt::Display {
+	const INTERFACE: &'static str;
+	const NAME: &'static str;
+	const OP: u16;
+	const SINCE: u32;
+
+	fn fds(&self) -> Vec<std::os::fd::RawFd> {
+		Vec::new()
+	}
+
+	fn set_fds(
+		&mut self,
+		fds: &mut std::collections::VecDeque<std::os::fd::RawFd>,
+	) -> crate::error::Result<()> {
+		let _ = fds;
+		Ok(())
+	}
+}
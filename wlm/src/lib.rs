@@ -1,6 +1,12 @@
+pub mod client;
 pub mod decode;
 pub mod encode;
 pub mod error;
+mod fixed;
+mod interface;
 mod message;
+pub mod protocol;
 
+pub use fixed::*;
+pub use interface::*;
 pub use message::*;
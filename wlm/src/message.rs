@@ -1,5 +1,3 @@
-use std::io::Write as _;
-
 pub struct Message<T> {
 	pub object_id: u32,
 	pub op: u16,
@@ -7,15 +5,18 @@ pub struct Message<T> {
 }
 
 impl<T: serde::Serialize> Message<T> {
-	pub fn to_vec(&self) -> color_eyre::eyre::Result<Vec<u8>> {
+	pub fn to_vec(&self) -> crate::error::Result<Vec<u8>> {
 		let mut ret = Vec::new();
 
-		ret.write_all(&self.object_id.to_ne_bytes())?;
-		ret.write_all(&self.op.to_ne_bytes())?;
+		ret.extend(self.object_id.to_ne_bytes());
+		ret.extend(self.op.to_ne_bytes());
 
 		let args = crate::encode::to_vec(&self.args)?;
 
-		ret.write_all(&(8u16 + args.len() as u16).to_ne_bytes())?;
+		let size = u16::try_from(8 + args.len())
+			.map_err(|_| crate::error::Error::MessageTooLarge(8 + args.len()))?;
+
+		ret.extend(size.to_ne_bytes());
 		ret.extend(args);
 
 		Ok(ret)